@@ -0,0 +1,17 @@
+fn main() {
+    // Without this, cargo reruns the script whenever any file in the package changes
+    println!("cargo:rerun-if-changed=build.rs");
+
+    // The gRPC service of swarc-server is generated from its protobuf definition, using a
+    // bundled protoc so building does not depend on one being installed
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/swarc.proto");
+        let protoc = protoc_bin_vendored::protoc_bin_path().expect("no bundled protoc for this platform");
+        std::env::set_var("PROTOC", protoc);
+        tonic_build::configure()
+            .build_client(false)
+            .compile_protos(&["proto/swarc.proto"], &["proto"])
+            .expect("failed to compile proto/swarc.proto");
+    }
+}
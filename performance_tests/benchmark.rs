@@ -1,8 +1,26 @@
-use swarc::{HNSWIndex, Document};
-use rand::Rng;
+mod allocator;
+mod churn;
+mod config;
+mod datasets;
+
+use allocator::CountingAllocator;
+use churn::{run_churn, ChurnWindow};
+use config::{BenchmarkConfig, USAGE};
+use datasets::{inject_near_duplicates, uniform_vector, Generator};
+use swarc::{HNSWIndex, FlatIndex, Document, DistanceMetric};
+use swarc::npy::read_npy_mmap;
+use swarc::simd;
+use swarc::quantization::QuantizationRange;
+use swarc::precision::HalfFormat;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 use std::time::{Duration, Instant};
 use std::fs::File;
 use std::io::Write;
+use std::path::Path;
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
 
 #[derive(Debug, Clone)]
 struct BenchmarkResult {
@@ -12,15 +30,71 @@ struct BenchmarkResult {
     parallel_insertion_time: Duration,
     search_time: Duration,
     memory_usage: usize,
+    allocated_memory: usize, // heap growth while building the index, as counted by the allocator
+    resident_memory: Option<usize>, // growth of the resident set while building the index
+    recall: f64,
+    ef_sweep: Vec<EfPoint>,
 }
 
-fn generate_random_embedding(dimension: usize) -> Vec<f32> {
-    let mut rng = rand::thread_rng();
-    (0..dimension)
-        .map(|_| rng.gen_range(-1.0..1.0))
+/// Recall and throughput of the search at one `ef_search` setting
+#[derive(Debug, Clone)]
+struct EfPoint {
+    ef_search: usize,
+    recall: f64,
+    queries_per_second: f64,
+}
+
+/// Neighbors returned per query when measuring search time and recall
+const K: usize = 10;
+
+fn generate_random_embeddings(rng: &mut StdRng, count: usize, dimension: usize) -> Vec<Vec<f32>> {
+    (0..count)
+        .map(|_| uniform_vector(rng, dimension))
         .collect()
 }
 
+/// Draw vectors and queries from the configured distribution, with near-duplicates
+/// injected into the vectors only
+fn generate_dataset(config: &BenchmarkConfig, rng: &mut StdRng, count: usize, num_queries: usize) -> Dataset {
+    let generator = Generator::new(config.distribution(), config.dimension, rng);
+    let mut embeddings = generator.samples(rng, count);
+    let queries = generator.samples(rng, num_queries);
+    if config.duplicates > 0.0 {
+        let replaced = inject_near_duplicates(&mut embeddings, config.duplicates, config.duplicate_noise, rng);
+        println!("Replaced {} embeddings with near-duplicates", replaced);
+    }
+    (embeddings, queries)
+}
+
+/// Vectors to index and the queries run against them
+type Dataset = (Vec<Vec<f32>>, Vec<Vec<f32>>);
+
+/// Load the vectors to index and the queries, from the dataset file or generated
+///
+/// A dataset file must hold the largest size, the vectors inserted under churn and the
+/// queries; the queries are its last rows, so they are never part of the indexed vectors.
+fn load_vectors(config: &BenchmarkConfig, rng: &mut StdRng) -> Result<Dataset, String> {
+    let max_size = config.sizes.iter().copied().max().unwrap_or(0) + config.churn_headroom();
+    let max_queries = config.sizes.iter().map(|&size| config.queries_for(size)).max().unwrap_or(0);
+    
+    let Some(path) = &config.dataset else {
+        println!("Generating {} {:?} embeddings of dimension {}...", max_size, config.distribution(), config.dimension);
+        return Ok(generate_dataset(config, rng, max_size, max_queries));
+    };
+    
+    let matrix = read_npy_mmap(path)?;
+    if matrix.rows < max_size + max_queries {
+        return Err(format!(
+            "Dataset '{}' has {} rows, but {} vectors and {} queries were requested",
+            path.display(), matrix.rows, max_size, max_queries
+        ));
+    }
+    println!("Loaded {} vectors of dimension {} from {}", matrix.rows, matrix.cols, path.display());
+    let embeddings = (0..max_size).map(|i| matrix.row(i).to_vec()).collect();
+    let queries = (matrix.rows - max_queries..matrix.rows).map(|i| matrix.row(i).to_vec()).collect();
+    Ok((embeddings, queries))
+}
+
 fn benchmark_insertion(
     index: &mut HNSWIndex<String>,
     embeddings: &[Vec<f32>],
@@ -97,51 +171,221 @@ fn benchmark_search(
     start.elapsed()
 }
 
-fn estimate_memory_usage(index: &HNSWIndex<String>) -> usize {
-    // Rough estimation of memory usage
-    let num_nodes = index.len();
-    let dimension = 3072; // Fixed dimension for this benchmark
-    let avg_connections = 16; // Average connections per node
+/// Euclidean distance as computed before the SIMD kernels, kept as a baseline
+fn baseline_euclidean(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b.iter()).map(|(x, y)| (x - y).powi(2)).sum::<f32>().sqrt()
+}
+
+/// Cosine distance as computed before the SIMD kernels, kept as a baseline
+fn baseline_cosine(a: &[f32], b: &[f32]) -> f32 {
+    let dot_product: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
+    let norm_a: f32 = a.iter().map(|x| x.powi(2)).sum::<f32>().sqrt();
+    let norm_b: f32 = b.iter().map(|x| x.powi(2)).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        1.0 - (dot_product / (norm_a * norm_b))
+    }
+}
+
+/// Inner product distance as computed before the SIMD kernels, kept as a baseline
+fn baseline_inner_product(a: &[f32], b: &[f32]) -> f32 {
+    -a.iter().zip(b.iter()).map(|(x, y)| x * y).sum::<f32>()
+}
+
+type DistanceKernel = fn(&[f32], &[f32]) -> f32;
+
+fn time_kernel(vectors: &[Vec<f32>], kernel: impl Fn(&[f32], &[f32]) -> f32) -> Duration {
+    let start = Instant::now();
+    let mut checksum = 0.0;
+    for pair in vectors.windows(2) {
+        checksum += kernel(&pair[0], &pair[1]);
+    }
+    std::hint::black_box(checksum);
+    start.elapsed()
+}
+
+fn benchmark_distance_kernels(rng: &mut StdRng, dimension: usize, num_vectors: usize) {
+    println!("\n=== Distance kernels ({} dimensions, SIMD backend: {:?}) ===", dimension, simd::backend());
     
-    // Memory for embeddings + connections + overhead
-    num_nodes * (dimension * 4 + avg_connections * 4 + 100) // 4 bytes per f32, 4 bytes per usize, 100 bytes overhead
+    let vectors = generate_random_embeddings(rng, num_vectors, dimension);
+    
+    let kernels: [(&str, DistanceKernel, DistanceKernel); 3] = [
+        ("euclidean", baseline_euclidean, |a, b| simd::l2_squared(a, b).sqrt()),
+        ("cosine", baseline_cosine, simd::cosine_distance),
+        ("inner_product", baseline_inner_product, |a, b| -simd::dot(a, b)),
+    ];
+    
+    for (name, baseline, accelerated) in kernels {
+        let baseline_time = time_kernel(&vectors, baseline);
+        let accelerated_time = time_kernel(&vectors, accelerated);
+        let calls = (num_vectors - 1) as f64;
+        println!(
+            "  {:<14} scalar: {:>8.1}ns/call  simd: {:>8.1}ns/call  speedup: {:.2}x",
+            name,
+            baseline_time.as_nanos() as f64 / calls,
+            accelerated_time.as_nanos() as f64 / calls,
+            baseline_time.as_secs_f64() / accelerated_time.as_secs_f64()
+        );
+    }
+}
+
+/// Exact top-k ids of each query, found by brute force with a `FlatIndex`
+fn ground_truth(embeddings: &[Vec<f32>], queries: &[Vec<f32>], k: usize, metric: DistanceMetric) -> Vec<Vec<String>> {
+    let mut exact: FlatIndex<()> = FlatIndex::new_with_distance(embeddings[0].len(), metric);
+    let items = embeddings
+        .iter()
+        .enumerate()
+        .map(|(i, embedding)| (format!("node_{}", i), embedding.clone(), None))
+        .collect();
+    exact.insert_parallel(items).expect("Failed to build exact index");
+    
+    queries
+        .iter()
+        .map(|query| exact.search(query, k).into_iter().map(|(id, _, _)| id).collect())
+        .collect()
+}
+
+/// Average fraction of the exact top-k found by the index for each query
+fn measure_recall(index: &HNSWIndex<String>, queries: &[Vec<f32>], truth: &[Vec<String>], k: usize) -> f64 {
+    let hits: usize = queries
+        .iter()
+        .zip(truth)
+        .map(|(query, expected)| index.search(query, k).iter().filter(|(id, _, _)| expected.contains(id)).count())
+        .sum();
+    hits as f64 / (queries.len() * k) as f64
+}
+
+/// Measure recall and queries per second at each `ef_search` value, restoring the default after
+fn benchmark_ef_sweep(index: &mut HNSWIndex<String>, queries: &[Vec<f32>], truth: &[Vec<String>], ef_search: &[usize]) -> Vec<EfPoint> {
+    let points = ef_search
+        .iter()
+        .map(|&ef_search| {
+            index.ef_search = Some(ef_search);
+            let search_time = benchmark_search(index, queries, K);
+            EfPoint {
+                ef_search,
+                recall: measure_recall(index, queries, truth, K),
+                queries_per_second: queries.len() as f64 / search_time.as_secs_f64(),
+            }
+        })
+        .collect();
+    index.ef_search = None;
+    points
+}
+
+fn benchmark_quantization(rng: &mut StdRng, config: &BenchmarkConfig, num_embeddings: usize) {
+    let dimension = config.dimension;
+    println!("\n=== Quantization ({} embeddings of dimension {}, {:?}) ===", num_embeddings, dimension, config.metric);
+    
+    let (embeddings, queries) = generate_dataset(config, rng, num_embeddings, 100);
+    
+    // Storage modes cannot be combined, so each quantizer gets a freshly built index
+    let build = || {
+        let mut index = HNSWIndex::new_with_distance(dimension, config.m, config.ef_construction, config.metric);
+        benchmark_parallel_insertion(&mut index, &embeddings, num_embeddings);
+        index
+    };
+    let mut index = build();
+    let truth = ground_truth(&embeddings, &queries, K, config.metric);
+    
+    // Vector memory as the index measures it: kept vectors, codes and trained tables
+    let report = |label: &str, index: &HNSWIndex<String>| {
+        let usage = index.memory_usage();
+        let search_time = benchmark_search(index, &queries, K);
+        println!(
+            "  {:<22} vectors: {:>8.2}MB  recall@{}: {:.3}  search: {:.2}ms/query",
+            label,
+            (usage.vectors + usage.quantized) as f64 / (1024.0 * 1024.0),
+            K,
+            measure_recall(index, &queries, &truth, K),
+            search_time.as_secs_f64() * 1000.0 / queries.len() as f64
+        );
+    };
+    
+    report("f32", &index);
+    
+    index.set_half_precision(Some(HalfFormat::F16)).expect("Failed to convert vectors");
+    report("f16", &index);
+    
+    index.set_half_precision(Some(HalfFormat::BF16)).expect("Failed to convert vectors");
+    report("bf16", &index);
+    index.set_half_precision(None).expect("Failed to convert vectors");
+    
+    index.quantize_int8(QuantizationRange::PerDimension, 1000, true).expect("Failed to quantize index");
+    report("int8", &index);
+    
+    index.rerank_candidates = Some(100);
+    report("int8 + re-rank (100)", &index);
+    
+    let subspaces = (1..=64).rev().find(|&s| dimension.is_multiple_of(s)).unwrap_or(1);
+    let mut index = build();
+    index.quantize_pq(subspaces, 256, num_embeddings, 10, true).expect("Failed to quantize index");
+    report(&format!("pq ({} bytes)", subspaces), &index);
+    
+    index.rerank_candidates = Some(100);
+    report("pq + re-rank (100)", &index);
+    
+    let mut index = build();
+    index.quantize_binary(true).expect("Failed to quantize index");
+    report("binary + rescore top k", &index);
+    
+    index.rerank_candidates = Some(100);
+    report("binary + re-rank (100)", &index);
 }
 
 fn run_benchmark(
-    num_embeddings: usize,
-    dimension: usize,
+    config: &BenchmarkConfig,
+    embeddings: &[Vec<f32>],
+    query_embeddings: &[Vec<f32>],
     batch_size: usize,
 ) -> BenchmarkResult {
+    let num_embeddings = embeddings.len();
+    let dimension = embeddings[0].len();
     println!("Starting benchmark with {} embeddings of dimension {}", num_embeddings, dimension);
     
-    // Generate embeddings
-    println!("Generating {} random embeddings...", num_embeddings);
-    let embeddings: Vec<Vec<f32>> = (0..num_embeddings)
-        .map(|_| generate_random_embedding(dimension))
-        .collect();
-    
-    // Generate query embeddings (10% of total)
-    let num_queries = (num_embeddings / 10).max(100);
-    let query_embeddings: Vec<Vec<f32>> = (0..num_queries)
-        .map(|_| generate_random_embedding(dimension))
-        .collect();
-    
     // Benchmark sequential insertion
     println!("Starting sequential insertion benchmark...");
-    let mut index_seq = HNSWIndex::new(dimension, 16, 200);
-    let insertion_time = benchmark_insertion(&mut index_seq, &embeddings, batch_size);
+    let mut index_seq = HNSWIndex::new_with_distance(dimension, config.m, config.ef_construction, config.metric);
+    let insertion_time = benchmark_insertion(&mut index_seq, embeddings, batch_size);
+    drop(index_seq);
     
-    // Benchmark parallel insertion
+    // Benchmark parallel insertion, sampling the process's real memory around the build
     println!("Starting parallel insertion benchmark...");
-    let mut index_parallel = HNSWIndex::new(dimension, 16, 200);
-    let parallel_insertion_time = benchmark_parallel_insertion(&mut index_parallel, &embeddings, batch_size);
+    let allocated_before = allocator::allocated();
+    let resident_before = allocator::resident();
+    let mut index_parallel = HNSWIndex::new_with_distance(dimension, config.m, config.ef_construction, config.metric);
+    let parallel_insertion_time = benchmark_parallel_insertion(&mut index_parallel, embeddings, batch_size);
+    let allocated_memory = allocator::allocated().saturating_sub(allocated_before);
+    let resident_memory = allocator::resident().zip(resident_before).map(|(after, before)| after.saturating_sub(before));
     
     // Benchmark search (using parallel index)
     println!("Starting search benchmark...");
-    let search_time = benchmark_search(&index_parallel, &query_embeddings, 10);
+    let search_time = benchmark_search(&index_parallel, query_embeddings, K);
+    
+    // Measure recall against exact neighbors, then trade it against speed with ef_search
+    println!("Computing exact neighbors for recall...");
+    let truth = ground_truth(embeddings, query_embeddings, K, config.metric);
+    let recall = measure_recall(&index_parallel, query_embeddings, &truth, K);
+    let ef_sweep = benchmark_ef_sweep(&mut index_parallel, query_embeddings, &truth, &config.ef_search);
     
-    // Estimate memory usage
-    let memory_usage = estimate_memory_usage(&index_parallel);
+    // Measure memory usage
+    let memory = index_parallel.memory_usage();
+    println!(
+        "Memory: vectors {:.2}MB, adjacency {:.2}MB over {} layers, nodes {:.2}MB, id map {:.2}MB, documents {:.2}MB",
+        memory.vectors as f64 / (1024.0 * 1024.0),
+        memory.adjacency() as f64 / (1024.0 * 1024.0),
+        memory.adjacency.len(),
+        memory.nodes as f64 / (1024.0 * 1024.0),
+        memory.id_map as f64 / (1024.0 * 1024.0),
+        memory.documents as f64 / (1024.0 * 1024.0)
+    );
+    let memory_usage = memory.total();
+    println!(
+        "Memory while building: {:.2}MB allocated, {} resident",
+        allocated_memory as f64 / (1024.0 * 1024.0),
+        resident_memory.map_or("unknown".to_string(), |bytes| format!("{:.2}MB", bytes as f64 / (1024.0 * 1024.0)))
+    );
     
     BenchmarkResult {
         num_embeddings,
@@ -150,49 +394,156 @@ fn run_benchmark(
         parallel_insertion_time,
         search_time,
         memory_usage,
+        allocated_memory,
+        resident_memory,
+        recall,
+        ef_sweep,
     }
 }
 
-fn save_results_to_csv(results: &[BenchmarkResult], filename: &str) {
+fn save_results_to_csv(results: &[BenchmarkResult], filename: &Path, sweep_filename: &Path) {
     let mut file = File::create(filename).expect("Failed to create CSV file");
     
     // Write header
-    writeln!(file, "num_embeddings,dimension,insertion_time_ms,parallel_insertion_time_ms,search_time_ms,memory_usage_mb").unwrap();
+    writeln!(file, "num_embeddings,dimension,insertion_time_ms,parallel_insertion_time_ms,search_time_ms,memory_usage_mb,allocated_memory_mb,resident_memory_mb,recall_at_{}", K).unwrap();
     
     // Write data
     for result in results {
+        // Resident memory is left empty where the platform does not report it
+        let resident = result.resident_memory.map_or(String::new(), |bytes| format!("{:.3}", bytes as f64 / (1024.0 * 1024.0)));
         writeln!(
             file,
-            "{},{},{},{},{},{}",
+            "{},{},{:.3},{:.3},{:.3},{:.3},{:.3},{},{:.4}",
             result.num_embeddings,
             result.dimension,
-            result.insertion_time.as_millis(),
-            result.parallel_insertion_time.as_millis(),
-            result.search_time.as_millis(),
-            result.memory_usage / (1024 * 1024) // Convert to MB
+            result.insertion_time.as_secs_f64() * 1000.0,
+            result.parallel_insertion_time.as_secs_f64() * 1000.0,
+            result.search_time.as_secs_f64() * 1000.0,
+            result.memory_usage as f64 / (1024.0 * 1024.0), // Convert to MB
+            result.allocated_memory as f64 / (1024.0 * 1024.0),
+            resident,
+            result.recall
         ).unwrap();
     }
     
-    println!("Results saved to {}", filename);
+    println!("Results saved to {}", filename.display());
+    
+    let mut file = File::create(sweep_filename).expect("Failed to create CSV file");
+    writeln!(file, "num_embeddings,ef_search,recall_at_{},queries_per_second", K).unwrap();
+    for result in results {
+        for point in &result.ef_sweep {
+            writeln!(
+                file,
+                "{},{},{:.4},{:.1}",
+                result.num_embeddings,
+                point.ef_search,
+                point.recall,
+                point.queries_per_second
+            ).unwrap();
+        }
+    }
+    
+    println!("Recall sweep saved to {}", sweep_filename.display());
+}
+
+/// Record the settings of the run, and the commit it was built from when git knows it
+fn save_parameters(config: &BenchmarkConfig, dimension: usize, filename: &Path) {
+    let mut file = File::create(filename).expect("Failed to create CSV file");
+    writeln!(file, "parameter,value").unwrap();
+    let commit = std::process::Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string());
+    if let Some(commit) = commit {
+        writeln!(file, "commit,{}", commit).unwrap();
+    }
+    for (name, value) in config.parameters(dimension) {
+        // Values are free text, so commas would split them into extra fields
+        writeln!(file, "{},{}", name, value.replace(',', ";")).unwrap();
+    }
+}
+
+fn save_churn_to_csv(results: &[(usize, Vec<ChurnWindow>)], filename: &Path) {
+    let mut file = File::create(filename).expect("Failed to create CSV file");
+    writeln!(file, "num_embeddings,operations,live_embeddings,inserts_per_second,removes_per_second,searches_per_second,recall_at_{}", K).unwrap();
+    for (size, windows) in results {
+        for window in windows {
+            writeln!(
+                file,
+                "{},{},{},{:.1},{:.1},{:.1},{:.4}",
+                size,
+                window.operations,
+                window.live,
+                window.inserts_per_second,
+                window.removes_per_second,
+                window.searches_per_second,
+                window.recall
+            ).unwrap();
+        }
+    }
+    
+    println!("Churn results saved to {}", filename.display());
 }
 
 fn main() {
-    let dimension = 3072;
+    let config = match BenchmarkConfig::parse(std::env::args().skip(1)) {
+        Ok(Some(config)) => config,
+        Ok(None) => {
+            print!("{}", USAGE);
+            return;
+        }
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
     let batch_size = 1000; // Print progress every 1k insertions
     
-    // Test different dataset sizes
-    let test_sizes = vec![
-        1000,      // 1K
-        10000,     // 10K
-        50000,     // 50K
-    ];
+    if let Some(threads) = config.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+            .expect("Failed to configure the thread pool");
+    }
+    println!("Seed: {} (pass --seed {} to reproduce the data)", config.seed, config.seed);
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    
+    let (embeddings, queries) = match load_vectors(&config, &mut rng) {
+        Ok(vectors) => vectors,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    
+    if config.extras {
+        benchmark_distance_kernels(&mut rng, config.dimension, 20000);
+        benchmark_quantization(&mut rng, &config, 2000);
+    }
+    
+    std::fs::create_dir_all(&config.output).expect("Failed to create output directory");
+    save_parameters(&config, embeddings[0].len(), &config.output.join("parameters.csv"));
+    
+    if config.churn.is_some() {
+        let mut results = Vec::new();
+        for &size in &config.sizes {
+            println!("\n=== Churn on {} embeddings ===", size);
+            let windows = run_churn(&config, &embeddings, size, &queries[..config.queries_for(size)], &mut rng);
+            results.push((size, windows));
+        }
+        save_churn_to_csv(&results, &config.output.join("churn.csv"));
+        println!("\n=== All benchmarks completed ===");
+        return;
+    }
     
     let mut results = Vec::new();
     
-    for &size in &test_sizes {
+    for &size in &config.sizes {
         println!("\n=== Benchmarking {} embeddings ===", size);
         
-        let result = run_benchmark(size, dimension, batch_size);
+        let result = run_benchmark(&config, &embeddings[..size], &queries[..config.queries_for(size)], batch_size);
         
         // Print summary
         println!("\nBenchmark Summary:");
@@ -200,8 +551,12 @@ fn main() {
         println!("  Sequential insertion time: {:.2}s", result.insertion_time.as_secs_f64());
         println!("  Parallel insertion time: {:.2}s", result.parallel_insertion_time.as_secs_f64());
         println!("  Speedup: {:.2}x", result.insertion_time.as_secs_f64() / result.parallel_insertion_time.as_secs_f64());
-        println!("  Search time: {:.2}ms", result.search_time.as_millis());
-        println!("  Memory usage: {:.2}MB", result.memory_usage as f64 / (1024.0 * 1024.0));
+        println!("  Search time: {:.2}ms", result.search_time.as_secs_f64() * 1000.0);
+        println!("  Memory usage: {:.2}MB estimated, {:.2}MB allocated", result.memory_usage as f64 / (1024.0 * 1024.0), result.allocated_memory as f64 / (1024.0 * 1024.0));
+        println!("  Recall@{}: {:.3}", K, result.recall);
+        for point in &result.ef_sweep {
+            println!("    ef_search {:>4}: recall {:.3}, {:.0} queries/sec", point.ef_search, point.recall, point.queries_per_second);
+        }
         println!("  Sequential insertions/sec: {:.0}", size as f64 / result.insertion_time.as_secs_f64());
         println!("  Parallel insertions/sec: {:.0}", size as f64 / result.parallel_insertion_time.as_secs_f64());
         
@@ -209,8 +564,9 @@ fn main() {
     }
     
     // Save results to CSV
-    save_results_to_csv(&results, "performance_tests/benchmark_results.csv");
+    let results_path = config.output.join("benchmark_results.csv");
+    save_results_to_csv(&results, &results_path, &config.output.join("recall_qps.csv"));
     
     println!("\n=== All benchmarks completed ===");
-    println!("Results saved to performance_tests/benchmark_results.csv");
+    println!("Results saved to {}", results_path.display());
 }
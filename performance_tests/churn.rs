@@ -0,0 +1,174 @@
+use rand::rngs::StdRng;
+use rand::Rng;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use swarc::{Document, FlatIndex, HNSWIndex};
+use crate::config::BenchmarkConfig;
+use crate::{benchmark_parallel_insertion, K};
+
+/// Relative weights of the operations in a churn workload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OperationMix {
+    pub insert: u32,
+    pub remove: u32,
+    pub search: u32,
+}
+
+impl OperationMix {
+    /// Expected share of operations that are insertions
+    pub fn insert_share(&self) -> f64 {
+        self.insert as f64 / (self.insert + self.remove + self.search) as f64
+    }
+}
+
+impl FromStr for OperationMix {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let weights: Vec<u32> = s
+            .split(':')
+            .map(|w| w.trim().parse().map_err(|_| format!("Invalid weight '{}' in operation mix '{}'", w, s)))
+            .collect::<Result<_, _>>()?;
+        match weights[..] {
+            [insert, remove, search] if insert + remove + search > 0 => Ok(OperationMix { insert, remove, search }),
+            [_, _, _] => Err(format!("Operation mix '{}' has no operations", s)),
+            _ => Err(format!("Operation mix '{}' must be insert:remove:search weights", s)),
+        }
+    }
+}
+
+/// Throughput of each operation and recall over one stretch of a churn run
+#[derive(Debug, Clone)]
+pub struct ChurnWindow {
+    pub operations: usize, // operations run since the start, at the end of the window
+    pub live: usize,
+    pub inserts_per_second: f64,
+    pub removes_per_second: f64,
+    pub searches_per_second: f64,
+    pub recall: f64,
+}
+
+/// Operations run and time spent on one kind of operation within a window
+#[derive(Default)]
+struct Tally {
+    count: usize,
+    time: Duration,
+}
+
+impl Tally {
+    fn record(&mut self, start: Instant) {
+        self.count += 1;
+        self.time += start.elapsed();
+    }
+
+    fn per_second(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.count as f64 / self.time.as_secs_f64()
+        }
+    }
+}
+
+/// Build an index from the first `initial` vectors of `pool`, then run `config.churn`
+/// random inserts, removes and searches in the proportions of `config.churn_mix`
+///
+/// Inserts draw vectors from the rest of the pool and from removed nodes. Recall@K over
+/// `queries` is measured against a `FlatIndex` kept in step with the index, before the
+/// first operation and after every `config.churn_interval()` operations.
+pub fn run_churn(config: &BenchmarkConfig, pool: &[Vec<f32>], initial: usize, queries: &[Vec<f32>], rng: &mut StdRng) -> Vec<ChurnWindow> {
+    let operations = config.churn.unwrap_or(0);
+    let interval = config.churn_interval();
+    let mix = config.churn_mix;
+    let dimension = pool[0].len();
+    println!("Starting churn benchmark: {} operations on {} embeddings, mix {}:{}:{}", operations, initial, mix.insert, mix.remove, mix.search);
+
+    let mut index = HNSWIndex::new_with_distance(dimension, config.m, config.ef_construction, config.metric);
+    benchmark_parallel_insertion(&mut index, &pool[..initial], initial);
+    let mut exact: FlatIndex<()> = FlatIndex::new_with_distance(dimension, config.metric);
+    let items = (0..initial).map(|i| (format!("node_{}", i), pool[i].clone(), None)).collect();
+    exact.insert_parallel(items).expect("Failed to build exact index");
+
+    // Positions in the pool of the vectors in the index, and of those free to insert
+    let mut live: Vec<usize> = (0..initial).collect();
+    let mut free: Vec<usize> = (initial..pool.len()).collect();
+
+    let mut windows = vec![ChurnWindow {
+        operations: 0,
+        live: live.len(),
+        inserts_per_second: 0.0,
+        removes_per_second: 0.0,
+        searches_per_second: 0.0,
+        recall: churn_recall(&index, &exact, queries),
+    }];
+    println!("  Initial recall@{}: {:.3}", K, windows[0].recall);
+
+    let total_weight = mix.insert + mix.remove + mix.search;
+    let (mut inserts, mut removes, mut searches) = (Tally::default(), Tally::default(), Tally::default());
+    for op in 1..=operations {
+        let draw = rng.gen_range(0..total_weight);
+        // An insert with nothing left to insert, or a remove from an empty index, searches instead
+        if draw < mix.insert && !free.is_empty() {
+            let slot = free.swap_remove(rng.gen_range(0..free.len()));
+            let id = format!("node_{}", slot);
+            let doc = Document {
+                id: format!("doc_{}", slot),
+                data: format!("Content for document {}", slot),
+            };
+            let start = Instant::now();
+            index.insert(id.clone(), pool[slot].clone(), Some(doc)).expect("Failed to insert embedding");
+            inserts.record(start);
+            exact.insert(id, pool[slot].clone(), None).expect("Failed to insert embedding");
+            live.push(slot);
+        } else if draw >= mix.insert && draw < mix.insert + mix.remove && !live.is_empty() {
+            let slot = live.swap_remove(rng.gen_range(0..live.len()));
+            let id = format!("node_{}", slot);
+            let start = Instant::now();
+            index.remove(&id).expect("Failed to remove embedding");
+            removes.record(start);
+            exact.remove(&id).expect("Failed to remove embedding");
+            free.push(slot);
+        } else {
+            let query = &queries[rng.gen_range(0..queries.len())];
+            let start = Instant::now();
+            std::hint::black_box(index.search(query, K));
+            searches.record(start);
+        }
+
+        if op % interval == 0 || op == operations {
+            let window = ChurnWindow {
+                operations: op,
+                live: live.len(),
+                inserts_per_second: inserts.per_second(),
+                removes_per_second: removes.per_second(),
+                searches_per_second: searches.per_second(),
+                recall: churn_recall(&index, &exact, queries),
+            };
+            println!(
+                "  After {} operations ({} live): {:.0} inserts/sec, {:.0} removes/sec, {:.0} searches/sec, recall@{} {:.3}",
+                window.operations, window.live, window.inserts_per_second, window.removes_per_second,
+                window.searches_per_second, K, window.recall
+            );
+            windows.push(window);
+            (inserts, removes, searches) = (Tally::default(), Tally::default(), Tally::default());
+        }
+    }
+
+    windows
+}
+
+/// Average fraction of the exact top-K of each query found by the index
+fn churn_recall(index: &HNSWIndex<String>, exact: &FlatIndex<()>, queries: &[Vec<f32>]) -> f64 {
+    let k = K.min(exact.len());
+    if k == 0 {
+        return 1.0;
+    }
+    let hits: usize = queries
+        .iter()
+        .map(|query| {
+            let expected: Vec<String> = exact.search(query, k).into_iter().map(|(id, _, _)| id).collect();
+            index.search(query, k).iter().filter(|(id, _, _)| expected.contains(id)).count()
+        })
+        .sum();
+    hits as f64 / (queries.len() * k) as f64
+}
@@ -0,0 +1,241 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+use swarc::DistanceMetric;
+use crate::churn::OperationMix;
+use crate::datasets::{Distribution, DistributionKind};
+
+pub const USAGE: &str = "\
+Usage: benchmark [OPTIONS]
+
+Options:
+  --sizes <N,N,...>          Dataset sizes to benchmark [default: 1000,10000,50000]
+  --dimension <N>            Vector dimension, ignored with --dataset [default: 3072]
+  --m <N>                    Maximum connections per node [default: 16]
+  --ef-construction <N>      Candidate list size while building [default: 200]
+  --ef-search <N,N,...>      ef_search values swept for recall vs QPS [default: 10,20,40,80,160,320]
+  --metric <NAME>            euclidean, cosine, inner_product, manhattan, chebyshev or minkowski:<p> [default: euclidean]
+  --queries <N>              Queries per dataset size [default: a tenth of the size, at least 100]
+  --threads <N>              Worker threads for parallel insertion and exact search [default: all cores]
+  --dataset <FILE.npy>       Benchmark rows of a .npy file; queries are taken from its last rows
+  --seed <N>                 Seed for generated vectors [default: random]
+  --distribution <NAME>      Generated vectors: uniform, gaussian-mixture or low-rank [default: uniform]
+  --clusters <N>             Clusters of a Gaussian mixture [default: 32]
+  --spread <F>               Standard deviation of each cluster [default: 0.1]
+  --intrinsic-dimension <N>  Dimension of the subspace of low-rank vectors [default: 16]
+  --noise <F>                Standard deviation of the noise added to low-rank vectors [default: 0.01]
+  --duplicates <F>           Fraction of vectors replaced by near-duplicates of others [default: 0]
+  --duplicate-noise <F>      Standard deviation of the noise on near-duplicates [default: 0.001]
+  --churn <N>                Run N interleaved inserts, removes and searches per size instead of the bulk benchmark
+  --churn-mix <I:R:S>        Relative weights of inserts, removes and searches under churn [default: 1:1:8]
+  --churn-interval <N>       Operations between churn recall measurements [default: a twentieth of --churn]
+  --output <DIR>             Directory for the CSV results [default: performance_tests]
+  --extras                   Also benchmark the distance kernels and quantization
+  -h, --help                 Print this help
+";
+
+/// Flags that take a value
+const VALUE_FLAGS: [&str; 21] = [
+    "--sizes", "--dimension", "--m", "--ef-construction", "--ef-search", "--metric",
+    "--queries", "--threads", "--dataset", "--seed", "--output", "--distribution",
+    "--clusters", "--spread", "--intrinsic-dimension", "--noise", "--duplicates",
+    "--duplicate-noise", "--churn", "--churn-mix", "--churn-interval",
+];
+
+/// Benchmark settings, read from the command line
+#[derive(Debug, Clone)]
+pub struct BenchmarkConfig {
+    pub sizes: Vec<usize>,
+    pub dimension: usize,
+    pub m: usize,
+    pub ef_construction: usize,
+    pub ef_search: Vec<usize>,
+    pub metric: DistanceMetric,
+    pub queries: Option<usize>,
+    pub threads: Option<usize>,
+    pub dataset: Option<PathBuf>,
+    pub seed: u64,
+    pub distribution: DistributionKind,
+    pub clusters: usize,
+    pub spread: f32,
+    pub intrinsic_dimension: usize,
+    pub noise: f32,
+    pub duplicates: f32,
+    pub duplicate_noise: f32,
+    pub churn: Option<usize>,
+    pub churn_mix: OperationMix,
+    pub churn_interval: Option<usize>,
+    pub output: PathBuf,
+    pub extras: bool,
+}
+
+impl Default for BenchmarkConfig {
+    fn default() -> Self {
+        Self {
+            sizes: vec![1000, 10000, 50000],
+            dimension: 3072,
+            m: 16,
+            ef_construction: 200,
+            ef_search: vec![10, 20, 40, 80, 160, 320],
+            metric: DistanceMetric::Euclidean,
+            queries: None,
+            threads: None,
+            dataset: None,
+            seed: rand::random(),
+            distribution: DistributionKind::Uniform,
+            clusters: 32,
+            spread: 0.1,
+            intrinsic_dimension: 16,
+            noise: 0.01,
+            duplicates: 0.0,
+            duplicate_noise: 0.001,
+            churn: None,
+            churn_mix: OperationMix { insert: 1, remove: 1, search: 8 },
+            churn_interval: None,
+            output: PathBuf::from("performance_tests"),
+            extras: false,
+        }
+    }
+}
+
+impl BenchmarkConfig {
+    /// Parse flags, not including the program name
+    ///
+    /// Returns `Ok(None)` when help was requested.
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Option<Self>, String> {
+        let mut config = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(flag) = args.next() {
+            // Both `--flag value` and `--flag=value` are accepted
+            let (flag, inline) = match flag.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (flag, None),
+            };
+            if flag == "-h" || flag == "--help" {
+                return Ok(None);
+            }
+            if flag == "--extras" {
+                config.extras = true;
+                continue;
+            }
+
+            if !VALUE_FLAGS.contains(&flag.as_str()) {
+                return Err(format!("Unknown option '{}'", flag));
+            }
+            let value = inline
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("Missing value for {}", flag))?;
+            match flag.as_str() {
+                "--sizes" => config.sizes = parse_list(&flag, &value)?,
+                "--dimension" => config.dimension = parse_positive(&flag, &value)?,
+                "--m" => config.m = parse_positive(&flag, &value)?,
+                "--ef-construction" => config.ef_construction = parse_positive(&flag, &value)?,
+                "--ef-search" => config.ef_search = parse_list(&flag, &value)?,
+                "--metric" => config.metric = value.parse()?,
+                "--queries" => config.queries = Some(parse_positive(&flag, &value)?),
+                "--threads" => config.threads = Some(parse_positive(&flag, &value)?),
+                "--dataset" => config.dataset = Some(PathBuf::from(value)),
+                "--seed" => config.seed = parse(&flag, &value)?,
+                "--output" => config.output = PathBuf::from(value),
+                "--distribution" => config.distribution = value.parse()?,
+                "--clusters" => config.clusters = parse_positive(&flag, &value)?,
+                "--spread" => config.spread = parse_non_negative(&flag, &value)?,
+                "--intrinsic-dimension" => config.intrinsic_dimension = parse_positive(&flag, &value)?,
+                "--noise" => config.noise = parse_non_negative(&flag, &value)?,
+                "--duplicates" => config.duplicates = parse_non_negative(&flag, &value)?,
+                "--duplicate-noise" => config.duplicate_noise = parse_non_negative(&flag, &value)?,
+                "--churn" => config.churn = Some(parse_positive(&flag, &value)?),
+                "--churn-mix" => config.churn_mix = value.parse()?,
+                "--churn-interval" => config.churn_interval = Some(parse_positive(&flag, &value)?),
+                _ => unreachable!(),
+            }
+        }
+
+        if config.duplicates > 1.0 {
+            return Err("--duplicates must be a fraction between 0 and 1".to_string());
+        }
+        if config.distribution == DistributionKind::LowRank && config.intrinsic_dimension > config.dimension {
+            return Err("--intrinsic-dimension must not exceed --dimension".to_string());
+        }
+
+        Ok(Some(config))
+    }
+
+    /// The distribution generated vectors are drawn from
+    pub fn distribution(&self) -> Distribution {
+        match self.distribution {
+            DistributionKind::Uniform => Distribution::Uniform,
+            DistributionKind::GaussianMixture => Distribution::GaussianMixture { clusters: self.clusters, spread: self.spread },
+            DistributionKind::LowRank => Distribution::LowRank { intrinsic_dimension: self.intrinsic_dimension, noise: self.noise },
+        }
+    }
+
+    /// Settings that shape the results, recorded next to them so reports can compare runs
+    ///
+    /// `dimension` is that of the benchmarked vectors, which a dataset file decides.
+    pub fn parameters(&self, dimension: usize) -> Vec<(&'static str, String)> {
+        let list = |values: &[usize]| values.iter().map(usize::to_string).collect::<Vec<_>>().join(" ");
+        let mut parameters = vec![
+            ("sizes", list(&self.sizes)),
+            ("dimension", dimension.to_string()),
+            ("m", self.m.to_string()),
+            ("ef_construction", self.ef_construction.to_string()),
+            ("ef_search", list(&self.ef_search)),
+            ("metric", format!("{:?}", self.metric)),
+            ("queries", self.queries.map_or("default".to_string(), |q| q.to_string())),
+            ("threads", self.threads.unwrap_or_else(rayon::current_num_threads).to_string()),
+        ];
+        match &self.dataset {
+            Some(path) => parameters.push(("dataset", path.display().to_string())),
+            None => {
+                parameters.push(("distribution", format!("{:?}", self.distribution())));
+                parameters.push(("duplicates", self.duplicates.to_string()));
+                parameters.push(("seed", self.seed.to_string()));
+            }
+        }
+        if let Some(churn) = self.churn {
+            let mix = self.churn_mix;
+            parameters.push(("churn", churn.to_string()));
+            parameters.push(("churn_mix", format!("{}:{}:{}", mix.insert, mix.remove, mix.search)));
+        }
+        parameters
+    }
+
+    /// Operations between recall measurements of a churn run
+    pub fn churn_interval(&self) -> usize {
+        self.churn_interval.unwrap_or((self.churn.unwrap_or(0) / 20).max(1))
+    }
+
+    /// Vectors needed beyond the largest size so churn inserts are usually fresh vectors
+    pub fn churn_headroom(&self) -> usize {
+        let operations = self.churn.unwrap_or(0) as f64;
+        (operations * self.churn_mix.insert_share()).ceil() as usize
+    }
+
+    /// Number of queries run against a dataset of the given size
+    pub fn queries_for(&self, size: usize) -> usize {
+        self.queries.unwrap_or((size / 10).max(100))
+    }
+}
+
+fn parse<T: FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("Invalid value '{}' for {}", value, flag))
+}
+
+fn parse_positive(flag: &str, value: &str) -> Result<usize, String> {
+    match parse(flag, value)? {
+        0 => Err(format!("{} must be greater than zero", flag)),
+        n => Ok(n),
+    }
+}
+
+fn parse_non_negative(flag: &str, value: &str) -> Result<f32, String> {
+    match parse::<f32>(flag, value)? {
+        x if x >= 0.0 && x.is_finite() => Ok(x),
+        _ => Err(format!("{} must be a non-negative number", flag)),
+    }
+}
+
+fn parse_list(flag: &str, value: &str) -> Result<Vec<usize>, String> {
+    value.split(',').map(|item| parse_positive(flag, item.trim())).collect()
+}
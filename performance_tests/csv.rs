@@ -0,0 +1,30 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// Column names of a CSV file and the fields of each row
+pub type CsvTable = (Vec<String>, Vec<Vec<String>>);
+
+/// Read a CSV file into its header and rows of fields
+pub fn read_csv(filename: &Path) -> Result<CsvTable, Box<dyn std::error::Error>> {
+    let file = File::open(filename)?;
+    let mut lines = BufReader::new(file).lines();
+    let header = match lines.next() {
+        Some(line) => line?.split(',').map(str::to_string).collect(),
+        None => return Ok((Vec::new(), Vec::new())),
+    };
+    
+    let mut rows = Vec::new();
+    for line in lines {
+        rows.push(line?.split(',').map(str::to_string).collect());
+    }
+    Ok((header, rows))
+}
+
+/// Position of a column, found by the start of its name so `recall_at_10` matches `recall_at`
+pub fn column(header: &[String], name: &str) -> Result<usize, Box<dyn std::error::Error>> {
+    header
+        .iter()
+        .position(|h| h.starts_with(name))
+        .ok_or_else(|| format!("Missing column '{}'", name).into())
+}
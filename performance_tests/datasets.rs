@@ -0,0 +1,117 @@
+use rand::rngs::StdRng;
+use rand::Rng;
+use std::str::FromStr;
+
+/// Shape of the generated vectors
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Distribution {
+    /// Every component uniform in [-1, 1], the easiest and least realistic case
+    Uniform,
+    /// Gaussian blobs around `clusters` centers drawn uniformly in [-1, 1], with standard
+    /// deviation `spread` in every dimension
+    GaussianMixture { clusters: usize, spread: f32 },
+    /// Gaussian points on a random `intrinsic_dimension`-dimensional subspace, plus isotropic
+    /// Gaussian noise of standard deviation `noise`
+    LowRank { intrinsic_dimension: usize, noise: f32 },
+}
+
+/// Distribution names accepted on the command line, before their parameters are known
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DistributionKind {
+    Uniform,
+    GaussianMixture,
+    LowRank,
+}
+
+impl FromStr for DistributionKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "uniform" => Ok(DistributionKind::Uniform),
+            "gaussian-mixture" | "mixture" => Ok(DistributionKind::GaussianMixture),
+            "low-rank" => Ok(DistributionKind::LowRank),
+            _ => Err(format!("Unknown distribution '{}', expected uniform, gaussian-mixture or low-rank", s)),
+        }
+    }
+}
+
+/// Draws vectors from a distribution whose random structure (cluster centers or subspace
+/// basis) is fixed when the generator is created, so data and queries share it
+pub struct Generator {
+    distribution: Distribution,
+    dimension: usize,
+    centers: Vec<Vec<f32>>, // cluster centers of a Gaussian mixture
+    basis: Vec<Vec<f32>>, // one row per intrinsic dimension of a low-rank distribution
+}
+
+impl Generator {
+    pub fn new(distribution: Distribution, dimension: usize, rng: &mut StdRng) -> Self {
+        let mut generator = Self { distribution, dimension, centers: Vec::new(), basis: Vec::new() };
+        match distribution {
+            Distribution::Uniform => {}
+            Distribution::GaussianMixture { clusters, .. } => {
+                generator.centers = (0..clusters).map(|_| uniform_vector(rng, dimension)).collect();
+            }
+            Distribution::LowRank { intrinsic_dimension, .. } => {
+                // Scaled so points have roughly unit variance per ambient dimension
+                let scale = 1.0 / (intrinsic_dimension as f32).sqrt();
+                generator.basis = (0..intrinsic_dimension)
+                    .map(|_| (0..dimension).map(|_| gaussian(rng) * scale).collect())
+                    .collect();
+            }
+        }
+        generator
+    }
+
+    pub fn sample(&self, rng: &mut StdRng) -> Vec<f32> {
+        match self.distribution {
+            Distribution::Uniform => uniform_vector(rng, self.dimension),
+            Distribution::GaussianMixture { spread, .. } => {
+                let center = &self.centers[rng.gen_range(0..self.centers.len())];
+                center.iter().map(|&c| c + spread * gaussian(rng)).collect()
+            }
+            Distribution::LowRank { noise, .. } => {
+                let mut vector: Vec<f32> = (0..self.dimension).map(|_| noise * gaussian(rng)).collect();
+                for row in &self.basis {
+                    let weight = gaussian(rng);
+                    for (x, b) in vector.iter_mut().zip(row) {
+                        *x += weight * b;
+                    }
+                }
+                vector
+            }
+        }
+    }
+
+    pub fn samples(&self, rng: &mut StdRng, count: usize) -> Vec<Vec<f32>> {
+        (0..count).map(|_| self.sample(rng)).collect()
+    }
+}
+
+/// Replace a `fraction` of the vectors with copies of earlier ones perturbed by Gaussian
+/// noise of standard deviation `noise`, as re-ingested or lightly edited documents produce
+///
+/// Returns the number of vectors replaced.
+pub fn inject_near_duplicates(vectors: &mut [Vec<f32>], fraction: f32, noise: f32, rng: &mut StdRng) -> usize {
+    let mut replaced = 0;
+    for i in 1..vectors.len() {
+        if rng.gen::<f32>() < fraction {
+            let source = rng.gen_range(0..i);
+            vectors[i] = vectors[source].iter().map(|&x| x + noise * gaussian(rng)).collect();
+            replaced += 1;
+        }
+    }
+    replaced
+}
+
+pub fn uniform_vector(rng: &mut StdRng, dimension: usize) -> Vec<f32> {
+    (0..dimension).map(|_| rng.gen_range(-1.0..1.0)).collect()
+}
+
+/// Standard normal sample by the Box-Muller transform
+fn gaussian(rng: &mut StdRng) -> f32 {
+    let u1: f32 = 1.0 - rng.gen::<f32>(); // in (0, 1], so the logarithm is finite
+    let u2: f32 = rng.gen();
+    (-2.0 * u1.ln()).sqrt() * (std::f32::consts::TAU * u2).cos()
+}
@@ -1,6 +1,8 @@
+mod csv;
+
+use csv::{column, read_csv};
 use plotters::prelude::*;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::path::Path;
 
 #[derive(Debug)]
 struct BenchmarkData {
@@ -8,41 +10,112 @@ struct BenchmarkData {
     insertion_time_ms: f64,
     search_time_ms: f64,
     memory_usage_mb: f64,
+    allocated_memory_mb: Option<f64>, // missing from results of older benchmarks
+    resident_memory_mb: Option<f64>, // also missing where the platform does not report it
+}
+
+#[derive(Debug)]
+struct RecallPoint {
+    num_embeddings: usize,
+    ef_search: usize,
+    recall: f64,
+    queries_per_second: f64,
+}
+
+#[derive(Debug)]
+struct ChurnPoint {
+    num_embeddings: usize,
+    operations: usize,
+    inserts_per_second: f64,
+    removes_per_second: f64,
+    searches_per_second: f64,
+    recall: f64,
 }
 
-fn read_csv_data(filename: &str) -> Result<Vec<BenchmarkData>, Box<dyn std::error::Error>> {
-    let file = File::open(filename)?;
-    let reader = BufReader::new(file);
+fn read_csv_data(filename: &Path) -> Result<Vec<BenchmarkData>, Box<dyn std::error::Error>> {
+    let (header, rows) = read_csv(filename)?;
+    if rows.is_empty() {
+        return Ok(Vec::new());
+    }
+    
+    let num_embeddings = column(&header, "num_embeddings")?;
+    let insertion_time = column(&header, "insertion_time_ms")?;
+    let search_time = column(&header, "search_time_ms")?;
+    let memory_usage = column(&header, "memory_usage_mb")?;
+    let allocated_memory = column(&header, "allocated_memory_mb").ok();
+    let resident_memory = column(&header, "resident_memory_mb").ok();
+    let optional = |parts: &[String], column: Option<usize>| column.and_then(|c| parts.get(c)?.parse().ok());
+    
     let mut data = Vec::new();
+    for parts in rows {
+        data.push(BenchmarkData {
+            num_embeddings: parts[num_embeddings].parse()?,
+            insertion_time_ms: parts[insertion_time].parse()?,
+            search_time_ms: parts[search_time].parse()?,
+            memory_usage_mb: parts[memory_usage].parse()?,
+            allocated_memory_mb: optional(&parts, allocated_memory),
+            resident_memory_mb: optional(&parts, resident_memory),
+        });
+    }
     
-    // Skip header
-    let mut lines = reader.lines();
-    lines.next();
+    Ok(data)
+}
+
+fn read_recall_data(filename: &Path) -> Result<Vec<RecallPoint>, Box<dyn std::error::Error>> {
+    let (header, rows) = read_csv(filename)?;
+    if rows.is_empty() {
+        return Ok(Vec::new());
+    }
     
-    for line in lines {
-        let line = line?;
-        let parts: Vec<&str> = line.split(',').collect();
-        
-        if parts.len() >= 5 {
-            let num_embeddings = parts[0].parse::<usize>()?;
-            let insertion_time_ms = parts[2].parse::<f64>()?;
-            let search_time_ms = parts[3].parse::<f64>()?;
-            let memory_usage_mb = parts[4].parse::<f64>()?;
-            
-            data.push(BenchmarkData {
-                num_embeddings,
-                insertion_time_ms,
-                search_time_ms,
-                memory_usage_mb,
-            });
-        }
+    let num_embeddings = column(&header, "num_embeddings")?;
+    let ef_search = column(&header, "ef_search")?;
+    let recall = column(&header, "recall_at")?;
+    let queries_per_second = column(&header, "queries_per_second")?;
+    
+    let mut data = Vec::new();
+    for parts in rows {
+        data.push(RecallPoint {
+            num_embeddings: parts[num_embeddings].parse()?,
+            ef_search: parts[ef_search].parse()?,
+            recall: parts[recall].parse()?,
+            queries_per_second: parts[queries_per_second].parse()?,
+        });
+    }
+    
+    Ok(data)
+}
+
+fn read_churn_data(filename: &Path) -> Result<Vec<ChurnPoint>, Box<dyn std::error::Error>> {
+    let (header, rows) = read_csv(filename)?;
+    if rows.is_empty() {
+        return Ok(Vec::new());
+    }
+    
+    let num_embeddings = column(&header, "num_embeddings")?;
+    let operations = column(&header, "operations")?;
+    let inserts = column(&header, "inserts_per_second")?;
+    let removes = column(&header, "removes_per_second")?;
+    let searches = column(&header, "searches_per_second")?;
+    let recall = column(&header, "recall_at")?;
+    
+    let mut data = Vec::new();
+    for parts in rows {
+        data.push(ChurnPoint {
+            num_embeddings: parts[num_embeddings].parse()?,
+            operations: parts[operations].parse()?,
+            inserts_per_second: parts[inserts].parse()?,
+            removes_per_second: parts[removes].parse()?,
+            searches_per_second: parts[searches].parse()?,
+            recall: parts[recall].parse()?,
+        });
     }
     
     Ok(data)
 }
 
-fn create_insertion_time_plot(data: &[BenchmarkData]) -> Result<(), Box<dyn std::error::Error>> {
-    let root = BitMapBackend::new("performance_tests/insertion_time.png", (800, 600)).into_drawing_area();
+fn create_insertion_time_plot(data: &[BenchmarkData], dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let path = dir.join("insertion_time.png");
+    let root = BitMapBackend::new(&path, (800, 600)).into_drawing_area();
     root.fill(&WHITE)?;
     
     let mut chart = ChartBuilder::on(&root)
@@ -76,17 +149,18 @@ fn create_insertion_time_plot(data: &[BenchmarkData]) -> Result<(), Box<dyn std:
         5,
         RGBColor(255, 0, 0),
         &|c, s, st| {
-            return EmptyElement::at(c) + Circle::new((0, 0), s, st.filled());
+            EmptyElement::at(c) + Circle::new((0, 0), s, st.filled())
         },
     ))?;
     
     root.present()?;
-    println!("Insertion time plot saved to performance_tests/insertion_time.png");
+    println!("Insertion time plot saved to {}", path.display());
     Ok(())
 }
 
-fn create_search_time_plot(data: &[BenchmarkData]) -> Result<(), Box<dyn std::error::Error>> {
-    let root = BitMapBackend::new("performance_tests/search_time.png", (800, 600)).into_drawing_area();
+fn create_search_time_plot(data: &[BenchmarkData], dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let path = dir.join("search_time.png");
+    let root = BitMapBackend::new(&path, (800, 600)).into_drawing_area();
     root.fill(&WHITE)?;
     
     let mut chart = ChartBuilder::on(&root)
@@ -120,19 +194,31 @@ fn create_search_time_plot(data: &[BenchmarkData]) -> Result<(), Box<dyn std::er
         5,
         RGBColor(0, 0, 255),
         &|c, s, st| {
-            return EmptyElement::at(c) + Circle::new((0, 0), s, st.filled());
+            EmptyElement::at(c) + Circle::new((0, 0), s, st.filled())
         },
     ))?;
     
     root.present()?;
-    println!("Search time plot saved to performance_tests/search_time.png");
+    println!("Search time plot saved to {}", path.display());
     Ok(())
 }
 
-fn create_memory_usage_plot(data: &[BenchmarkData]) -> Result<(), Box<dyn std::error::Error>> {
-    let root = BitMapBackend::new("performance_tests/memory_usage.png", (800, 600)).into_drawing_area();
+/// (x, y) coordinates of a plotted series
+type Points = Vec<(f64, f64)>;
+
+fn create_memory_usage_plot(data: &[BenchmarkData], dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let path = dir.join("memory_usage.png");
+    let root = BitMapBackend::new(&path, (800, 600)).into_drawing_area();
     root.fill(&WHITE)?;
     
+    // The index's own estimate, and what the process really allocated and kept resident
+    let series: [(&str, RGBColor, Points); 3] = [
+        ("memory_usage() estimate", RGBColor(0, 255, 0), data.iter().map(|d| (d.num_embeddings as f64, d.memory_usage_mb)).collect()),
+        ("Heap allocated", RGBColor(255, 128, 0), data.iter().filter_map(|d| Some((d.num_embeddings as f64, d.allocated_memory_mb?))).collect()),
+        ("Resident set growth", RGBColor(0, 0, 255), data.iter().filter_map(|d| Some((d.num_embeddings as f64, d.resident_memory_mb?))).collect()),
+    ];
+    let max_memory = series.iter().flat_map(|(_, _, points)| points.iter().map(|(_, y)| *y)).fold(0.0, f64::max);
+    
     let mut chart = ChartBuilder::on(&root)
         .caption("SWARC Memory Usage vs Dataset Size", ("sans-serif", 20))
         .margin(10)
@@ -140,45 +226,57 @@ fn create_memory_usage_plot(data: &[BenchmarkData]) -> Result<(), Box<dyn std::e
         .y_label_area_size(80)
         .build_cartesian_2d(
             0.0..data.last().unwrap().num_embeddings as f64 * 1.1,
-            0.0..data.last().unwrap().memory_usage_mb * 1.1,
+            0.0..max_memory.max(1.0) * 1.1,
         )?;
     
     chart.configure_mesh()
         .x_desc("Number of Embeddings")
         .y_desc("Memory Usage (MB)")
         .x_label_formatter(&|x| format!("{:.0}", x))
-        .y_label_formatter(&|y| format!("{:.0}", y))
+        .y_label_formatter(&|y| format!("{:.1}", y))
         .draw()?;
     
-    let points: Vec<(f64, f64)> = data.iter()
-        .map(|d| (d.num_embeddings as f64, d.memory_usage_mb))
-        .collect();
-    
-    chart.draw_series(LineSeries::new(
-        points.iter().map(|(x, y)| (*x, *y)),
-        RGBColor(0, 255, 0).stroke_width(3),
-    ))?;
+    for (label, color, points) in series {
+        if points.is_empty() {
+            continue;
+        }
+        
+        chart.draw_series(LineSeries::new(
+            points.iter().map(|(x, y)| (*x, *y)),
+            color.stroke_width(3),
+        ))?
+            .label(label)
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color.stroke_width(3)));
+        
+        chart.draw_series(PointSeries::of_element(
+            points,
+            5,
+            color,
+            &|c, s, st| {
+                EmptyElement::at(c) + Circle::new((0, 0), s, st.filled())
+            },
+        ))?;
+    }
     
-    chart.draw_series(PointSeries::of_element(
-        points,
-        5,
-        RGBColor(0, 255, 0),
-        &|c, s, st| {
-            return EmptyElement::at(c) + Circle::new((0, 0), s, st.filled());
-        },
-    ))?;
+    chart.configure_series_labels()
+        .position(SeriesLabelPosition::UpperLeft)
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()?;
     
     root.present()?;
-    println!("Memory usage plot saved to performance_tests/memory_usage.png");
+    println!("Memory usage plot saved to {}", path.display());
     Ok(())
 }
 
-fn create_throughput_plot(data: &[BenchmarkData]) -> Result<(), Box<dyn std::error::Error>> {
-    let root = BitMapBackend::new("performance_tests/throughput.png", (800, 600)).into_drawing_area();
+fn create_throughput_plot(data: &[BenchmarkData], dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let path = dir.join("throughput.png");
+    let root = BitMapBackend::new(&path, (800, 600)).into_drawing_area();
     root.fill(&WHITE)?;
     
     // Calculate throughput (insertions per second)
     let throughput_data: Vec<(f64, f64)> = data.iter()
+        .filter(|d| d.insertion_time_ms > 0.0)
         .map(|d| {
             let throughput = d.num_embeddings as f64 / (d.insertion_time_ms / 1000.0);
             (d.num_embeddings as f64, throughput)
@@ -214,29 +312,223 @@ fn create_throughput_plot(data: &[BenchmarkData]) -> Result<(), Box<dyn std::err
         5,
         RGBColor(128, 0, 128),
         &|c, s, st| {
-            return EmptyElement::at(c) + Circle::new((0, 0), s, st.filled());
+            EmptyElement::at(c) + Circle::new((0, 0), s, st.filled())
         },
     ))?;
     
     root.present()?;
-    println!("Throughput plot saved to performance_tests/throughput.png");
+    println!("Throughput plot saved to {}", path.display());
+    Ok(())
+}
+
+fn create_recall_qps_plot(data: &[RecallPoint], dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let path = dir.join("recall_qps.png");
+    let root = BitMapBackend::new(&path, (800, 600)).into_drawing_area();
+    root.fill(&WHITE)?;
+    
+    let max_qps = data.iter().map(|p| p.queries_per_second).fold(0.0, f64::max);
+    let min_recall = data.iter().map(|p| p.recall).fold(1.0, f64::min);
+    
+    let mut chart = ChartBuilder::on(&root)
+        .caption("SWARC Recall vs Queries per Second", ("sans-serif", 20))
+        .margin(10)
+        .x_label_area_size(60)
+        .y_label_area_size(80)
+        .build_cartesian_2d(
+            (min_recall - 0.05).max(0.0)..1.0,
+            0.0..max_qps * 1.1,
+        )?;
+    
+    chart.configure_mesh()
+        .x_desc("Recall@10")
+        .y_desc("Queries per Second")
+        .x_label_formatter(&|x| format!("{:.2}", x))
+        .y_label_formatter(&|y| format!("{:.0}", y))
+        .draw()?;
+    
+    // One curve per dataset size, with points in ef_search order
+    let mut sizes: Vec<usize> = data.iter().map(|p| p.num_embeddings).collect();
+    sizes.dedup();
+    for (i, &size) in sizes.iter().enumerate() {
+        let color = Palette99::pick(i).to_rgba();
+        let mut points: Vec<&RecallPoint> = data.iter().filter(|p| p.num_embeddings == size).collect();
+        points.sort_by_key(|p| p.ef_search);
+        let points: Vec<(f64, f64)> = points.iter().map(|p| (p.recall, p.queries_per_second)).collect();
+        
+        chart.draw_series(LineSeries::new(points.clone(), color.stroke_width(3)))?
+            .label(format!("{} embeddings", size))
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+        
+        chart.draw_series(PointSeries::of_element(
+            points,
+            5,
+            color,
+            &|c, s, st| {
+                EmptyElement::at(c) + Circle::new((0, 0), s, st.filled())
+            },
+        ))?;
+    }
+    
+    chart.configure_series_labels()
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()?;
+    
+    root.present()?;
+    println!("Recall vs QPS plot saved to {}", path.display());
+    Ok(())
+}
+
+fn create_churn_recall_plot(data: &[ChurnPoint], dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let path = dir.join("churn_recall.png");
+    let root = BitMapBackend::new(&path, (800, 600)).into_drawing_area();
+    root.fill(&WHITE)?;
+    
+    let max_operations = data.iter().map(|p| p.operations).max().unwrap_or(0).max(1);
+    let min_recall = data.iter().map(|p| p.recall).fold(1.0, f64::min);
+    
+    let mut chart = ChartBuilder::on(&root)
+        .caption("SWARC Recall under Churn", ("sans-serif", 20))
+        .margin(10)
+        .x_label_area_size(60)
+        .y_label_area_size(80)
+        .build_cartesian_2d(
+            0.0..max_operations as f64 * 1.05,
+            (min_recall - 0.05).max(0.0)..1.0,
+        )?;
+    
+    chart.configure_mesh()
+        .x_desc("Operations")
+        .y_desc("Recall@10")
+        .x_label_formatter(&|x| format!("{:.0}", x))
+        .y_label_formatter(&|y| format!("{:.2}", y))
+        .draw()?;
+    
+    let mut sizes: Vec<usize> = data.iter().map(|p| p.num_embeddings).collect();
+    sizes.dedup();
+    for (i, &size) in sizes.iter().enumerate() {
+        let color = Palette99::pick(i).to_rgba();
+        let points: Vec<(f64, f64)> = data.iter()
+            .filter(|p| p.num_embeddings == size)
+            .map(|p| (p.operations as f64, p.recall))
+            .collect();
+        
+        chart.draw_series(LineSeries::new(points, color.stroke_width(3)))?
+            .label(format!("{} embeddings", size))
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+    }
+    
+    chart.configure_series_labels()
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()?;
+    
+    root.present()?;
+    println!("Churn recall plot saved to {}", path.display());
+    Ok(())
+}
+
+/// Reads the rate of one kind of operation from a churn point
+type ChurnRate = fn(&ChurnPoint) -> f64;
+
+fn create_churn_throughput_plot(data: &[ChurnPoint], dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let path = dir.join("churn_throughput.png");
+    let root = BitMapBackend::new(&path, (800, 600)).into_drawing_area();
+    root.fill(&WHITE)?;
+    
+    // The first row of each run is measured before any operation, so it has no throughput
+    let data: Vec<&ChurnPoint> = data.iter().filter(|p| p.operations > 0).collect();
+    let max_operations = data.iter().map(|p| p.operations).max().unwrap_or(0).max(1);
+    let max_throughput = data.iter()
+        .flat_map(|p| [p.inserts_per_second, p.removes_per_second, p.searches_per_second])
+        .fold(0.0, f64::max)
+        .max(1.0);
+    
+    let mut chart = ChartBuilder::on(&root)
+        .caption("SWARC Throughput under Churn", ("sans-serif", 20))
+        .margin(10)
+        .x_label_area_size(60)
+        .y_label_area_size(80)
+        .build_cartesian_2d(
+            0.0..max_operations as f64 * 1.05,
+            // Inserts, removes and searches run at rates orders of magnitude apart
+            (1.0..max_throughput * 2.0).log_scale(),
+        )?;
+    
+    chart.configure_mesh()
+        .x_desc("Operations")
+        .y_desc("Operations per Second (log scale)")
+        .x_label_formatter(&|x| format!("{:.0}", x))
+        .y_label_formatter(&|y| format!("{:.0}", y))
+        .draw()?;
+    
+    // One curve per dataset size and operation, skipping windows where it did not run
+    let mut sizes: Vec<usize> = data.iter().map(|p| p.num_embeddings).collect();
+    sizes.dedup();
+    let operations: [(&str, ChurnRate); 3] = [
+        ("inserts", |p| p.inserts_per_second),
+        ("removes", |p| p.removes_per_second),
+        ("searches", |p| p.searches_per_second),
+    ];
+    for (i, &size) in sizes.iter().enumerate() {
+        for (j, (name, rate)) in operations.iter().enumerate() {
+            let color = Palette99::pick(i * operations.len() + j).to_rgba();
+            let points: Vec<(f64, f64)> = data.iter()
+                .filter(|p| p.num_embeddings == size && rate(p) > 0.0)
+                .map(|p| (p.operations as f64, rate(p)))
+                .collect();
+            if points.is_empty() {
+                continue;
+            }
+            
+            chart.draw_series(LineSeries::new(points, color.stroke_width(3)))?
+                .label(format!("{} ({} embeddings)", name, size))
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color.stroke_width(3)));
+        }
+    }
+    
+    chart.configure_series_labels()
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()?;
+    
+    root.present()?;
+    println!("Churn throughput plot saved to {}", path.display());
     Ok(())
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Results are read from, and plots written to, the benchmark's output directory
+    let dir = std::env::args().nth(1).unwrap_or_else(|| "performance_tests".to_string());
+    let dir = Path::new(&dir);
+    
     println!("Reading benchmark data...");
-    let data = read_csv_data("performance_tests/benchmark_results.csv")?;
+    // A churn run writes only churn.csv, so either file may be missing
+    let data = read_csv_data(&dir.join("benchmark_results.csv")).unwrap_or_default();
+    let churn = read_churn_data(&dir.join("churn.csv")).unwrap_or_default();
     
-    if data.is_empty() {
-        eprintln!("No data found in benchmark_results.csv");
+    if data.is_empty() && churn.is_empty() {
+        eprintln!("No data found in benchmark_results.csv or churn.csv");
         return Ok(());
     }
     
     println!("Creating plots...");
-    create_insertion_time_plot(&data)?;
-    create_search_time_plot(&data)?;
-    create_memory_usage_plot(&data)?;
-    create_throughput_plot(&data)?;
+    if !data.is_empty() {
+        create_insertion_time_plot(&data, dir)?;
+        create_search_time_plot(&data, dir)?;
+        create_memory_usage_plot(&data, dir)?;
+        create_throughput_plot(&data, dir)?;
+        
+        match read_recall_data(&dir.join("recall_qps.csv")) {
+            Ok(recall) if !recall.is_empty() => create_recall_qps_plot(&recall, dir)?,
+            _ => eprintln!("No data found in recall_qps.csv, skipping the recall plot"),
+        }
+    }
+    
+    if !churn.is_empty() {
+        create_churn_recall_plot(&churn, dir)?;
+        create_churn_throughput_plot(&churn, dir)?;
+    }
     
     println!("All plots created successfully!");
     Ok(())
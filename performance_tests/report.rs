@@ -0,0 +1,441 @@
+mod csv;
+
+use csv::{column, read_csv, CsvTable};
+use plotters::prelude::*;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+const USAGE: &str = "\
+Usage: report [OPTIONS] <DIR>...
+
+Combine the results of benchmark runs into one HTML page. Each DIR is the --output
+directory of a benchmark run; the first one is the baseline the others are compared with.
+
+Options:
+  --output <FILE>      HTML file to write [default: report.html]
+  --title <TEXT>       Title of the report [default: SWARC Benchmark Report]
+  --threshold <PCT>    Smallest change from the baseline that is highlighted [default: 5]
+  -h, --help           Print this help
+";
+
+/// Report settings, read from the command line
+struct ReportConfig {
+    runs: Vec<PathBuf>,
+    output: PathBuf,
+    title: String,
+    threshold: f64, // percent
+}
+
+impl ReportConfig {
+    /// Parse flags and directories, not including the program name
+    ///
+    /// Returns `Ok(None)` when help was requested.
+    fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Option<Self>, String> {
+        let mut config = Self {
+            runs: Vec::new(),
+            output: PathBuf::from("report.html"),
+            title: "SWARC Benchmark Report".to_string(),
+            threshold: 5.0,
+        };
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            if arg == "-h" || arg == "--help" {
+                return Ok(None);
+            }
+            if !arg.starts_with("--") {
+                config.runs.push(PathBuf::from(arg));
+                continue;
+            }
+
+            // Both `--flag value` and `--flag=value` are accepted
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+            if !["--output", "--title", "--threshold"].contains(&flag.as_str()) {
+                return Err(format!("Unknown option '{}'", flag));
+            }
+            let value = inline
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("Missing value for {}", flag))?;
+            match flag.as_str() {
+                "--output" => config.output = PathBuf::from(value),
+                "--title" => config.title = value,
+                "--threshold" => {
+                    config.threshold = match value.parse::<f64>() {
+                        Ok(pct) if pct >= 0.0 && pct.is_finite() => pct,
+                        _ => return Err(format!("Invalid value '{}' for {}", value, flag)),
+                    }
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        if config.runs.is_empty() {
+            return Err("At least one benchmark output directory is required".to_string());
+        }
+        Ok(Some(config))
+    }
+}
+
+/// The results of one benchmark run, any of which may be missing
+struct Run {
+    label: String,
+    parameters: Vec<(String, String)>,
+    results: Option<CsvTable>,
+    recall: Option<CsvTable>,
+    churn: Option<CsvTable>,
+}
+
+impl Run {
+    fn load(dir: &Path) -> Result<Self, String> {
+        let table = |name: &str| read_csv(&dir.join(name)).ok().filter(|(_, rows)| !rows.is_empty());
+        let parameters = table("parameters.csv")
+            .map(|(_, rows)| rows.into_iter().filter(|row| row.len() >= 2).map(|row| (row[0].clone(), row[1].clone())).collect())
+            .unwrap_or_default();
+        let run = Self {
+            label: dir.display().to_string(),
+            parameters,
+            results: table("benchmark_results.csv"),
+            recall: table("recall_qps.csv"),
+            churn: table("churn.csv"),
+        };
+        if run.results.is_none() && run.churn.is_none() {
+            return Err(format!("No benchmark_results.csv or churn.csv found in '{}'", dir.display()));
+        }
+        Ok(run)
+    }
+}
+
+/// (x, y) coordinates of a plotted series
+type Points = Vec<(f64, f64)>;
+
+/// Points of column `y` against column `x`, from the rows whose `key` column holds `value`
+fn series(table: &CsvTable, x: &str, y: &str, key: Option<(&str, &str)>) -> Points {
+    let (header, rows) = table;
+    let (Ok(x), Ok(y)) = (column(header, x), column(header, y)) else {
+        return Vec::new();
+    };
+    let key = match key {
+        Some((name, value)) => match column(header, name) {
+            Ok(index) => Some((index, value)),
+            Err(_) => return Vec::new(),
+        },
+        None => None,
+    };
+    rows.iter()
+        .filter(|row| key.is_none_or(|(index, value)| row.get(index).is_some_and(|v| v == value)))
+        .filter_map(|row| Some((row.get(x)?.parse().ok()?, row.get(y)?.parse().ok()?)))
+        .collect()
+}
+
+/// Distinct values of a column, in order of first appearance across the runs
+fn distinct(runs: &[Run], table: fn(&Run) -> Option<&CsvTable>, name: &str) -> Vec<String> {
+    let mut values: Vec<String> = Vec::new();
+    for (header, rows) in runs.iter().filter_map(table) {
+        let Ok(index) = column(header, name) else { continue };
+        for row in rows {
+            if let Some(value) = row.get(index) {
+                if !values.contains(value) {
+                    values.push(value.clone());
+                }
+            }
+        }
+    }
+    values
+}
+
+/// Render a line chart with one series per run as an inline SVG element
+fn line_chart(title: &str, x_desc: &str, y_desc: &str, series: &[(String, Points)], from_zero: bool) -> Result<String, Box<dyn std::error::Error>> {
+    let points = || series.iter().flat_map(|(_, points)| points.iter());
+    if points().next().is_none() {
+        return Ok(format!("<p>No data for {}.</p>\n", escape(title)));
+    }
+    let (mut x_min, mut x_max) = points().fold((f64::MAX, f64::MIN), |(lo, hi), (x, _)| (lo.min(*x), hi.max(*x)));
+    let (mut y_min, mut y_max) = points().fold((f64::MAX, f64::MIN), |(lo, hi), (_, y)| (lo.min(*y), hi.max(*y)));
+    // Pad the ranges, and give single points a range to sit in
+    let pad = |lo: f64, hi: f64| if hi > lo { (hi - lo) * 0.05 } else { hi.abs().max(1.0) * 0.1 };
+    let (x_pad, y_pad) = (pad(x_min, x_max), pad(y_min, y_max));
+    x_min -= x_pad;
+    x_max += x_pad;
+    y_min = if from_zero { 0.0 } else { y_min - y_pad };
+    y_max += y_pad;
+
+    let mut svg = String::new();
+    {
+        let root = SVGBackend::with_string(&mut svg, (720, 420)).into_drawing_area();
+        root.fill(&WHITE)?;
+
+        let mut chart = ChartBuilder::on(&root)
+            .caption(title, ("sans-serif", 18))
+            .margin(10)
+            .x_label_area_size(50)
+            .y_label_area_size(70)
+            .build_cartesian_2d(x_min..x_max, y_min..y_max)?;
+
+        chart.configure_mesh()
+            .x_desc(x_desc)
+            .y_desc(y_desc)
+            .draw()?;
+
+        for (i, (label, points)) in series.iter().enumerate() {
+            if points.is_empty() {
+                continue;
+            }
+            let color = Palette99::pick(i).to_rgba();
+            chart.draw_series(LineSeries::new(points.clone(), color.stroke_width(2)))?
+                .label(label.as_str())
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color.stroke_width(2)));
+            chart.draw_series(points.iter().map(|&point| Circle::new(point, 3, color.filled())))?;
+        }
+
+        chart.configure_series_labels()
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .draw()?;
+        root.present()?;
+    }
+    Ok(svg)
+}
+
+/// Whether a larger value of a result column is an improvement
+fn higher_is_better(column: &str) -> bool {
+    column.starts_with("recall") || column.ends_with("per_second")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Table of every setting recorded by any run, marking those that differ from the baseline
+fn parameter_table(html: &mut String, runs: &[Run]) {
+    let mut names: Vec<&str> = Vec::new();
+    for run in runs {
+        for (name, _) in &run.parameters {
+            if !names.contains(&name.as_str()) {
+                names.push(name);
+            }
+        }
+    }
+    if names.is_empty() {
+        html.push_str("<p>No parameters.csv found; rerun the benchmarks to record their settings.</p>\n");
+        return;
+    }
+
+    html.push_str("<table>\n<tr><th>Parameter</th>");
+    for run in runs {
+        let _ = write!(html, "<th>{}</th>", escape(&run.label));
+    }
+    html.push_str("</tr>\n");
+    for name in names {
+        let value = |run: &Run| run.parameters.iter().find(|(n, _)| n == name).map(|(_, v)| v.clone());
+        let baseline = value(&runs[0]);
+        let _ = write!(html, "<tr><td>{}</td>", escape(name));
+        for (i, run) in runs.iter().enumerate() {
+            let current = value(run);
+            let class = if i > 0 && current != baseline { " class=\"changed\"" } else { "" };
+            let _ = write!(html, "<td{}>{}</td>", class, escape(current.as_deref().unwrap_or("-")));
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n");
+}
+
+/// Table of every result column by the `keys` columns, with each run's change from the
+/// baseline highlighted when it exceeds `threshold` percent
+fn comparison_table(html: &mut String, runs: &[Run], table: fn(&Run) -> Option<&CsvTable>, keys: &[&str], threshold: f64) {
+    // Rows are (key values, column), in order of first appearance across the runs
+    let mut rows: Vec<(Vec<String>, String)> = Vec::new();
+    let mut values: Vec<HashMap<(Vec<String>, String), String>> = Vec::new();
+    for run in runs {
+        let mut run_values = HashMap::new();
+        if let Some((header, data)) = table(run) {
+            let key_indexes: Vec<usize> = keys.iter().filter_map(|key| column(header, key).ok()).collect();
+            for row in data {
+                let key: Vec<String> = key_indexes.iter().filter_map(|&i| row.get(i).cloned()).collect();
+                for (i, name) in header.iter().enumerate() {
+                    if key_indexes.contains(&i) || name == "dimension" {
+                        continue;
+                    }
+                    let Some(value) = row.get(i).filter(|v| !v.is_empty()) else { continue };
+                    let entry = (key.clone(), name.clone());
+                    if !rows.contains(&entry) {
+                        rows.push(entry.clone());
+                    }
+                    run_values.insert(entry, value.clone());
+                }
+            }
+        }
+        values.push(run_values);
+    }
+
+    html.push_str("<table>\n<tr>");
+    for key in keys {
+        let _ = write!(html, "<th>{}</th>", escape(key));
+    }
+    html.push_str("<th>Result</th>");
+    for run in runs {
+        let _ = write!(html, "<th>{}</th>", escape(&run.label));
+    }
+    html.push_str("</tr>\n");
+
+    for entry in &rows {
+        let (key, name) = entry;
+        html.push_str("<tr>");
+        for value in key {
+            let _ = write!(html, "<td>{}</td>", escape(value));
+        }
+        let _ = write!(html, "<td>{}</td>", escape(name));
+        let baseline = values[0].get(entry).and_then(|v| v.parse::<f64>().ok());
+        for (i, run_values) in values.iter().enumerate() {
+            let Some(value) = run_values.get(entry) else {
+                html.push_str("<td>-</td>");
+                continue;
+            };
+            let change = match (i, baseline, value.parse::<f64>()) {
+                (1.., Some(base), Ok(current)) if base != 0.0 => Some((current - base) / base.abs() * 100.0),
+                _ => None,
+            };
+            match change {
+                Some(change) => {
+                    let improved = (change > 0.0) == higher_is_better(name);
+                    let class = match change.abs() >= threshold {
+                        true if improved => " class=\"better\"",
+                        true => " class=\"worse\"",
+                        false => "",
+                    };
+                    let _ = write!(html, "<td{}>{} ({:+.1}%)</td>", class, escape(value), change);
+                }
+                None => {
+                    let _ = write!(html, "<td>{}</td>", escape(value));
+                }
+            }
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n");
+}
+
+/// Build the whole HTML page
+fn render(config: &ReportConfig, runs: &[Run]) -> Result<String, Box<dyn std::error::Error>> {
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>\n\
+         body {{ font-family: sans-serif; margin: 2em; color: #222; }}\n\
+         table {{ border-collapse: collapse; margin-bottom: 2em; font-size: 14px; }}\n\
+         th, td {{ border: 1px solid #ccc; padding: 4px 8px; text-align: right; }}\n\
+         th {{ background: #f0f0f0; }}\n\
+         td:first-child, th:first-child {{ text-align: left; }}\n\
+         .better {{ background: #d4f5d4; }}\n\
+         .worse {{ background: #f8d0d0; }}\n\
+         .changed {{ background: #fff3c4; }}\n\
+         .charts svg {{ margin: 0 1em 1em 0; border: 1px solid #eee; }}\n\
+         </style>\n</head>\n<body>\n<h1>{title}</h1>\n",
+        title = escape(&config.title)
+    );
+    let _ = writeln!(
+        html,
+        "<p>Baseline: <b>{}</b>. Changes of at least {}% are highlighted in green when they improve on the baseline and in red when they regress.</p>",
+        escape(&runs[0].label),
+        config.threshold
+    );
+
+    html.push_str("<h2>Parameters</h2>\n");
+    parameter_table(&mut html, runs);
+
+    if runs.iter().any(|run| run.results.is_some()) {
+        html.push_str("<h2>Build and Search</h2>\n<div class=\"charts\">\n");
+        let charts = [
+            ("Search Time", "search_time_ms", "Search time (ms)"),
+            ("Parallel Insertion Time", "parallel_insertion_time_ms", "Insertion time (ms)"),
+            ("Memory Usage", "memory_usage_mb", "Memory (MB)"),
+            ("Recall@10", "recall_at", "Recall"),
+        ];
+        for (title, name, y_desc) in charts {
+            let curves: Vec<(String, Points)> = runs
+                .iter()
+                .map(|run| (run.label.clone(), run.results.as_ref().map_or(Vec::new(), |t| series(t, "num_embeddings", name, None))))
+                .collect();
+            html.push_str(&line_chart(title, "Number of embeddings", y_desc, &curves, name != "recall_at")?);
+        }
+        html.push_str("</div>\n");
+        comparison_table(&mut html, runs, |run| run.results.as_ref(), &["num_embeddings"], config.threshold);
+    }
+
+    if runs.iter().any(|run| run.recall.is_some()) {
+        html.push_str("<h2>Recall vs Queries per Second</h2>\n<div class=\"charts\">\n");
+        for size in distinct(runs, |run| run.recall.as_ref(), "num_embeddings") {
+            let curves: Vec<(String, Points)> = runs
+                .iter()
+                .map(|run| {
+                    let points = run.recall.as_ref().map_or(Vec::new(), |t| series(t, "recall_at", "queries_per_second", Some(("num_embeddings", &size))));
+                    (run.label.clone(), points)
+                })
+                .collect();
+            let title = format!("{} embeddings", size);
+            html.push_str(&line_chart(&title, "Recall@10", "Queries per second", &curves, true)?);
+        }
+        html.push_str("</div>\n");
+        comparison_table(&mut html, runs, |run| run.recall.as_ref(), &["num_embeddings", "ef_search"], config.threshold);
+    }
+
+    if runs.iter().any(|run| run.churn.is_some()) {
+        html.push_str("<h2>Churn</h2>\n<div class=\"charts\">\n");
+        for size in distinct(runs, |run| run.churn.as_ref(), "num_embeddings") {
+            for (name, y_desc) in [("recall_at", "Recall@10"), ("removes_per_second", "Removes per second"), ("searches_per_second", "Searches per second")] {
+                let curves: Vec<(String, Points)> = runs
+                    .iter()
+                    .map(|run| {
+                        let mut points = run.churn.as_ref().map_or(Vec::new(), |t| series(t, "operations", name, Some(("num_embeddings", &size))));
+                        // Throughput is only measured once operations have run
+                        if name != "recall_at" {
+                            points.retain(|&(operations, rate)| operations > 0.0 && rate > 0.0);
+                        }
+                        (run.label.clone(), points)
+                    })
+                    .collect();
+                let title = format!("{} under churn ({} embeddings)", y_desc, size);
+                html.push_str(&line_chart(&title, "Operations", y_desc, &curves, name != "recall_at")?);
+            }
+        }
+        html.push_str("</div>\n");
+    }
+
+    html.push_str("</body>\n</html>\n");
+    Ok(html)
+}
+
+fn main() {
+    let config = match ReportConfig::parse(std::env::args().skip(1)) {
+        Ok(Some(config)) => config,
+        Ok(None) => {
+            print!("{}", USAGE);
+            return;
+        }
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let runs: Vec<Run> = match config.runs.iter().map(|dir| Run::load(dir)).collect() {
+        Ok(runs) => runs,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let html = render(&config, &runs).unwrap_or_else(|e| {
+        eprintln!("Failed to render the report: {}", e);
+        std::process::exit(1);
+    });
+    std::fs::write(&config.output, html).unwrap_or_else(|e| {
+        eprintln!("Failed to write {}: {}", config.output.display(), e);
+        std::process::exit(1);
+    });
+    println!("Report comparing {} runs saved to {}", runs.len(), config.output.display());
+}
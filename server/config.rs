@@ -0,0 +1,109 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+use swarc::DistanceMetric;
+
+pub const USAGE: &str = "\
+Usage: swarc-server --index <FILE> [OPTIONS]
+
+Serves an index over HTTP with JSON bodies, and its metrics in Prometheus text format at
+/metrics. The index is loaded from --index when the file exists, or created empty otherwise,
+and saved back to it on shutdown (Ctrl-C or SIGTERM).
+
+Options:
+  --index <FILE>             Index file loaded on startup and snapshotted on shutdown
+  --bind <ADDR>              Address to listen on [default: 127.0.0.1:7070]
+  --grpc <ADDR>              Also serve the gRPC service of proto/swarc.proto on this address;
+                             needs a build with --features grpc
+  --threads <N>              Threads handling requests [default: all cores]
+  --m <N>                    Maximum connections per node of a new index [default: 16]
+  --ef-construction <N>      Candidate list size while building a new index [default: 200]
+  --metric <NAME>            Metric of a new index: euclidean, cosine, inner_product, manhattan,
+                             chebyshev or minkowski:<p> [default: euclidean]
+  --ef-search <N>            Candidates kept on layer 0 while searching [default: ef-construction]
+  --max-body <MB>            Largest request body or gRPC message accepted [default: 64]
+  -h, --help                 Print this help
+";
+
+/// Flags that take a value
+const VALUE_FLAGS: [&str; 9] = [
+    "--index", "--bind", "--grpc", "--threads", "--m", "--ef-construction", "--metric", "--ef-search", "--max-body",
+];
+
+/// Server settings, read from the command line
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub index: PathBuf,
+    pub bind: String,
+    pub grpc: Option<String>,
+    pub threads: usize,
+    pub m: usize,
+    pub ef_construction: usize,
+    pub metric: DistanceMetric,
+    pub ef_search: Option<usize>,
+    pub max_body: usize, // bytes
+}
+
+impl ServerConfig {
+    /// Parse flags, not including the program name
+    ///
+    /// Returns `Ok(None)` when help was requested.
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Option<Self>, String> {
+        let mut index = None;
+        let mut config = Self {
+            index: PathBuf::new(),
+            bind: "127.0.0.1:7070".to_string(),
+            grpc: None,
+            threads: num_cpus::get(),
+            m: 16,
+            ef_construction: 200,
+            metric: DistanceMetric::Euclidean,
+            ef_search: None,
+            max_body: 64 << 20,
+        };
+        let mut args = args.into_iter();
+
+        while let Some(flag) = args.next() {
+            // Both `--flag value` and `--flag=value` are accepted
+            let (flag, inline) = match flag.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (flag, None),
+            };
+            if flag == "-h" || flag == "--help" {
+                return Ok(None);
+            }
+
+            if !VALUE_FLAGS.contains(&flag.as_str()) {
+                return Err(format!("Unknown option '{}'", flag));
+            }
+            let value = inline
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("Missing value for {}", flag))?;
+            match flag.as_str() {
+                "--index" => index = Some(PathBuf::from(value)),
+                "--bind" => config.bind = value,
+                "--grpc" => config.grpc = Some(value),
+                "--threads" => config.threads = parse_positive(&flag, &value)?,
+                "--m" => config.m = parse_positive(&flag, &value)?,
+                "--ef-construction" => config.ef_construction = parse_positive(&flag, &value)?,
+                "--metric" => config.metric = value.parse()?,
+                "--ef-search" => config.ef_search = Some(parse_positive(&flag, &value)?),
+                "--max-body" => config.max_body = parse_positive(&flag, &value)? << 20,
+                _ => unreachable!(),
+            }
+        }
+
+        config.index = index.ok_or("Missing --index")?;
+        Ok(Some(config))
+    }
+}
+
+fn parse<T: FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("Invalid value '{}' for {}", value, flag))
+}
+
+fn parse_positive(flag: &str, value: &str) -> Result<usize, String> {
+    match parse(flag, value)? {
+        0 => Err(format!("{} must be greater than zero", flag)),
+        n => Ok(n),
+    }
+}
@@ -0,0 +1,198 @@
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use serde_json::Value;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
+use tonic::{Request, Response, Status};
+use swarc::jsonl::JsonlRecord;
+use crate::routes::DEFAULT_K;
+use crate::state::{Neighbor, State, Stats};
+use crate::POLL_INTERVAL;
+
+pub mod proto {
+    tonic::include_proto!("swarc");
+}
+
+use proto::index_server::{Index, IndexServer};
+
+/// The `swarc.Index` service over the shared server state
+pub struct Service {
+    state: Arc<State>,
+    shutdown: Arc<AtomicBool>,
+}
+
+impl Service {
+    /// Serve `state`, ending open streams once `shutdown` is set
+    pub fn new(state: Arc<State>, shutdown: Arc<AtomicBool>) -> Self {
+        Self { state, shutdown }
+    }
+}
+
+/// Serve the gRPC service on its own thread until `shutdown` is set
+///
+/// The address is bound before returning, so a port in use is reported at startup.
+pub fn spawn(addr: &str, state: Arc<State>, shutdown: Arc<AtomicBool>, max_message: usize) -> Result<JoinHandle<Result<(), String>>, String> {
+    let listener = TcpListener::bind(addr).map_err(|e| format!("Failed to listen on {}: {}", addr, e))?;
+    listener.set_nonblocking(true).map_err(|e| format!("Failed to listen on {}: {}", addr, e))?;
+
+    Ok(thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| format!("Failed to start the gRPC runtime: {}", e))?;
+        runtime.block_on(async move {
+            let listener = tokio::net::TcpListener::from_std(listener).map_err(|e| format!("Failed to listen for gRPC: {}", e))?;
+            let stopped = {
+                let shutdown = Arc::clone(&shutdown);
+                async move {
+                    while !shutdown.load(Ordering::SeqCst) {
+                        tokio::time::sleep(POLL_INTERVAL).await;
+                    }
+                }
+            };
+            let service = IndexServer::new(Service::new(state, shutdown))
+                .max_decoding_message_size(max_message)
+                .max_encoding_message_size(max_message);
+            tonic::transport::Server::builder()
+                .add_service(service)
+                .serve_with_incoming_shutdown(TcpListenerStream::new(listener), stopped)
+                .await
+                .map_err(|e| format!("gRPC server failed: {}", e))
+        })
+    }))
+}
+
+/// Run index work on the blocking pool, which the index lock and searches may tie up
+async fn blocking<R: Send + 'static>(work: impl FnOnce() -> R + Send + 'static) -> Result<R, Status> {
+    tokio::task::spawn_blocking(work).await.map_err(|e| Status::internal(e.to_string()))
+}
+
+/// Parse a document sent as JSON text, where an empty string means none
+fn parse_document(text: &str) -> Result<Option<Value>, String> {
+    if text.is_empty() {
+        return Ok(None);
+    }
+    serde_json::from_str(text).map(Some).map_err(|e| format!("Invalid document: {}", e))
+}
+
+fn neighbors(results: Vec<Neighbor>) -> proto::SearchResponse {
+    let neighbors = results
+        .into_iter()
+        .map(|(id, distance, document)| proto::Neighbor {
+            id,
+            distance,
+            document: document.map_or(String::new(), |document| document.to_string()),
+        })
+        .collect();
+    proto::SearchResponse { neighbors }
+}
+
+fn stats_response(stats: Stats) -> proto::StatsResponse {
+    proto::StatsResponse {
+        nodes: stats.nodes as u64,
+        dimension: stats.dimension.unwrap_or(0) as u32,
+        metric: stats.metric,
+        m: stats.m as u32,
+        ef_construction: stats.ef_construction as u32,
+        ef_search: stats.ef_search.unwrap_or(0) as u32,
+        nodes_per_layer: stats.nodes_per_layer.into_iter().map(|count| count as u64).collect(),
+        memory_bytes: stats.memory.total() as u64,
+    }
+}
+
+fn k_or_default(k: u32) -> usize {
+    if k == 0 { DEFAULT_K } else { k as usize }
+}
+
+#[tonic::async_trait]
+impl Index for Service {
+    async fn upsert(&self, request: Request<proto::UpsertRequest>) -> Result<Response<proto::UpsertResponse>, Status> {
+        let mut errors = Vec::new();
+        let mut records = Vec::new();
+        for record in request.into_inner().records {
+            match parse_document(&record.document) {
+                Ok(document) => records.push(JsonlRecord { id: record.id, vector: record.vector, document }),
+                Err(error) => errors.push(proto::RecordError { id: record.id, error }),
+            }
+        }
+
+        let state = Arc::clone(&self.state);
+        let outcome = blocking(move || state.insert(records, true)).await?;
+        errors.extend(outcome.errors.into_iter().map(|(id, error)| proto::RecordError { id, error }));
+        Ok(Response::new(proto::UpsertResponse {
+            inserted: outcome.inserted as u64,
+            replaced: outcome.replaced as u64,
+            errors,
+        }))
+    }
+
+    async fn delete(&self, request: Request<proto::DeleteRequest>) -> Result<Response<proto::DeleteResponse>, Status> {
+        let ids = request.into_inner().ids;
+        let state = Arc::clone(&self.state);
+        let (removed, missing) = blocking(move || state.remove(ids)).await?;
+        Ok(Response::new(proto::DeleteResponse { removed: removed as u64, missing }))
+    }
+
+    async fn search(&self, request: Request<proto::SearchRequest>) -> Result<Response<proto::SearchResponse>, Status> {
+        let request = request.into_inner();
+        let k = k_or_default(request.k);
+        let state = Arc::clone(&self.state);
+        let mut results = blocking(move || state.search(&[request.vector], k))
+            .await?
+            .map_err(Status::invalid_argument)?;
+        Ok(Response::new(neighbors(results.remove(0))))
+    }
+
+    async fn search_batch(&self, request: Request<proto::SearchBatchRequest>) -> Result<Response<proto::SearchBatchResponse>, Status> {
+        let request = request.into_inner();
+        let dimension = request.dimension as usize;
+        if dimension == 0 || !request.vectors.len().is_multiple_of(dimension) {
+            return Err(Status::invalid_argument(format!(
+                "{} values are not a whole number of queries of dimension {}",
+                request.vectors.len(), dimension
+            )));
+        }
+
+        let queries: Vec<Vec<f32>> = request.vectors.chunks(dimension).map(<[f32]>::to_vec).collect();
+        let k = k_or_default(request.k);
+        let state = Arc::clone(&self.state);
+        let results = blocking(move || state.search(&queries, k))
+            .await?
+            .map_err(Status::invalid_argument)?;
+        Ok(Response::new(proto::SearchBatchResponse { results: results.into_iter().map(neighbors).collect() }))
+    }
+
+    type StatsStream = ReceiverStream<Result<proto::StatsResponse, Status>>;
+
+    async fn stats(&self, request: Request<proto::StatsRequest>) -> Result<Response<Self::StatsStream>, Status> {
+        let interval = Duration::from_millis(request.into_inner().interval_ms as u64);
+        let (sender, receiver) = mpsc::channel(1);
+        let (state, shutdown) = (Arc::clone(&self.state), Arc::clone(&self.shutdown));
+
+        tokio::spawn(async move {
+            loop {
+                let state = Arc::clone(&state);
+                let report = blocking(move || stats_response(state.stats())).await;
+                // Sending fails once the client has cancelled the call
+                if sender.send(report).await.is_err() || interval.is_zero() {
+                    return;
+                }
+
+                // Wait in short steps so an open stream does not hold up shutdown
+                let deadline = Instant::now() + interval;
+                while Instant::now() < deadline {
+                    if shutdown.load(Ordering::SeqCst) {
+                        return;
+                    }
+                    tokio::time::sleep_until(deadline.min(Instant::now() + POLL_INTERVAL)).await;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+}
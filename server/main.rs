@@ -0,0 +1,127 @@
+mod config;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod routes;
+mod state;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::Duration;
+use tiny_http::{Header, Request, Server};
+use config::{ServerConfig, USAGE};
+use routes::Body;
+use state::{Index, State};
+
+/// How often idle workers check for shutdown
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+fn main() {
+    let config = match ServerConfig::parse(std::env::args().skip(1)) {
+        Ok(Some(config)) => config,
+        Ok(None) => {
+            print!("{}", USAGE);
+            return;
+        }
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if let Err(e) = run(config) {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run(config: ServerConfig) -> Result<(), String> {
+    let mut index = if config.index.exists() {
+        Index::load(&config.index)?
+    } else {
+        // The dimension is set by the first vector inserted
+        Index::new_with_distance(0, config.m, config.ef_construction, config.metric)
+    };
+    if config.ef_search.is_some() {
+        index.ef_search = config.ef_search;
+    }
+    #[cfg(not(feature = "grpc"))]
+    if config.grpc.is_some() {
+        return Err("swarc-server was built without gRPC support; rebuild it with --features grpc".to_string());
+    }
+    let nodes = index.len();
+    let state = Arc::new(State { index: RwLock::new(index), path: config.index.clone() });
+
+    let server = Arc::new(Server::http(&config.bind).map_err(|e| format!("Failed to listen on {}: {}", config.bind, e))?);
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let flag = Arc::clone(&shutdown);
+    ctrlc::set_handler(move || flag.store(true, Ordering::SeqCst))
+        .map_err(|e| format!("Failed to install the shutdown handler: {}", e))?;
+    println!("Serving {} nodes from {} on http://{} with {} threads", nodes, config.index.display(), config.bind, config.threads);
+    #[cfg(feature = "grpc")]
+    let grpc = match &config.grpc {
+        Some(addr) => {
+            let handle = grpc::spawn(addr, Arc::clone(&state), Arc::clone(&shutdown), config.max_body)?;
+            println!("Serving gRPC on {}", addr);
+            Some(handle)
+        }
+        None => None,
+    };
+
+    let workers: Vec<_> = (0..config.threads)
+        .map(|_| {
+            let (server, state, shutdown) = (Arc::clone(&server), Arc::clone(&state), Arc::clone(&shutdown));
+            thread::spawn(move || serve(&server, &state, &shutdown, config.max_body))
+        })
+        .collect();
+    for worker in workers {
+        // Reported rather than returned, so the index is still saved
+        if worker.join().is_err() {
+            eprintln!("error: A request handler panicked");
+        }
+    }
+    #[cfg(feature = "grpc")]
+    if let Some(grpc) = grpc {
+        match grpc.join() {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => eprintln!("error: {}", e),
+            Err(_) => eprintln!("error: The gRPC server panicked"),
+        }
+    }
+
+    println!("Shutting down");
+    let nodes = state.snapshot()?;
+    println!("Saved {} nodes to {}", nodes, config.index.display());
+    Ok(())
+}
+
+/// Answer requests until shutdown is requested
+fn serve(server: &Server, state: &State, shutdown: &AtomicBool, max_body: usize) {
+    while !shutdown.load(Ordering::SeqCst) {
+        match server.recv_timeout(POLL_INTERVAL) {
+            Ok(Some(request)) => respond(request, state, max_body),
+            Ok(None) => {}
+            Err(e) => eprintln!("Failed to receive a request: {}", e),
+        }
+    }
+}
+
+fn respond(mut request: Request, state: &State, max_body: usize) {
+    let length = request.body_length();
+    let response = match routes::read_body(request.as_reader(), length, max_body) {
+        Ok(body) => routes::handle(state, request.method().as_str(), request.url(), &body),
+        Err(response) => response,
+    };
+
+    let (body, content_type) = match response.body {
+        Body::Json(body) => (body.to_string(), "application/json"),
+        Body::Metrics(text) => (text, "text/plain; version=0.0.4"),
+    };
+    let content_type = Header::from_bytes("Content-Type", content_type).unwrap();
+    let reply = tiny_http::Response::from_string(body)
+        .with_status_code(response.status)
+        .with_header(content_type);
+    if let Err(e) = request.respond(reply) {
+        eprintln!("Failed to send a response: {}", e);
+    }
+}
@@ -0,0 +1,173 @@
+use std::io::Read;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use swarc::jsonl::JsonlRecord;
+use crate::state::{Neighbor, State};
+
+/// Neighbors returned when a search does not ask for a number
+pub const DEFAULT_K: usize = 10;
+
+/// Status code and body of a response
+#[derive(Debug)]
+pub struct Response {
+    pub status: u16,
+    pub body: Body,
+}
+
+/// JSON for every endpoint but `/metrics`, which is Prometheus text
+#[derive(Debug)]
+pub enum Body {
+    Json(Value),
+    Metrics(String),
+}
+
+impl Response {
+    pub fn error(status: u16, message: impl Into<String>) -> Self {
+        Self { status, body: Body::Json(json!({ "error": message.into() })) }
+    }
+}
+
+/// One record or a list of them
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Records {
+    One(JsonlRecord<Value>),
+    Many(Vec<JsonlRecord<Value>>),
+}
+
+#[derive(Deserialize)]
+struct RemoveRequest {
+    ids: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct SearchRequest {
+    vector: Vec<f32>,
+    k: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct BatchSearchRequest {
+    vectors: Vec<Vec<f32>>,
+    k: Option<usize>,
+}
+
+/// Route a request to its endpoint
+pub fn handle(state: &State, method: &str, path: &str, body: &[u8]) -> Response {
+    let path = path.split('?').next().unwrap_or(path);
+    if (method, path) == ("GET", "/metrics") {
+        return Response { status: 200, body: Body::Metrics(state.metrics()) };
+    }
+    let result = match (method, path) {
+        ("GET", "/stats") => Ok(stats(state)),
+        ("POST", "/insert") => parse(body).map(|records| insert(state, records, false)),
+        ("POST", "/upsert") => parse(body).map(|records| insert(state, records, true)),
+        ("POST", "/remove") => parse(body).map(|request| remove(state, request)),
+        ("POST", "/search") => parse(body).and_then(|request| search(state, request)),
+        ("POST", "/search/batch") => parse(body).and_then(|request| search_batch(state, request)),
+        ("POST", "/snapshot") => state
+            .snapshot()
+            .map(|nodes| json!({ "nodes": nodes, "path": state.path.display().to_string() }))
+            .map_err(|e| Response::error(500, e)),
+        (_, "/stats" | "/metrics" | "/insert" | "/upsert" | "/remove" | "/search" | "/search/batch" | "/snapshot") => {
+            Err(Response::error(405, format!("{} does not accept {}", path, method)))
+        }
+        _ => Err(Response::error(404, format!("No endpoint at {}", path))),
+    };
+
+    match result {
+        Ok(body) => Response { status: 200, body: Body::Json(body) },
+        Err(response) => response,
+    }
+}
+
+/// Read a request body of at most `max_body` bytes, whose length may be declared
+pub fn read_body(reader: impl Read, length: Option<usize>, max_body: usize) -> Result<Vec<u8>, Response> {
+    let too_large = || Response::error(413, format!("Request body is larger than {}MB", max_body >> 20));
+    if length.is_some_and(|length| length > max_body) {
+        return Err(too_large());
+    }
+
+    // Bodies without a declared length are read until they pass the limit
+    let mut body = Vec::with_capacity(length.unwrap_or(0));
+    reader
+        .take(max_body as u64 + 1)
+        .read_to_end(&mut body)
+        .map_err(|e| Response::error(400, format!("Failed to read the request body: {}", e)))?;
+    if body.len() > max_body {
+        return Err(too_large());
+    }
+    Ok(body)
+}
+
+fn parse<T: DeserializeOwned>(body: &[u8]) -> Result<T, Response> {
+    serde_json::from_slice(body).map_err(|e| Response::error(400, format!("Invalid request body: {}", e)))
+}
+
+fn insert(state: &State, records: Records, upsert: bool) -> Value {
+    let records = match records {
+        Records::One(record) => vec![record],
+        Records::Many(records) => records,
+    };
+    let outcome = state.insert(records, upsert);
+    let errors: Vec<Value> = outcome.errors.into_iter().map(|(id, error)| json!({ "id": id, "error": error })).collect();
+
+    let mut body = json!({ "inserted": outcome.inserted, "errors": errors });
+    if upsert {
+        body["replaced"] = json!(outcome.replaced);
+    }
+    body
+}
+
+fn remove(state: &State, request: RemoveRequest) -> Value {
+    let (removed, missing) = state.remove(request.ids);
+    json!({ "removed": removed, "missing": missing })
+}
+
+fn neighbors(results: Vec<Neighbor>) -> Value {
+    results
+        .into_iter()
+        .map(|(id, distance, document)| json!({ "id": id, "distance": distance, "document": document }))
+        .collect()
+}
+
+fn search(state: &State, request: SearchRequest) -> Result<Value, Response> {
+    let mut results = state
+        .search(&[request.vector], request.k.unwrap_or(DEFAULT_K))
+        .map_err(|e| Response::error(400, e))?;
+    Ok(json!({ "results": neighbors(results.remove(0)) }))
+}
+
+fn search_batch(state: &State, request: BatchSearchRequest) -> Result<Value, Response> {
+    let results = state
+        .search(&request.vectors, request.k.unwrap_or(DEFAULT_K))
+        .map_err(|e| Response::error(400, e))?;
+    let results: Vec<Value> = results.into_iter().map(neighbors).collect();
+    Ok(json!({ "results": results }))
+}
+
+fn stats(state: &State) -> Value {
+    let stats = state.stats();
+    let memory = &stats.memory;
+    json!({
+        "nodes": stats.nodes,
+        "dimension": stats.dimension,
+        "metric": stats.metric,
+        "m": stats.m,
+        "m_max": stats.m_max,
+        "ef_construction": stats.ef_construction,
+        "ef_search": stats.ef_search,
+        "nodes_per_layer": stats.nodes_per_layer,
+        "memory": {
+            "total": memory.total(),
+            "vectors": memory.vectors,
+            "quantized": memory.quantized,
+            "adjacency": memory.adjacency(),
+            "nodes": memory.nodes,
+            "id_map": memory.id_map,
+            "documents": memory.documents,
+        },
+        "path": state.path.display().to_string(),
+    })
+}
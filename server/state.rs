@@ -0,0 +1,192 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use rayon::prelude::*;
+use serde_json::Value;
+use swarc::jsonl::JsonlRecord;
+use swarc::memory::MemoryUsage;
+use swarc::{Document, HNSWIndex};
+
+/// Documents are arbitrary JSON, as sent by clients
+pub type Index = HNSWIndex<Value>;
+
+/// A search result: id, distance and document
+pub type Neighbor = (String, f32, Option<Value>);
+
+/// The served index and the file it is snapshotted to
+///
+/// Searches share the index; inserts and removes take it exclusively. A handler that
+/// panics while holding the lock poisons it; the index is used as it was left rather
+/// than failing every later request.
+pub struct State {
+    pub index: RwLock<Index>,
+    pub path: PathBuf,
+}
+
+/// Outcome of inserting a list of records
+#[derive(Debug, Default)]
+pub struct InsertOutcome {
+    pub inserted: usize,
+    pub replaced: usize, // existing nodes replaced by an upsert
+    pub errors: Vec<(String, String)>, // id and why the record was not inserted
+}
+
+/// Parameters and size of the index
+#[derive(Debug)]
+pub struct Stats {
+    pub nodes: usize,
+    pub dimension: Option<usize>,
+    pub metric: String,
+    pub m: usize,
+    pub m_max: usize,
+    pub ef_construction: usize,
+    pub ef_search: Option<usize>,
+    pub nodes_per_layer: Vec<usize>,
+    pub memory: MemoryUsage,
+}
+
+impl State {
+    /// Share the index, recovering it from a poisoned lock
+    fn read(&self) -> RwLockReadGuard<'_, Index> {
+        self.index.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Take the index exclusively, recovering it from a poisoned lock
+    fn write(&self) -> RwLockWriteGuard<'_, Index> {
+        self.index.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Insert records one at a time, replacing existing ids when `upsert` is set
+    ///
+    /// Records that cannot be inserted are reported by id; the others are still inserted.
+    pub fn insert(&self, records: Vec<JsonlRecord<Value>>, upsert: bool) -> InsertOutcome {
+        let mut index = self.write();
+        let mut outcome = InsertOutcome::default();
+        for record in records {
+            // Checked before an upsert removes the old node, so a bad record never loses it
+            if let Err(e) = check_vector(&record.vector, index.dimension()) {
+                outcome.errors.push((record.id, e));
+                continue;
+            }
+            // The old node is kept aside and put back if the new one cannot be inserted
+            let old = match index.get_original_embedding(&record.id) {
+                Some(embedding) if upsert => {
+                    let embedding = embedding.into_owned();
+                    index.remove(&record.id).ok().map(|document| (embedding, document))
+                }
+                _ => None,
+            };
+
+            let document = record.document.map(|data| Document { id: record.id.clone(), data });
+            match index.insert(record.id.clone(), record.vector, document) {
+                Ok(()) => {
+                    outcome.inserted += 1;
+                    outcome.replaced += old.is_some() as usize;
+                }
+                Err(e) => {
+                    let e = match old.map(|(embedding, document)| index.insert(record.id.clone(), embedding, document)) {
+                        Some(Err(restore)) => format!("{}; the old node could not be restored: {}", e, restore),
+                        _ => e,
+                    };
+                    outcome.errors.push((record.id, e));
+                }
+            }
+        }
+        outcome
+    }
+
+    /// Remove nodes, returning how many were removed and the ids that were not found
+    pub fn remove(&self, ids: Vec<String>) -> (usize, Vec<String>) {
+        let mut index = self.write();
+        let mut removed = 0;
+        let mut missing = Vec::new();
+        for id in ids {
+            match index.remove(&id) {
+                Ok(_) => removed += 1,
+                Err(_) => missing.push(id),
+            }
+        }
+        (removed, missing)
+    }
+
+    /// Search for the k nearest neighbors of each query, in parallel
+    ///
+    /// Fails without searching if any query has the wrong dimension.
+    pub fn search(&self, queries: &[Vec<f32>], k: usize) -> Result<Vec<Vec<Neighbor>>, String> {
+        let index = self.read();
+        for (i, query) in queries.iter().enumerate() {
+            check_vector(query, index.dimension())
+                .map_err(|e| if queries.len() > 1 { format!("Query {}: {}", i, e) } else { e })?;
+        }
+        Ok(queries
+            .par_iter()
+            .map(|query| {
+                index
+                    .search(query, k)
+                    .into_iter()
+                    .map(|(id, distance, document)| (id, distance, document.map(|d| d.data.clone())))
+                    .collect()
+            })
+            .collect())
+    }
+
+    /// Parameters, layer sizes and memory of the index
+    pub fn stats(&self) -> Stats {
+        let index = self.read();
+        Stats {
+            nodes: index.len(),
+            dimension: index.dimension(),
+            metric: format!("{:?}", index.distance_metric),
+            m: index.m,
+            m_max: index.m_max,
+            ef_construction: index.ef_construction,
+            ef_search: index.ef_search,
+            nodes_per_layer: index.layer_counts(),
+            memory: index.memory_usage(),
+        }
+    }
+
+    /// Index metrics and size in Prometheus text format
+    pub fn metrics(&self) -> String {
+        let index = self.read();
+        let mut text = index.metrics().to_prometheus("swarc");
+        let gauges = [
+            ("swarc_nodes", "Nodes in the index.", index.len()),
+            ("swarc_memory_bytes", "Estimated memory used by the index.", index.memory_usage().total()),
+        ];
+        for (name, help, value) in gauges {
+            text.push_str(&format!("# HELP {} {}\n# TYPE {} gauge\n{} {}\n", name, help, name, name, value));
+        }
+        text
+    }
+
+    /// Save the index, returning the number of nodes saved
+    ///
+    /// The index is written next to its file and then renamed over it, so a failed save
+    /// never leaves a truncated snapshot.
+    pub fn snapshot(&self) -> Result<usize, String> {
+        let index = self.read();
+        let mut temporary = self.path.clone().into_os_string();
+        temporary.push(".tmp");
+        index.save(&temporary)?;
+        fs::rename(&temporary, &self.path)
+            .map_err(|e| format!("Failed to replace '{}': {}", self.path.display(), e))?;
+        Ok(index.len())
+    }
+}
+
+/// Check that a vector can be inserted into or searched in an index of the given dimension
+fn check_vector(vector: &[f32], dimension: Option<usize>) -> Result<(), String> {
+    if vector.is_empty() {
+        return Err("Vector is empty".to_string());
+    }
+    if vector.iter().any(|value| !value.is_finite()) {
+        return Err("Vector has a non-finite value".to_string());
+    }
+    match dimension {
+        Some(dimension) if vector.len() != dimension => {
+            Err(format!("Vector has dimension {}, index expects {}", vector.len(), dimension))
+        }
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,465 @@
+use serde::{Deserialize, Serialize};
+
+/// Marks a slot whose vector is not held in memory
+const NO_ROW: u32 = u32::MAX;
+
+/// Fixed-dimension rows stored back to back in one buffer, indexed by node slot
+///
+/// Holds f32 vectors by default; indexes also keep quantized codes, 16-bit floats and
+/// per-node values (in rows of one) this way. Slots without a value (for instance vectors
+/// dropped after quantization) have no row. Neither do slots past the end of the slot
+/// table, so an arena that is never set stays empty. Rows freed by removals are reused by
+/// later vectors rather than compacted.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VectorArena<E = f32> {
+    dimension: usize,
+    data: Vec<E>,
+    rows: Vec<u32>, // row of each slot in data, or NO_ROW
+    #[serde(default)]
+    free: Vec<u32>, // rows in data that no slot uses
+}
+
+impl<E: Copy> VectorArena<E> {
+    /// Length of every stored vector, or 0 before the first one is stored
+    pub fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    /// Number of slots, with or without a vector
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Check if there are no slots
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Number of vectors held in the buffer
+    pub fn rows(&self) -> usize {
+        self.buffer_rows() - self.free.len()
+    }
+
+    /// Get the vector of a slot, if it is held in memory
+    pub fn get(&self, slot: usize) -> Option<&[E]> {
+        match self.rows.get(slot) {
+            Some(&row) if row != NO_ROW => {
+                let start = row as usize * self.dimension;
+                Some(&self.data[start..start + self.dimension])
+            }
+            _ => None,
+        }
+    }
+
+    /// Get the value of a slot in an arena of one-element rows
+    pub fn value(&self, slot: usize) -> Option<E> {
+        self.get(slot).and_then(|row| row.first().copied())
+    }
+
+    /// Check that a vector fits the arena's dimension
+    pub fn check_dimension(&self, vector: &[E]) -> Result<(), String> {
+        if self.dimension != 0 && vector.len() != self.dimension {
+            return Err(format!("Embedding has dimension {}, index expects {}", vector.len(), self.dimension));
+        }
+        Ok(())
+    }
+
+    /// Add a slot at the end, holding `vector` if it is non-empty
+    pub fn push(&mut self, vector: &[E]) -> Result<(), String> {
+        self.rows.push(NO_ROW);
+        let slot = self.rows.len() - 1;
+        self.set(slot, vector).inspect_err(|_| {
+            self.rows.pop();
+        })
+    }
+
+    /// Replace the vector of a slot, dropping it if `vector` is empty
+    ///
+    /// The slot table grows to reach `slot` if it is past the end.
+    pub fn set(&mut self, slot: usize, vector: &[E]) -> Result<(), String> {
+        if vector.is_empty() {
+            self.drop_row(slot);
+            return Ok(());
+        }
+        self.check_dimension(vector)?;
+        self.dimension = vector.len();
+        if slot >= self.rows.len() {
+            self.rows.resize(slot + 1, NO_ROW);
+        }
+
+        match self.rows[slot] {
+            NO_ROW => match self.free.pop() {
+                Some(row) => {
+                    self.rows[slot] = row;
+                    let start = row as usize * self.dimension;
+                    self.data[start..start + self.dimension].copy_from_slice(vector);
+                }
+                None => {
+                    self.rows[slot] = self.buffer_rows() as u32;
+                    self.data.extend_from_slice(vector);
+                }
+            },
+            row => {
+                let start = row as usize * self.dimension;
+                self.data[start..start + self.dimension].copy_from_slice(vector);
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove a slot out of `slots` by moving the last slot into its place
+    ///
+    /// The removed slot's row is kept for the next vector stored.
+    pub fn swap_remove(&mut self, slot: usize, slots: usize) {
+        self.drop_row(slot);
+        let last = slots - 1;
+        if let Some(&row) = self.rows.get(last).filter(|_| slot < last) {
+            self.rows[slot] = row;
+        }
+        self.rows.truncate(last);
+    }
+
+    /// Drop every vector but keep the slots, releasing the buffer
+    pub fn clear_rows(&mut self) {
+        self.rows.iter_mut().for_each(|row| *row = NO_ROW);
+        self.data = Vec::new();
+        self.free = Vec::new();
+    }
+
+    /// Remove every slot, so that vectors of any dimension can be stored again
+    pub fn clear(&mut self) {
+        self.dimension = 0;
+        self.rows = Vec::new();
+        self.data = Vec::new();
+        self.free = Vec::new();
+    }
+
+    /// Bytes allocated for the buffer, the slot table and the free rows
+    pub fn heap_size(&self) -> usize {
+        self.data.capacity() * std::mem::size_of::<E>() + (self.rows.capacity() + self.free.capacity()) * std::mem::size_of::<u32>()
+    }
+
+    /// Check that rows point inside the buffer and that no two slots share one
+    pub(crate) fn validate(&self, slots: usize) -> Result<(), String> {
+        if self.rows.len() > slots {
+            return Err(format!("Vector arena has {} slots for {} nodes", self.rows.len(), slots));
+        }
+        if self.dimension == 0 && !self.data.is_empty() || !self.data.len().is_multiple_of(self.dimension.max(1)) {
+            return Err("Vector arena buffer does not hold whole vectors".to_string());
+        }
+        // Every row in the buffer belongs to exactly one slot or is free
+        let mut used = vec![false; self.buffer_rows()];
+        for &row in self.rows.iter().filter(|&&row| row != NO_ROW).chain(&self.free) {
+            match used.get_mut(row as usize) {
+                Some(used) if !*used => *used = true,
+                _ => return Err(format!("Vector arena row {} is out of range or shared", row)),
+            }
+        }
+        if used.contains(&false) {
+            return Err("Vector arena holds rows that are neither used nor free".to_string());
+        }
+        Ok(())
+    }
+
+    /// Number of rows in the buffer, used or free
+    fn buffer_rows(&self) -> usize {
+        self.data.len() / self.dimension.max(1)
+    }
+
+    /// Free a slot's row for reuse
+    fn drop_row(&mut self, slot: usize) {
+        if let Some(row) = self.rows.get_mut(slot).filter(|row| **row != NO_ROW) {
+            self.free.push(*row);
+            *row = NO_ROW;
+        }
+    }
+}
+
+/// Neighbor lists of every node in fixed-capacity u32 blocks
+///
+/// Each block is a length followed by room for `capacity` neighbor slots, as in hnswlib.
+/// Layer 0 blocks sit back to back in one buffer indexed by slot. Only about one node in
+/// `m` reaches layer 1, so upper layer blocks are packed into a separate pool and each
+/// node records where its own start. Blocks of removed nodes are reused by later nodes.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Graph {
+    layer0_capacity: usize,
+    upper_capacity: usize,
+    layer0: Vec<u32>,
+    layers: Vec<u32>, // number of layers of each node
+    upper_offsets: Vec<u32>, // start of each node's upper layer blocks in upper
+    upper: Vec<u32>,
+    #[serde(default)]
+    free_upper: Vec<(u32, u32)>, // start and block count of each unused run of blocks in upper
+}
+
+impl Graph {
+    /// Create a graph with room for `layer0_capacity` neighbors on layer 0 and
+    /// `upper_capacity` on the layers above
+    pub fn new(layer0_capacity: usize, upper_capacity: usize) -> Self {
+        Self { layer0_capacity, upper_capacity, ..Self::default() }
+    }
+
+    /// Number of nodes
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Check if there are no nodes
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Maximum number of neighbors a node keeps on a layer
+    pub fn capacity(&self, layer: usize) -> usize {
+        if layer == 0 { self.layer0_capacity } else { self.upper_capacity }
+    }
+
+    /// Number of layers a node is linked on, which is its level plus one
+    pub fn layers(&self, slot: usize) -> usize {
+        self.layers.get(slot).map_or(0, |&layers| layers as usize)
+    }
+
+    /// Highest number of layers of any node
+    pub fn max_layers(&self) -> usize {
+        self.layers.iter().max().map_or(0, |&layers| layers as usize)
+    }
+
+    /// Neighbors of a node on a layer, empty if the node is not on that layer
+    pub fn neighbors(&self, slot: usize, layer: usize) -> &[u32] {
+        match self.block_range(slot, layer) {
+            Some((buffer, start)) => &buffer[start + 1..start + 1 + buffer[start] as usize],
+            None => &[],
+        }
+    }
+
+    /// Neighbors of a node on every layer, bottom layer first
+    pub fn connections(&self, slot: usize) -> Vec<Vec<usize>> {
+        (0..self.layers(slot))
+            .map(|layer| self.neighbors(slot, layer).iter().map(|&n| n as usize).collect())
+            .collect()
+    }
+
+    /// Add a node linked on `layers` layers, with no neighbors yet
+    ///
+    /// Its upper layer blocks take the first free run that is long enough, if any.
+    pub fn push(&mut self, layers: usize) {
+        self.layer0.resize(self.layer0.len() + 1 + self.layer0_capacity, 0);
+        self.layers.push(layers as u32);
+
+        let blocks = layers.saturating_sub(1) as u32;
+        let offset = match self.free_upper.iter().position(|&(_, free)| blocks > 0 && free >= blocks) {
+            Some(i) => {
+                let (start, free) = self.free_upper[i];
+                if free == blocks {
+                    self.free_upper.swap_remove(i);
+                } else {
+                    self.free_upper[i] = (start + blocks * (1 + self.upper_capacity as u32), free - blocks);
+                }
+                start
+            }
+            None => {
+                let start = self.upper.len() as u32;
+                self.upper.resize(self.upper.len() + blocks as usize * (1 + self.upper_capacity), 0);
+                start
+            }
+        };
+        self.upper_offsets.push(offset);
+    }
+
+    /// Replace a node's neighbors on a layer, growing every block if they do not fit
+    pub fn set_neighbors(&mut self, slot: usize, layer: usize, neighbors: &[usize]) {
+        if layer >= self.layers(slot) {
+            return;
+        }
+        if neighbors.len() > self.capacity(layer) {
+            let (layer0, upper) = if layer == 0 {
+                (neighbors.len(), self.upper_capacity)
+            } else {
+                (self.layer0_capacity, neighbors.len())
+            };
+            self.set_capacity(layer0, upper);
+        }
+
+        let block = self.block_mut(slot, layer);
+        block[0] = neighbors.len() as u32;
+        for (link, &neighbor) in block[1..].iter_mut().zip(neighbors) {
+            *link = neighbor as u32;
+        }
+    }
+
+    /// Append a neighbor on a layer, returning false if the block is full
+    pub fn add_neighbor(&mut self, slot: usize, layer: usize, neighbor: usize) -> bool {
+        if layer >= self.layers(slot) {
+            return true;
+        }
+        let block = self.block_mut(slot, layer);
+        let len = block[0] as usize;
+        if len + 1 >= block.len() {
+            return false;
+        }
+        block[1 + len] = neighbor as u32;
+        block[0] += 1;
+        true
+    }
+
+    /// Remove a node and every link to it by moving the last node into its slot
+    ///
+    /// Links to the last node are renamed in the same pass over the blocks, and the removed
+    /// node's upper layer blocks are kept for the next node that needs as many.
+    pub fn swap_remove(&mut self, slot: usize) {
+        let last = self.len() - 1;
+        let blocks = self.layers(slot).saturating_sub(1);
+        if blocks > 0 {
+            let start = self.upper_offsets[slot] as usize;
+            self.upper[start..start + blocks * (1 + self.upper_capacity)].fill(0);
+            self.free_upper.push((start as u32, blocks as u32));
+        }
+
+        let (removed, moved) = (slot as u32, last as u32);
+        let layer0_block = 1 + self.layer0_capacity;
+        for (buffer, block) in [(&mut self.layer0, layer0_block), (&mut self.upper, 1 + self.upper_capacity)] {
+            for block in buffer.chunks_exact_mut(block) {
+                let mut kept = 0;
+                for i in 1..=block[0] as usize {
+                    let neighbor = block[i];
+                    if neighbor != removed {
+                        kept += 1;
+                        block[kept] = if neighbor == moved { removed } else { neighbor };
+                    }
+                }
+                block[0] = kept as u32;
+            }
+        }
+
+        self.layer0.copy_within(last * layer0_block..(last + 1) * layer0_block, slot * layer0_block);
+        self.layer0.truncate(last * layer0_block);
+        self.layers.swap_remove(slot);
+        self.upper_offsets.swap_remove(slot);
+    }
+
+    /// Remove every node
+    pub fn clear(&mut self) {
+        *self = Self::new(self.layer0_capacity, self.upper_capacity);
+    }
+
+    /// Bytes allocated for the blocks of each layer, bottom layer first
+    ///
+    /// Layer 0 also carries the per-node layer counts and offsets, and layer 1 any spare
+    /// or freed room in the upper layer pool.
+    pub fn layer_sizes(&self) -> Vec<usize> {
+        let word = std::mem::size_of::<u32>();
+        let mut sizes = vec![0; self.max_layers().max(1)];
+        sizes[0] = (self.layer0.capacity() + self.layers.capacity() + self.upper_offsets.capacity()) * word;
+        for &layers in &self.layers {
+            for size in &mut sizes[1..layers as usize] {
+                *size += (1 + self.upper_capacity) * word;
+            }
+        }
+        if sizes.len() > 1 {
+            let free: usize = self.free_upper.iter().map(|&(_, blocks)| blocks as usize * (1 + self.upper_capacity)).sum();
+            sizes[1] += (self.upper.capacity() - self.upper.len() + free + self.free_upper.capacity() * 2) * word;
+        }
+        sizes
+    }
+
+    /// Move every block in place to new capacities, which must fit the existing lists
+    pub fn set_capacity(&mut self, layer0_capacity: usize, upper_capacity: usize) {
+        restride(&mut self.layer0, 1 + self.layer0_capacity, 1 + layer0_capacity);
+        let (old, new) = (1 + self.upper_capacity as u32, 1 + upper_capacity as u32);
+        restride(&mut self.upper, old as usize, new as usize);
+        for offset in self.upper_offsets.iter_mut().chain(self.free_upper.iter_mut().map(|(start, _)| start)) {
+            *offset = *offset / old * new;
+        }
+        self.layer0_capacity = layer0_capacity;
+        self.upper_capacity = upper_capacity;
+    }
+
+    /// Check the buffer sizes and that every link points at an existing node
+    pub(crate) fn validate(&self, slots: usize) -> Result<(), String> {
+        if self.layers.len() != slots || self.upper_offsets.len() != slots {
+            return Err(format!("Graph has {} nodes, index has {}", self.layers.len(), slots));
+        }
+        if self.layer0.len() != slots * (1 + self.layer0_capacity) {
+            return Err("Graph layer 0 buffer has the wrong size".to_string());
+        }
+
+        // Every upper layer block belongs to at most one node or free run
+        let block = 1 + self.upper_capacity;
+        if !self.upper.len().is_multiple_of(block) {
+            return Err("Graph upper layer buffer does not hold whole blocks".to_string());
+        }
+        let mut used = vec![false; self.upper.len() / block];
+        let runs = (0..slots)
+            .map(|slot| (self.upper_offsets[slot] as usize, self.layers(slot).saturating_sub(1)))
+            .chain(self.free_upper.iter().map(|&(start, blocks)| (start as usize, blocks as usize)));
+        for (start, blocks) in runs {
+            if !start.is_multiple_of(block) || start / block + blocks > used.len() {
+                return Err("Graph upper layer blocks are out of range".to_string());
+            }
+            for used in &mut used[start / block..start / block + blocks] {
+                if *used {
+                    return Err("Graph upper layer blocks are shared".to_string());
+                }
+                *used = true;
+            }
+        }
+
+        for slot in 0..slots {
+            if self.layers(slot) == 0 {
+                return Err(format!("Graph node {} has a corrupt layer count", slot));
+            }
+            for layer in 0..self.layers(slot) {
+                let (buffer, start) = self.block_range(slot, layer).unwrap();
+                if buffer[start] as usize > self.capacity(layer) {
+                    return Err(format!("Graph node {} has too many neighbors on layer {}", slot, layer));
+                }
+                if self.neighbors(slot, layer).iter().any(|&n| n as usize >= slots) {
+                    return Err(format!("Graph node {} links to a node that does not exist", slot));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Buffer holding a node's block on a layer and the block's start in it
+    fn block_range(&self, slot: usize, layer: usize) -> Option<(&[u32], usize)> {
+        if layer >= self.layers(slot) {
+            return None;
+        }
+        if layer == 0 {
+            Some((&self.layer0, slot * (1 + self.layer0_capacity)))
+        } else {
+            Some((&self.upper, self.upper_offsets[slot] as usize + (layer - 1) * (1 + self.upper_capacity)))
+        }
+    }
+
+    fn block_mut(&mut self, slot: usize, layer: usize) -> &mut [u32] {
+        if layer == 0 {
+            let start = slot * (1 + self.layer0_capacity);
+            &mut self.layer0[start..start + 1 + self.layer0_capacity]
+        } else {
+            let start = self.upper_offsets[slot] as usize + (layer - 1) * (1 + self.upper_capacity);
+            &mut self.upper[start..start + 1 + self.upper_capacity]
+        }
+    }
+}
+
+/// Move the fixed-size blocks of a buffer in place from one size to another, truncating or
+/// zero-padding each block
+fn restride(buffer: &mut Vec<u32>, old: usize, new: usize) {
+    let blocks = buffer.len() / old;
+    if new > old {
+        // Back to front, so no block is overwritten before it has moved
+        buffer.resize(blocks * new, 0);
+        for block in (0..blocks).rev() {
+            buffer.copy_within(block * old..(block + 1) * old, block * new);
+            buffer[block * new + old..(block + 1) * new].fill(0);
+        }
+    } else if new < old {
+        for block in 0..blocks {
+            buffer.copy_within(block * old..block * old + new, block * new);
+        }
+        buffer.truncate(blocks * new);
+    }
+}
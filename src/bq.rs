@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+use crate::index::HNSWIndex;
+use crate::quantization::Quantizer;
+use crate::simd;
+use crate::types::Distance;
+
+/// Compresses vectors to one sign bit per dimension, compared by Hamming distance
+///
+/// Bit `i` of the code is set when component `i` is positive, packed eight to a byte with
+/// the lowest bit first.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BinaryQuantizer {
+    pub dimension: usize,
+}
+
+impl BinaryQuantizer {
+    /// Create a quantizer for vectors of the given dimension
+    pub fn new(dimension: usize) -> Self {
+        Self { dimension }
+    }
+
+    /// Size in bytes of one encoded vector
+    pub fn code_size(&self) -> usize {
+        self.dimension.div_ceil(8)
+    }
+
+    /// Encode a vector as its sign bits
+    pub fn encode(&self, vector: &[f32]) -> Result<Vec<u8>, String> {
+        if vector.len() != self.dimension {
+            return Err(format!("Vector has dimension {}, quantizer expects {}", vector.len(), self.dimension));
+        }
+
+        Ok(vector
+            .chunks(8)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .enumerate()
+                    .fold(0u8, |byte, (bit, &value)| if value > 0.0 { byte | (1 << bit) } else { byte })
+            })
+            .collect())
+    }
+
+    /// Reconstruct a unit-length vector with components of `±1 / sqrt(dimension)`
+    pub fn decode(&self, codes: &[u8]) -> Vec<f32> {
+        let mut vector = Vec::with_capacity(self.dimension);
+        self.decode_into(codes, &mut vector);
+        vector
+    }
+
+    /// Reconstruct a unit-length sign vector into a reusable buffer
+    pub fn decode_into(&self, codes: &[u8], out: &mut Vec<f32>) {
+        out.clear();
+        let magnitude = 1.0 / (self.dimension.max(1) as f32).sqrt();
+        out.extend((0..self.dimension).map(|i| {
+            if codes[i / 8] & (1 << (i % 8)) != 0 { magnitude } else { -magnitude }
+        }));
+    }
+
+    /// Number of dimensions whose signs differ
+    pub fn hamming(&self, a: &[u8], b: &[u8]) -> u32 {
+        simd::hamming(a, b)
+    }
+}
+
+impl<T, D: Distance> HNSWIndex<T, D> {
+    /// Store every vector as sign bits and traverse the graph by Hamming distance
+    ///
+    /// Codes take one bit per dimension, 32 times less than f32 vectors. Searches rescore
+    /// the final candidates (`rerank_candidates`, or just the top `k` if unset) against the
+    /// full-precision vectors, so keep them in memory or move them to disk with
+    /// `spill_vectors`; with `keep_full_precision` unset they are dropped and searches
+    /// return Hamming distances. Fails if the index already stores half precision or another
+    /// quantizer's codes.
+    pub fn quantize_binary(&mut self, keep_full_precision: bool) -> Result<(), String> {
+        self.check_storage_mode("binary")?;
+        let vectors = self.full_vectors()?;
+        let dimension = vectors.first().map(|v| v.len()).ok_or_else(|| "Cannot quantize an empty index".to_string())?;
+        let quantizer = BinaryQuantizer::new(dimension);
+
+        let codes = vectors.iter().map(|v| quantizer.encode(v)).collect::<Result<Vec<_>, _>>()?;
+        drop(vectors);
+
+        self.apply_codes(Quantizer::Binary(quantizer), codes, keep_full_precision)
+    }
+}
@@ -0,0 +1,286 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use rayon::prelude::*;
+use crate::arena::VectorArena;
+use crate::simd;
+use crate::types::{Distance, DistanceMetric, Document, VectorIndex};
+
+/// Exact nearest neighbor index that compares the query with every stored vector
+///
+/// Serves as ground truth when measuring the recall of `HNSWIndex`, and as a fallback for
+/// collections small or filtered enough that a full scan is cheap. Searches are spread
+/// across threads with rayon.
+#[derive(Debug)]
+pub struct FlatIndex<T, D = DistanceMetric> {
+    ids: Vec<String>,
+    documents: Vec<Option<Document<T>>>,
+    vectors: VectorArena, // indexed like ids, normalized when the metric asks for it
+    id_to_index: HashMap<String, usize>,
+    pub distance_metric: D,
+}
+
+impl<T> FlatIndex<T> {
+    /// Create a new flat index with Euclidean distance
+    pub fn new(_dim: usize) -> Self {
+        Self::new_with_distance(_dim, DistanceMetric::Euclidean)
+    }
+}
+
+impl<T, D: Distance> FlatIndex<T, D> {
+    /// Create a new flat index with specified distance metric
+    pub fn new_with_distance(_dim: usize, distance_metric: D) -> Self {
+        Self {
+            ids: Vec::new(),
+            documents: Vec::new(),
+            vectors: VectorArena::default(),
+            id_to_index: HashMap::new(),
+            distance_metric,
+        }
+    }
+
+    /// Calculate distance between two vectors using the configured distance metric
+    pub fn distance(&self, a: &[f32], b: &[f32]) -> f32 {
+        self.distance_metric.distance(a, b)
+    }
+
+    /// Normalize a vector if the metric asks for it
+    fn prepare<'a>(&self, vector: &'a [f32]) -> Cow<'a, [f32]> {
+        if self.distance_metric.normalizes() {
+            let mut vector = vector.to_vec();
+            simd::normalize(&mut vector);
+            Cow::Owned(vector)
+        } else {
+            Cow::Borrowed(vector)
+        }
+    }
+
+    /// Check that a vector fits the index's dimension, and that the metric can compare
+    /// vectors of its length when it is the first
+    fn check_dimension(&self, embedding: &[f32]) -> Result<(), String> {
+        self.vectors.check_dimension(embedding)?;
+        if self.vectors.dimension() == 0 {
+            self.distance_metric.validate(embedding.len())?;
+        }
+        Ok(())
+    }
+
+    /// Insert a new node into the index
+    pub fn insert(&mut self, id: String, embedding: Vec<f32>, document: Option<Document<T>>) -> Result<(), String> {
+        if self.id_to_index.contains_key(&id) {
+            return Err(format!("Node with id '{}' already exists", id));
+        }
+        if embedding.is_empty() {
+            return Err(format!("Node '{}' has an empty embedding", id));
+        }
+        self.check_dimension(&embedding)?;
+
+        self.vectors.push(&self.prepare(&embedding))?;
+        self.id_to_index.insert(id.clone(), self.ids.len());
+        self.ids.push(id);
+        self.documents.push(document);
+        Ok(())
+    }
+
+    /// Insert multiple nodes, preparing their vectors in parallel
+    ///
+    /// The whole batch is rejected if an id is repeated or already present, or if the
+    /// vectors do not share the index's dimension.
+    pub fn insert_parallel(&mut self, items: Vec<(String, Vec<f32>, Option<Document<T>>)>) -> Result<Vec<Result<(), String>>, String>
+    where
+        T: Send + Sync,
+        D: Sync,
+    {
+        let mut seen_ids = std::collections::HashSet::new();
+        for (id, embedding, _) in &items {
+            if !seen_ids.insert(id) {
+                return Err(format!("Duplicate ID found: '{}'", id));
+            }
+            if self.id_to_index.contains_key(id) {
+                return Err(format!("Node with id '{}' already exists in index", id));
+            }
+            if embedding.is_empty() {
+                return Err(format!("Node '{}' has an empty embedding", id));
+            }
+            self.check_dimension(embedding)?;
+            if embedding.len() != items[0].1.len() {
+                return Err(format!("Embedding has dimension {}, batch expects {}", embedding.len(), items[0].1.len()));
+            }
+        }
+
+        let prepared: Vec<_> = items
+            .into_par_iter()
+            .map(|(id, embedding, document)| {
+                let embedding = self.prepare(&embedding).into_owned();
+                (id, embedding, document)
+            })
+            .collect();
+
+        let mut results = Vec::with_capacity(prepared.len());
+        for (id, embedding, document) in prepared {
+            self.vectors.push(&embedding)?;
+            self.id_to_index.insert(id.clone(), self.ids.len());
+            self.ids.push(id);
+            self.documents.push(document);
+            results.push(Ok(()));
+        }
+        Ok(results)
+    }
+
+    /// Insert multiple nodes sequentially
+    pub fn insert_multiple(&mut self, items: Vec<(String, Vec<f32>, Option<Document<T>>)>) -> Result<Vec<Result<(), String>>, String> {
+        Ok(items
+            .into_iter()
+            .map(|(id, embedding, document)| self.insert(id, embedding, document))
+            .collect())
+    }
+
+    /// Search for the k nearest neighbors
+    pub fn search(&self, query: &[f32], k: usize) -> Vec<(String, f32, Option<&Document<T>>)>
+    where
+        T: Sync,
+        D: Sync,
+    {
+        self.search_filtered(query, k, |_, _| true)
+    }
+
+    /// Search for the k nearest neighbors accepted by `filter`, which sees each node's id
+    /// and document
+    pub fn search_filtered<F>(&self, query: &[f32], k: usize, filter: F) -> Vec<(String, f32, Option<&Document<T>>)>
+    where
+        T: Sync,
+        D: Sync,
+        F: Fn(&str, Option<&Document<T>>) -> bool + Sync,
+    {
+        if k == 0 || self.ids.is_empty() || self.vectors.check_dimension(query).is_err() {
+            return Vec::new();
+        }
+
+        let query = self.prepare(query);
+        let query = query.as_ref();
+        let normalizes = self.distance_metric.normalizes();
+        let mut candidates: Vec<(usize, f32)> = (0..self.ids.len())
+            .into_par_iter()
+            .filter(|&slot| filter(&self.ids[slot], self.documents[slot].as_ref()))
+            .filter_map(|slot| {
+                let vector = self.vectors.get(slot)?;
+                let dist = if normalizes {
+                    self.distance_metric.normalized_distance(query, vector)
+                } else {
+                    self.distance_metric.distance(query, vector)
+                };
+                Some((slot, dist))
+            })
+            .collect();
+
+        // Ties are broken by slot so equidistant results come back in a stable order
+        let order = |a: &(usize, f32), b: &(usize, f32)| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0));
+        if candidates.len() > k {
+            candidates.select_nth_unstable_by(k - 1, order);
+            candidates.truncate(k);
+        }
+        candidates.sort_by(order);
+
+        candidates
+            .into_iter()
+            .map(|(slot, dist)| (self.ids[slot].clone(), dist, self.documents[slot].as_ref()))
+            .collect()
+    }
+}
+
+impl<T, D> FlatIndex<T, D> {
+    /// Remove a node from the index
+    pub fn remove(&mut self, id: &str) -> Result<Option<Document<T>>, String> {
+        let slot = match self.id_to_index.remove(id) {
+            Some(slot) => slot,
+            None => return Err(format!("Node with id '{}' not found", id)),
+        };
+
+        // Move the last node into the freed slot, so no other node is renumbered
+        self.vectors.swap_remove(slot, self.ids.len());
+        self.ids.swap_remove(slot);
+        if let Some(moved) = self.ids.get(slot) {
+            self.id_to_index.insert(moved.clone(), slot);
+        }
+        Ok(self.documents.swap_remove(slot))
+    }
+
+    /// Remove multiple nodes by their IDs
+    pub fn remove_multiple(&mut self, ids: &[&str]) -> Result<Vec<Option<Document<T>>>, String> {
+        // Verify all nodes exist before removing any
+        if let Some(id) = ids.iter().find(|id| !self.id_to_index.contains_key(**id)) {
+            return Err(format!("Node with id '{}' not found", id));
+        }
+        ids.iter().map(|id| self.remove(id)).collect()
+    }
+
+    /// Clear all nodes from the index
+    pub fn clear(&mut self) {
+        self.ids.clear();
+        self.documents.clear();
+        self.vectors.clear();
+        self.id_to_index.clear();
+    }
+
+    /// Check if a node exists in the index
+    pub fn contains(&self, id: &str) -> bool {
+        self.id_to_index.contains_key(id)
+    }
+
+    /// Get the number of nodes in the index
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Check if the index is empty
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Get a node's vector as stored for search
+    ///
+    /// Cosine vectors are stored normalized.
+    pub fn get_embedding(&self, id: &str) -> Option<&[f32]> {
+        self.id_to_index.get(id).and_then(|&slot| self.vectors.get(slot))
+    }
+
+    /// Get a node's document by ID
+    pub fn get_document(&self, id: &str) -> Option<&Document<T>> {
+        self.id_to_index.get(id).and_then(|&slot| self.documents[slot].as_ref())
+    }
+
+    /// Get all node IDs in the index
+    pub fn get_all_ids(&self) -> Vec<String> {
+        self.ids.clone()
+    }
+}
+
+impl<T: Send + Sync, D: Distance + Sync> VectorIndex<T> for FlatIndex<T, D> {
+    fn insert(&mut self, id: String, embedding: Vec<f32>, document: Option<Document<T>>) -> Result<(), String> {
+        FlatIndex::insert(self, id, embedding, document)
+    }
+
+    fn remove(&mut self, id: &str) -> Result<Option<Document<T>>, String> {
+        FlatIndex::remove(self, id)
+    }
+
+    fn search(&self, query: &[f32], k: usize) -> Vec<(String, f32, Option<&Document<T>>)> {
+        FlatIndex::search(self, query, k)
+    }
+
+    fn search_filtered(
+        &self,
+        query: &[f32],
+        k: usize,
+        filter: &(dyn Fn(&str, Option<&Document<T>>) -> bool + Sync),
+    ) -> Vec<(String, f32, Option<&Document<T>>)> {
+        FlatIndex::search_filtered(self, query, k, filter)
+    }
+
+    fn contains(&self, id: &str) -> bool {
+        FlatIndex::contains(self, id)
+    }
+
+    fn len(&self) -> usize {
+        FlatIndex::len(self)
+    }
+}
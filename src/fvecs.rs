@@ -0,0 +1,63 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use crate::npy::NpyMatrix;
+
+/// Read a `.fvecs` file, the format of the TEXMEX (SIFT, GIST) benchmark datasets
+///
+/// Each vector is stored as its dimension, a little-endian `i32`, followed by that many
+/// little-endian `f32` values. Every vector must have the same dimension.
+pub fn read_fvecs<P: AsRef<Path>>(path: P) -> Result<NpyMatrix, String> {
+    let path = path.as_ref();
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+    parse_fvecs(&bytes)
+}
+
+fn parse_fvecs(bytes: &[u8]) -> Result<NpyMatrix, String> {
+    if bytes.is_empty() {
+        return NpyMatrix::new(0, 0, Vec::new());
+    }
+    if bytes.len() < 4 {
+        return Err("Truncated .fvecs file".to_string());
+    }
+
+    let dimension = i32::from_le_bytes(bytes[..4].try_into().unwrap());
+    if dimension <= 0 {
+        return Err(format!("Invalid .fvecs dimension {}", dimension));
+    }
+    let dimension = dimension as usize;
+    let record = 4 + dimension * 4;
+    if !bytes.len().is_multiple_of(record) {
+        return Err(format!(".fvecs file of {} bytes is not a whole number of {}-dimensional vectors", bytes.len(), dimension));
+    }
+
+    let rows = bytes.len() / record;
+    let mut data = Vec::with_capacity(rows * dimension);
+    for (row, chunk) in bytes.chunks_exact(record).enumerate() {
+        let row_dimension = i32::from_le_bytes(chunk[..4].try_into().unwrap());
+        if row_dimension as usize != dimension {
+            return Err(format!("Vector {} has dimension {}, expected {}", row, row_dimension, dimension));
+        }
+        data.extend(chunk[4..].chunks_exact(4).map(|value| f32::from_le_bytes(value.try_into().unwrap())));
+    }
+
+    NpyMatrix::new(rows, dimension, data)
+}
+
+/// Write a matrix as a `.fvecs` file
+pub fn write_fvecs<P: AsRef<Path>>(path: P, matrix: &NpyMatrix) -> Result<(), String> {
+    let path = path.as_ref();
+    let file = File::create(path).map_err(|e| format!("Failed to create '{}': {}", path.display(), e))?;
+    let mut writer = BufWriter::new(file);
+
+    let write_err = |e: std::io::Error| format!("Failed to write '{}': {}", path.display(), e);
+    for row in matrix.iter_rows() {
+        writer.write_all(&(matrix.cols as i32).to_le_bytes()).map_err(write_err)?;
+        for value in row {
+            writer.write_all(&value.to_le_bytes()).map_err(write_err)?;
+        }
+    }
+    writer.flush().map_err(write_err)?;
+
+    Ok(())
+}
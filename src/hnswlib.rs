@@ -0,0 +1,312 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use crate::index::HNSWIndex;
+use crate::types::{DistanceMetric, HNSWNode};
+
+/// Bit set in the third byte of a link list header when hnswlib marks an element as deleted
+const DELETE_MARK: u8 = 0x01;
+
+/// Size of hnswlib's link list header (`linklistsizeint`)
+const LINK_HEADER_SIZE: usize = 4;
+
+/// Size of hnswlib's label type (`labeltype`, a `size_t`)
+const LABEL_SIZE: usize = 8;
+
+/// Little-endian cursor over the bytes of an hnswlib index file
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self.pos.checked_add(len).filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| "Truncated hnswlib index file".to_string())?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn read_u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn read_i32(&mut self) -> Result<i32, String> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn read_usize(&mut self) -> Result<usize, String> {
+        usize::try_from(self.read_u64()?).map_err(|_| "Value in hnswlib header does not fit in usize".to_string())
+    }
+
+    fn read_f64(&mut self) -> Result<f64, String> {
+        Ok(f64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+/// Decode one hnswlib link list (header followed by neighbor slots)
+fn parse_link_list(list: &[u8], element_count: usize) -> Result<(Vec<usize>, bool), String> {
+    let count = u16::from_le_bytes([list[0], list[1]]) as usize;
+    let deleted = list[2] & DELETE_MARK != 0;
+
+    if LINK_HEADER_SIZE + count * 4 > list.len() {
+        return Err(format!("Link list holds {} neighbors but has room for {}", count, (list.len() - LINK_HEADER_SIZE) / 4));
+    }
+
+    let mut neighbors = Vec::with_capacity(count);
+    for slot in list[LINK_HEADER_SIZE..LINK_HEADER_SIZE + count * 4].chunks_exact(4) {
+        let neighbor = u32::from_le_bytes(slot.try_into().unwrap()) as usize;
+        if neighbor >= element_count {
+            return Err(format!("Link to element {} is out of range", neighbor));
+        }
+        neighbors.push(neighbor);
+    }
+
+    Ok((neighbors, deleted))
+}
+
+/// Encode one hnswlib link list into a slot of `capacity` neighbors
+fn write_link_list(out: &mut Vec<u8>, neighbors: &[u32], capacity: usize) {
+    out.extend_from_slice(&(neighbors.len() as u32).to_le_bytes());
+    for &neighbor in neighbors {
+        out.extend_from_slice(&neighbor.to_le_bytes());
+    }
+    out.resize(out.len() + (capacity - neighbors.len()) * 4, 0);
+}
+
+impl<T> HNSWIndex<T> {
+    /// Load an index saved by hnswlib's `saveIndex`
+    ///
+    /// hnswlib files do not record the space they were built with, so the metric has to be
+    /// supplied: `l2` maps to `Euclidean`, `ip` to `InnerProduct` and `cosine` to `Cosine`. Integer labels become
+    /// string ids through `labels` when given, or their decimal representation otherwise.
+    /// Elements marked as deleted in the file are dropped, and links to them are replaced by
+    /// links to their neighbors.
+    pub fn load_hnswlib<P: AsRef<Path>>(
+        path: P,
+        distance_metric: DistanceMetric,
+        labels: Option<&HashMap<u64, String>>,
+    ) -> Result<Self, String> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+        let mut reader = Reader { bytes: &bytes, pos: 0 };
+
+        let offset_level0 = reader.read_usize()?;
+        let _max_elements = reader.read_usize()?;
+        let element_count = reader.read_usize()?;
+        let size_data_per_element = reader.read_usize()?;
+        let label_offset = reader.read_usize()?;
+        let offset_data = reader.read_usize()?;
+        let max_level = reader.read_i32()?;
+        let entry_point = reader.read_u32()?;
+        let max_m = reader.read_usize()?;
+        let max_m0 = reader.read_usize()?;
+        let m = reader.read_usize()?;
+        let mult = reader.read_f64()?;
+        let ef_construction = reader.read_usize()?;
+
+        if offset_level0 != 0
+            || offset_data != LINK_HEADER_SIZE + max_m0 * 4
+            || label_offset < offset_data
+            || !(label_offset - offset_data).is_multiple_of(4)
+            || size_data_per_element != label_offset + LABEL_SIZE
+        {
+            return Err("Unsupported or corrupt hnswlib index layout".to_string());
+        }
+        let dimension = (label_offset - offset_data) / 4;
+        let size_links_per_element = LINK_HEADER_SIZE + max_m * 4;
+
+        let level0 = reader.take(element_count.checked_mul(size_data_per_element)
+            .ok_or_else(|| "Corrupt hnswlib element count".to_string())?)?;
+
+        let mut index = Self::new_with_distance(dimension, m, ef_construction, distance_metric);
+        index.m_max = max_m0;
+        index.ml = mult as f32;
+        index.max_layers = index.max_layers.max(max_level.max(0) as usize + 1);
+
+        // Deleted elements are skipped rather than removed afterwards, which would renumber
+        // every later node once per deletion
+        let mut slots = Vec::with_capacity(element_count);
+        let mut live = 0;
+        for element in level0.chunks_exact(size_data_per_element) {
+            if element[2] & DELETE_MARK != 0 {
+                slots.push(None);
+            } else {
+                slots.push(Some(live));
+                live += 1;
+            }
+        }
+
+        let mut links = Vec::with_capacity(live);
+        let mut deleted_links = HashMap::new();
+        for (i, slot) in slots.iter().enumerate() {
+            let element = &level0[i * size_data_per_element..(i + 1) * size_data_per_element];
+            let (layer0, _) = parse_link_list(&element[..offset_data], element_count)?;
+
+            let link_list_size = reader.read_u32()? as usize;
+            if !link_list_size.is_multiple_of(size_links_per_element) {
+                return Err(format!("Element {} has a malformed link list", i));
+            }
+            let upper = reader.take(link_list_size)?;
+
+            let mut connections = vec![layer0];
+            for list in upper.chunks_exact(size_links_per_element) {
+                connections.push(parse_link_list(list, element_count)?.0);
+            }
+
+            let Some(slot) = *slot else {
+                deleted_links.insert(i, connections);
+                continue;
+            };
+
+            let label = u64::from_le_bytes(element[label_offset..label_offset + LABEL_SIZE].try_into().unwrap());
+            let id = match labels {
+                Some(labels) => labels.get(&label).cloned()
+                    .ok_or_else(|| format!("No id given for hnswlib label {}", label))?,
+                None => label.to_string(),
+            };
+
+            let embedding: Vec<f32> = element[offset_data..label_offset]
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+                .collect();
+
+            index.max_layers = index.max_layers.max(connections.len());
+            if index.node_id_to_index.insert(id.clone(), slot).is_some() {
+                return Err(format!("Duplicate label {} in hnswlib index", label));
+            }
+            index.push_node(HNSWNode::new(id, None), embedding, connections.len())?;
+            links.push(connections);
+        }
+
+        // A link to a deleted element is replaced by links to its live neighbors on the same
+        // layer, so what was only reachable through it stays reachable
+        for (slot, connections) in links.into_iter().enumerate() {
+            for (layer, neighbors) in connections.into_iter().enumerate() {
+                let mut remapped = Vec::with_capacity(neighbors.len());
+                for neighbor in neighbors {
+                    match slots[neighbor] {
+                        Some(neighbor) => remapped.push(neighbor),
+                        None => remapped.extend(deleted_links[&neighbor].get(layer).into_iter().flatten().filter_map(|&n| slots[n])),
+                    }
+                }
+                let mut seen = HashSet::new();
+                remapped.retain(|&neighbor| neighbor != slot && seen.insert(neighbor));
+
+                let m_layer = if layer == 0 { index.m_max } else { index.m };
+                if remapped.len() > m_layer {
+                    let mut candidates: Vec<(usize, f32)> = remapped
+                        .into_iter()
+                        .map(|neighbor| (neighbor, index.nodes_distance(slot, neighbor)))
+                        .collect();
+                    candidates.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+                    remapped = index.select_neighbors(&candidates, m_layer);
+                }
+                index.graph.set_neighbors(slot, layer, &remapped);
+            }
+        }
+
+        if element_count > 0 {
+            if entry_point as usize >= element_count {
+                return Err(format!("Entry point {} is out of range", entry_point));
+            }
+            // A deleted entry point is replaced by the first live node on the highest layer
+            index.entry_point = slots[entry_point as usize].or_else(|| {
+                (0..live).rev().max_by_key(|&slot| index.graph.layers(slot))
+            });
+        }
+
+        Ok(index)
+    }
+
+    /// Save the index in hnswlib's binary format so it can be loaded with `loadIndex`
+    ///
+    /// Ids that all parse as integers are used as labels directly; otherwise each node's
+    /// label is its position in the index. Returns the (label, id) pair of every node.
+    /// Cosine indexes are written with normalized vectors, as hnswlib's `cosine` space expects.
+    pub fn save_hnswlib<P: AsRef<Path>>(&self, path: P) -> Result<Vec<(u64, String)>, String> {
+        let path = path.as_ref();
+        let dimension = if self.nodes.is_empty() { 0 } else { self.stored_embedding(0).len() };
+        if let Some(slot) = (0..self.nodes.len()).find(|&slot| self.stored_embedding(slot).len() != dimension) {
+            return Err(format!("Node '{}' has dimension {}, expected {}", self.nodes[slot].id, self.stored_embedding(slot).len(), dimension));
+        }
+        if self.nodes.len() > u32::MAX as usize {
+            return Err("hnswlib indexes are limited to u32::MAX elements".to_string());
+        }
+
+        // Ids such as "1" and "01" parse to the same label, so numeric labels must also be unique
+        let numeric: Option<Vec<u64>> = self.nodes.iter().map(|node| node.id.parse::<u64>().ok()).collect();
+        let numeric = numeric.filter(|labels| labels.iter().collect::<HashSet<_>>().len() == labels.len());
+        let labels: Vec<(u64, String)> = match numeric {
+            Some(numeric) => numeric.into_iter().zip(self.nodes.iter().map(|node| node.id.clone())).collect(),
+            None => self.nodes.iter().enumerate().map(|(i, node)| (i as u64, node.id.clone())).collect(),
+        };
+
+        // Size the neighbor slots so every existing link list fits
+        let max_m0 = (0..self.nodes.len())
+            .map(|slot| self.graph.neighbors(slot, 0).len())
+            .fold(self.m_max, usize::max);
+        let max_m = (0..self.nodes.len())
+            .flat_map(|slot| (1..self.graph.layers(slot)).map(move |layer| (slot, layer)))
+            .map(|(slot, layer)| self.graph.neighbors(slot, layer).len())
+            .fold(self.m, usize::max);
+        if max_m0.max(max_m) > u16::MAX as usize {
+            return Err("hnswlib link lists are limited to u16::MAX neighbors".to_string());
+        }
+
+        let offset_data = LINK_HEADER_SIZE + max_m0 * 4;
+        let label_offset = offset_data + dimension * 4;
+        let size_data_per_element = label_offset + LABEL_SIZE;
+        let size_links_per_element = LINK_HEADER_SIZE + max_m * 4;
+
+        let (entry_point, max_level) = match self.entry_point {
+            Some(ep) => (ep as u32, self.graph.layers(ep).saturating_sub(1) as i32),
+            None => (u32::MAX, -1),
+        };
+
+        let mut out = Vec::with_capacity(96 + self.nodes.len() * size_data_per_element);
+        for value in [0, self.nodes.len(), self.nodes.len(), size_data_per_element, label_offset, offset_data] {
+            out.extend_from_slice(&(value as u64).to_le_bytes());
+        }
+        out.extend_from_slice(&max_level.to_le_bytes());
+        out.extend_from_slice(&entry_point.to_le_bytes());
+        for value in [max_m, max_m0, self.m] {
+            out.extend_from_slice(&(value as u64).to_le_bytes());
+        }
+        out.extend_from_slice(&(self.ml as f64).to_le_bytes());
+        out.extend_from_slice(&(self.ef_construction as u64).to_le_bytes());
+
+        for (slot, (label, _)) in labels.iter().enumerate() {
+            write_link_list(&mut out, self.graph.neighbors(slot, 0), max_m0);
+
+            // Cosine embeddings are already stored normalized
+            for &value in self.stored_embedding(slot).iter() {
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+
+            out.extend_from_slice(&label.to_le_bytes());
+        }
+
+        for slot in 0..self.nodes.len() {
+            let upper_layers = self.graph.layers(slot).saturating_sub(1);
+            out.extend_from_slice(&((upper_layers * size_links_per_element) as u32).to_le_bytes());
+            for layer in 1..=upper_layers {
+                write_link_list(&mut out, self.graph.neighbors(slot, layer), max_m);
+            }
+        }
+
+        let file = File::create(path).map_err(|e| format!("Failed to create '{}': {}", path.display(), e))?;
+        let mut writer = BufWriter::new(file);
+        writer.write_all(&out)
+            .and_then(|_| writer.flush())
+            .map_err(|e| format!("Failed to write '{}': {}", path.display(), e))?;
+
+        Ok(labels)
+    }
+}
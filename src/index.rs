@@ -1,18 +1,72 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
-use crate::types::{HNSWNode, DistanceMetric, Distance};
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use crate::arena::{Graph, VectorArena};
+use crate::metrics::IndexMetrics;
+use crate::npy::NpyRows;
+use crate::quantization::StorageMode;
+use crate::simd;
+use crate::types::{HNSWNode, DistanceMetric, Distance, Document, VectorIndex};
 
 /// HNSW Index structure
-#[derive(Debug)]
-pub struct HNSWIndex<T> {
+///
+/// Distances come from `D`, which defaults to the built-in `DistanceMetric` enum but can
+/// be any `Distance` implementation, including a boxed `dyn Distance`. Metrics that
+/// normalize (such as Cosine) store unit-length embeddings; set `keep_raw_vectors` to
+/// also keep each vector as inserted.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HNSWIndex<T, D = DistanceMetric> {
     pub(crate) nodes: Vec<HNSWNode<T>>,
+    #[serde(default)]
+    pub(crate) vectors: VectorArena, // f32 vectors, indexed like nodes
+    #[serde(default)]
+    pub(crate) raw_vectors: VectorArena, // vectors as inserted, when kept beside normalized copies
+    #[serde(default)]
+    pub(crate) norms: VectorArena, // length of each vector before it was normalized, in rows of one
+    #[serde(default)]
+    pub(crate) codes: VectorArena<u8>, // quantized codes when the index is quantized
+    #[serde(default)]
+    pub(crate) half_vectors: VectorArena<u16>, // vectors as 16-bit floats when the index stores half precision
+    #[serde(default)]
+    pub(crate) disk_rows: VectorArena<u32>, // row of each vector in vector_file once spilled, in rows of one
+    #[serde(default)]
+    pub(crate) graph: Graph, // neighbor lists, indexed like nodes
+    #[serde(skip)] // rebuilt from the nodes on load
     pub(crate) node_id_to_index: HashMap<String, usize>,
+    #[serde(default)] // filled in on load for indexes saved without it
+    pub(crate) dimension: usize, // length of every vector, or 0 before the first insert
     pub(crate) max_layers: usize,
     pub m: usize, // maximum number of connections per node
     pub m_max: usize, // maximum number of connections for layer 0
     pub ef_construction: usize, // size of dynamic candidate list
+    #[serde(default)]
+    pub ef_search: Option<usize>, // candidates kept on layer 0 during a search, at least k
     pub(crate) ml: f32, // normalization factor for level generation
     pub entry_point: Option<usize>, // index of entry point
-    pub distance_metric: DistanceMetric, // distance metric to use
+    pub distance_metric: D, // distance metric to use
+    #[serde(default)]
+    pub keep_raw_vectors: bool, // keep inserted vectors alongside their normalized copies
+    #[serde(default)]
+    pub(crate) storage: StorageMode, // full precision, half precision or quantized codes
+    #[serde(default)]
+    pub rerank_candidates: Option<usize>, // candidates re-scored at full precision after a quantized search
+    #[serde(default)]
+    pub(crate) vector_file: Option<PathBuf>, // full-precision vectors moved to disk by spill_vectors
+    #[serde(skip)] // reopened from vector_file on load
+    pub(crate) vector_rows: Option<NpyRows>,
+    #[serde(skip)] // counted from when the index is created or loaded
+    pub(crate) metrics: IndexMetrics,
+}
+
+/// A vector converted by `prepare_vector`, with empty fields for what the index does not keep
+#[derive(Debug, Default)]
+pub(crate) struct PreparedVector {
+    pub(crate) vector: Vec<f32>, // f32 vector kept in memory
+    pub(crate) raw: Vec<f32>,    // vector as inserted, kept beside its normalized copy
+    pub(crate) norm: Option<f32>,
+    pub(crate) codes: Vec<u8>,
+    pub(crate) half: Vec<u16>,
 }
 
 impl<T> HNSWIndex<T> {
@@ -20,23 +74,40 @@ impl<T> HNSWIndex<T> {
     pub fn new(_dim: usize, m: usize, ef_construction: usize) -> Self {
         Self::new_with_distance(_dim, m, ef_construction, DistanceMetric::Euclidean)
     }
+}
 
+impl<T, D: Distance> HNSWIndex<T, D> {
     /// Create a new HNSW index with specified distance metric
-    pub fn new_with_distance(_dim: usize, m: usize, ef_construction: usize, distance_metric: DistanceMetric) -> Self {
+    pub fn new_with_distance(_dim: usize, m: usize, ef_construction: usize, distance_metric: D) -> Self {
         let max_layers = (f32::ln(1000.0) / f32::ln(2.0)) as usize + 1; // reasonable default
         let m_max = m;
         let ml = 1.0 / f32::ln(2.0);
         
         Self {
             nodes: Vec::new(),
+            vectors: VectorArena::default(),
+            raw_vectors: VectorArena::default(),
+            norms: VectorArena::default(),
+            codes: VectorArena::default(),
+            half_vectors: VectorArena::default(),
+            disk_rows: VectorArena::default(),
+            graph: Graph::new(m_max, m),
             node_id_to_index: HashMap::new(),
+            dimension: 0,
             max_layers,
             m,
             m_max,
             ef_construction,
+            ef_search: None,
             ml,
             entry_point: None,
             distance_metric,
+            keep_raw_vectors: false,
+            storage: StorageMode::Full,
+            rerank_candidates: None,
+            vector_file: None,
+            vector_rows: None,
+            metrics: IndexMetrics::default(),
         }
     }
 
@@ -45,6 +116,94 @@ impl<T> HNSWIndex<T> {
         self.distance_metric.distance(a, b)
     }
 
+    /// Distance between a prepared query and a stored embedding, or two stored embeddings
+    pub(crate) fn stored_distance(&self, a: &[f32], b: &[f32]) -> f32 {
+        if self.distance_metric.normalizes() {
+            self.distance_metric.normalized_distance(a, b)
+        } else {
+            self.distance_metric.distance(a, b)
+        }
+    }
+
+    /// Convert an embedding to what the index stores for it: normalized if the metric asks
+    /// for it, with its original norm, and then kept as f32, 16-bit floats or quantized
+    /// codes depending on the storage mode
+    pub(crate) fn prepare_vector(&self, mut embedding: Vec<f32>) -> Result<PreparedVector, String> {
+        let mut prepared = PreparedVector::default();
+        if self.distance_metric.normalizes() {
+            if self.keep_raw_vectors {
+                prepared.raw = embedding.clone();
+            }
+            prepared.norm = Some(simd::normalize(&mut embedding));
+        }
+
+        match &self.storage {
+            StorageMode::Full => prepared.vector = embedding,
+            StorageMode::Half(format) => prepared.half = format.encode(&embedding),
+            StorageMode::Quantized { quantizer, quantized_only } => {
+                prepared.codes = quantizer.encode(&embedding)?;
+                if !quantized_only {
+                    prepared.vector = embedding;
+                }
+            }
+        }
+        Ok(prepared)
+    }
+
+    /// Prepare a node's vector and append the node with room for links on `layers` layers,
+    /// returning its position
+    pub(crate) fn push_node(&mut self, node: HNSWNode<T>, embedding: Vec<f32>, layers: usize) -> Result<usize, String> {
+        self.check_dimension(&embedding)?;
+        let dimension = embedding.len();
+        let prepared = self.prepare_vector(embedding)?;
+        self.push_prepared(node, &prepared, dimension, layers)
+    }
+
+    /// Append a node whose vector was already checked and prepared, and had `dimension`
+    /// components before it was prepared, returning its position
+    pub(crate) fn push_prepared(&mut self, node: HNSWNode<T>, prepared: &PreparedVector, dimension: usize, layers: usize) -> Result<usize, String> {
+        let slot = self.nodes.len();
+        self.vectors.set(slot, &prepared.vector)?;
+        self.raw_vectors.set(slot, &prepared.raw)?;
+        self.codes.set(slot, &prepared.codes)?;
+        self.half_vectors.set(slot, &prepared.half)?;
+        if let Some(norm) = prepared.norm {
+            self.norms.set(slot, &[norm])?;
+        }
+        self.graph.push(layers);
+        self.nodes.push(node);
+        self.dimension = dimension;
+        Ok(slot)
+    }
+
+    /// Check that a vector is non-empty and fits the index's dimension, and that the metric
+    /// can compare vectors of its length when it is the first
+    ///
+    /// The dimension is kept on the index rather than read from the vector arena, which
+    /// holds no rows when vectors are stored as half precision or only as codes.
+    pub(crate) fn check_dimension(&self, embedding: &[f32]) -> Result<(), String> {
+        if embedding.is_empty() {
+            return Err("Embedding is empty".to_string());
+        }
+        if self.dimension == 0 {
+            self.distance_metric.validate(embedding.len())?;
+        } else if embedding.len() != self.dimension {
+            return Err(format!("Embedding has dimension {}, index expects {}", embedding.len(), self.dimension));
+        }
+        Ok(())
+    }
+
+    /// Normalize a query if the metric asks for it
+    pub(crate) fn prepare_query<'a>(&self, query: &'a [f32]) -> Cow<'a, [f32]> {
+        if self.distance_metric.normalizes() {
+            let mut query = query.to_vec();
+            simd::normalize(&mut query);
+            Cow::Owned(query)
+        } else {
+            Cow::Borrowed(query)
+        }
+    }
+
     /// Generate random level for a new node
     pub fn generate_level(&self) -> usize {
         use rand::Rng;
@@ -63,13 +222,103 @@ impl<T> HNSWIndex<T> {
         self.nodes.is_empty()
     }
 
+    /// Dimension of the vectors in the index, or `None` before the first insert
+    pub fn dimension(&self) -> Option<usize> {
+        (self.dimension != 0).then_some(self.dimension)
+    }
+
+    /// Counters and histograms recorded by inserts, removes and searches
+    pub fn metrics(&self) -> &IndexMetrics {
+        &self.metrics
+    }
+
     /// Get a reference to a node by ID
     pub fn get_node(&self, id: &str) -> Option<&HNSWNode<T>> {
         self.node_id_to_index.get(id).and_then(|&idx| self.nodes.get(idx))
     }
 
+    /// Get a node's f32 vector as stored for search, if it is held in memory
+    ///
+    /// Cosine vectors are stored normalized. Vectors dropped after quantization, moved to
+    /// disk or stored as half precision are not held as f32; see `get_original_embedding`.
+    pub fn get_embedding(&self, id: &str) -> Option<&[f32]> {
+        self.node_id_to_index.get(id).and_then(|&idx| self.vectors.get(idx))
+    }
+
+    /// Get a node's vector as inserted, if `keep_raw_vectors` kept it beside its normalized copy
+    pub fn get_raw_embedding(&self, id: &str) -> Option<&[f32]> {
+        self.node_id_to_index.get(id).and_then(|&idx| self.raw_vectors.get(idx))
+    }
+
+    /// Get the length a node's vector had before it was normalized, if it was
+    pub fn get_norm(&self, id: &str) -> Option<f32> {
+        self.node_id_to_index.get(id).and_then(|&idx| self.norms.value(idx))
+    }
+
+    /// Get a node's quantized codes, if the index is quantized
+    pub fn get_codes(&self, id: &str) -> Option<&[u8]> {
+        self.node_id_to_index.get(id).and_then(|&idx| self.codes.get(idx))
+    }
+
+    /// Get a node's vector as 16-bit floats, if the index stores half precision
+    pub fn get_half_embedding(&self, id: &str) -> Option<&[u16]> {
+        self.node_id_to_index.get(id).and_then(|&idx| self.half_vectors.get(idx))
+    }
+
+    /// Get a node's vector as it was inserted, reconstructing it if needed
+    pub fn get_original_embedding(&self, id: &str) -> Option<Cow<'_, [f32]>> {
+        self.node_id_to_index.get(id).map(|&idx| self.original_embedding_of(idx))
+    }
+
+    /// Get a node's neighbors on every layer, bottom layer first
+    pub fn get_connections(&self, id: &str) -> Option<Vec<Vec<usize>>> {
+        self.node_id_to_index.get(id).map(|&idx| self.graph.connections(idx))
+    }
+
+    /// Number of nodes on each layer, bottom layer first
+    pub fn layer_counts(&self) -> Vec<usize> {
+        let mut counts = vec![0; self.graph.max_layers()];
+        for slot in 0..self.graph.len() {
+            for count in &mut counts[..self.graph.layers(slot)] {
+                *count += 1;
+            }
+        }
+        counts
+    }
+
     /// Get all node IDs in the index
     pub fn get_all_ids(&self) -> Vec<String> {
         self.nodes.iter().map(|node| node.id.clone()).collect()
     }
 }
+
+impl<T: Clone + Send + Sync, D: Distance + Sync> VectorIndex<T> for HNSWIndex<T, D> {
+    fn insert(&mut self, id: String, embedding: Vec<f32>, document: Option<Document<T>>) -> Result<(), String> {
+        HNSWIndex::insert(self, id, embedding, document)
+    }
+
+    fn remove(&mut self, id: &str) -> Result<Option<Document<T>>, String> {
+        HNSWIndex::remove(self, id)
+    }
+
+    fn search(&self, query: &[f32], k: usize) -> Vec<(String, f32, Option<&Document<T>>)> {
+        HNSWIndex::search(self, query, k)
+    }
+
+    fn search_filtered(
+        &self,
+        query: &[f32],
+        k: usize,
+        filter: &(dyn Fn(&str, Option<&Document<T>>) -> bool + Sync),
+    ) -> Vec<(String, f32, Option<&Document<T>>)> {
+        HNSWIndex::search_filtered(self, query, k, filter)
+    }
+
+    fn contains(&self, id: &str) -> bool {
+        HNSWIndex::contains(self, id)
+    }
+
+    fn len(&self) -> usize {
+        HNSWIndex::len(self)
+    }
+}
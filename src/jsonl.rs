@@ -0,0 +1,116 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use crate::index::HNSWIndex;
+use crate::types::{Distance, Document};
+
+/// One line of a JSON lines file: `{"id": ..., "vector": [...], "document": ...}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonlRecord<T> {
+    pub id: String,
+    pub vector: Vec<f32>,
+    pub document: Option<T>, // may be left out
+}
+
+/// Outcome of a JSON lines ingestion
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JsonlReport {
+    pub inserted: usize,
+    pub errors: Vec<(usize, String)>, // line number, counted from 1, and why the line was skipped
+}
+
+impl<T: DeserializeOwned + Clone + Send + Sync, D: Distance + Sync> HNSWIndex<T, D> {
+    /// Insert the records of a JSON lines stream, `chunk_size` records at a time
+    ///
+    /// Each chunk is inserted with `insert_parallel`, so the stream is never held in memory
+    /// at once. Lines that do not parse, hold empty or non-finite vectors, disagree with the
+    /// index's dimension or repeat an id are skipped and reported in the returned
+    /// `JsonlReport`; blank lines are ignored. Only a failure to read the stream is an error.
+    pub fn insert_jsonl<R: BufRead>(&mut self, reader: R, chunk_size: usize) -> Result<JsonlReport, String> {
+        let chunk_size = chunk_size.max(1);
+        let mut report = JsonlReport::default();
+        let mut dimension = self.dimension();
+        let mut chunk = Vec::with_capacity(chunk_size);
+        let mut chunk_ids = HashSet::new();
+        let mut chunk_start = 0;
+
+        for (number, line) in reader.lines().enumerate() {
+            let number = number + 1;
+            let line = line.map_err(|e| format!("Failed to read line {}: {}", number, e))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let record = match self.check_record(&line, dimension, &chunk_ids) {
+                Ok(record) => record,
+                Err(e) => {
+                    report.errors.push((number, e));
+                    continue;
+                }
+            };
+            // The first valid record fixes the dimension of an empty index
+            dimension = Some(record.vector.len());
+            if chunk.is_empty() {
+                chunk_start = number;
+            }
+            chunk_ids.insert(record.id.clone());
+            let document = record.document.map(|data| Document { id: record.id.clone(), data });
+            chunk.push((record.id, record.vector, document));
+
+            if chunk.len() == chunk_size {
+                self.insert_chunk(std::mem::take(&mut chunk), chunk_start, &mut report)?;
+                chunk_ids.clear();
+            }
+        }
+        if !chunk.is_empty() {
+            self.insert_chunk(chunk, chunk_start, &mut report)?;
+        }
+
+        Ok(report)
+    }
+
+    /// Insert the records of a JSON lines file, or of standard input when `path` is `-`
+    pub fn insert_jsonl_file<P: AsRef<Path>>(&mut self, path: P, chunk_size: usize) -> Result<JsonlReport, String> {
+        let path = path.as_ref();
+        if path == Path::new("-") {
+            return self.insert_jsonl(std::io::stdin().lock(), chunk_size);
+        }
+        let file = File::open(path).map_err(|e| format!("Failed to open '{}': {}", path.display(), e))?;
+        self.insert_jsonl(BufReader::new(file), chunk_size)
+            .map_err(|e| format!("'{}': {}", path.display(), e))
+    }
+
+    /// Parse a line and check that its record can be inserted alongside `chunk_ids`
+    fn check_record(&self, line: &str, dimension: Option<usize>, chunk_ids: &HashSet<String>) -> Result<JsonlRecord<T>, String> {
+        let record: JsonlRecord<T> = serde_json::from_str(line).map_err(|e| e.to_string())?;
+        if record.vector.is_empty() {
+            return Err(format!("Record '{}' has an empty vector", record.id));
+        }
+        if record.vector.iter().any(|value| !value.is_finite()) {
+            return Err(format!("Record '{}' has a non-finite value", record.id));
+        }
+        if let Some(dimension) = dimension {
+            if record.vector.len() != dimension {
+                return Err(format!("Record '{}' has dimension {}, expected {}", record.id, record.vector.len(), dimension));
+            }
+        }
+        if self.node_id_to_index.contains_key(&record.id) || chunk_ids.contains(&record.id) {
+            return Err(format!("Node with id '{}' already exists", record.id));
+        }
+        Ok(record)
+    }
+
+    fn insert_chunk(&mut self, chunk: Vec<(String, Vec<f32>, Option<Document<T>>)>, start: usize, report: &mut JsonlReport) -> Result<(), String> {
+        // Records were checked, so a failure here is a problem with the index rather than a line
+        for result in self.insert_parallel(chunk)? {
+            match result {
+                Ok(()) => report.inserted += 1,
+                Err(e) => report.errors.push((start, format!("In the chunk starting here: {}", e))),
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,8 +1,25 @@
 pub mod types;
 pub mod index;
+pub mod arena;
 pub mod insert;
 pub mod search;
 pub mod remove;
+pub mod npy;
+pub mod fvecs;
+pub mod jsonl;
+pub mod hnswlib;
+pub mod mips;
+pub mod persist;
+pub mod simd;
+pub mod quantization;
+pub mod pq;
+pub mod bq;
+pub mod precision;
+pub mod memory;
+pub mod metrics;
+pub mod flat;
+pub mod validate;
 
 pub use types::*;
 pub use index::*;
+pub use flat::FlatIndex;
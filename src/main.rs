@@ -1,45 +1,553 @@
-use swarc::{HNSWIndex, Document};
-use rand::Rng;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+use serde_json::{json, Value};
+use swarc::fvecs::{read_fvecs, write_fvecs};
+use swarc::jsonl::JsonlRecord;
+use swarc::npy::{write_npy, NpyMatrix, NpyRows};
+use swarc::{DistanceMetric, Document, HNSWIndex};
 
-fn main() {
-    // Example usage
-    let mut index = HNSWIndex::new(128, 16, 200);
-    
-    // Create some sample documents
-    let doc1 = Document {
-        id: "doc1".to_string(),
-        data: "This is document 1".to_string(),
+const USAGE: &str = "\
+Usage: swarc <COMMAND> [OPTIONS]
+
+Commands:
+  build <INPUT> --output <INDEX>   Build an index from a .npy, .fvecs or .jsonl file
+  add <INDEX> <INPUT>              Insert the vectors of a file into an index
+  remove <INDEX> <ID>...           Remove nodes from an index
+  query <INDEX>                    Search for the nearest neighbors of one or more vectors
+  stats <INDEX>                    Print the parameters, layers and memory of an index
+  validate <INDEX>                 Check the structure of an index
+  export <INDEX> --output <FILE>   Write the vectors of an index to a .npy, .fvecs or .jsonl file
+
+Input and output files:
+  .npy, .fvecs    One vector per row; ids are read from or written to --ids, one per
+                  line, or default to row numbers
+  .jsonl          One {\"id\": ..., \"vector\": [...], \"document\": ...} object per line
+                  Bad lines are reported and skipped; the rest are inserted
+  -               Standard input or output, as JSON lines; .npy and .fvecs need a file
+
+Options:
+  -o, --output <FILE>        Where build and export write, and add and remove save to
+                             instead of overwriting the index
+  --format <npy|fvecs|jsonl> File format, when the extension does not tell
+  --ids <FILE>               Ids of .npy and .fvecs rows, one per line
+  --chunk-size <N>           Records of a .jsonl file inserted at a time, for build and add
+                             [default: 10000]
+  --m <N>                    Maximum connections per node, for build [default: 16]
+  --ef-construction <N>      Candidate list size while building [default: 200]
+  --metric <NAME>            euclidean, cosine, inner_product, manhattan, chebyshev or
+                             minkowski:<p>, for build [default: euclidean]
+  --vector <FILE>            Query vectors, from a file or - for standard input [default: -]
+                             Lines hold a JSON array, numbers separated by spaces or
+                             commas, or a .jsonl record
+  -k <N>                     Neighbors returned per query [default: 10]
+  --ef-search <N>            Candidates kept on layer 0 while searching [default: the
+                             index's saved ef_search, or else ef-construction]
+  --json                     Print query results and stats as JSON
+  -h, --help                 Print this help
+";
+
+/// Records of a .jsonl file inserted at a time
+const DEFAULT_CHUNK_SIZE: usize = 10_000;
+
+/// Flags that take no value
+const SWITCHES: [&str; 2] = ["--json", "--help"];
+
+/// Documents are arbitrary JSON, as read from .jsonl files
+type Index = HNSWIndex<Value>;
+
+/// Ids, vectors and documents ready for `insert_parallel`
+type Records = Vec<(String, Vec<f32>, Option<Document<Value>>)>;
+
+/// Arguments of a command: positional values in order, and options by long name
+struct Args {
+    positional: VecDeque<String>,
+    options: HashMap<String, String>,
+    switches: HashSet<String>,
+}
+
+impl Args {
+    fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self, String> {
+        let mut parsed = Self { positional: VecDeque::new(), options: HashMap::new(), switches: HashSet::new() };
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let arg = match arg.as_str() {
+                "-o" => "--output".to_string(),
+                "-k" => "--k".to_string(),
+                "-h" => "--help".to_string(),
+                _ => arg,
+            };
+            // A lone - names standard input, and negative numbers are values
+            if arg.len() > 1 && arg.starts_with('-') && !arg.starts_with("--") && arg.parse::<f64>().is_err() {
+                return Err(format!("Unknown option '{}'", arg));
+            }
+            if !arg.starts_with("--") {
+                parsed.positional.push_back(arg);
+                continue;
+            }
+
+            // Both `--flag value` and `--flag=value` are accepted
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+            if SWITCHES.contains(&flag.as_str()) {
+                parsed.switches.insert(flag);
+                continue;
+            }
+            let value = inline
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("Missing value for {}", flag))?;
+            parsed.options.insert(flag, value);
+        }
+        Ok(parsed)
+    }
+
+    /// Take the next positional argument
+    fn positional(&mut self, name: &str) -> Result<String, String> {
+        self.positional.pop_front().ok_or_else(|| format!("Missing {}", name))
+    }
+
+    /// Take an option's value
+    fn option(&mut self, flag: &str) -> Option<String> {
+        self.options.remove(flag)
+    }
+
+    /// Take an option's value and parse it
+    fn parsed<T: FromStr>(&mut self, flag: &str) -> Result<Option<T>, String> {
+        match self.option(flag) {
+            Some(value) => value.parse().map(Some).map_err(|_| format!("Invalid value '{}' for {}", value, flag)),
+            None => Ok(None),
+        }
+    }
+
+    fn switch(&mut self, flag: &str) -> bool {
+        self.switches.remove(flag)
+    }
+
+    /// Fail on any argument the command did not take
+    fn finish(self) -> Result<(), String> {
+        if let Some(arg) = self.positional.front() {
+            return Err(format!("Unexpected argument '{}'", arg));
+        }
+        if let Some(flag) = self.options.keys().chain(self.switches.iter()).next() {
+            return Err(format!("Unknown option '{}'", flag));
+        }
+        Ok(())
+    }
+}
+
+/// Vector file formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Npy,
+    Fvecs,
+    Jsonl,
+}
+
+impl Format {
+    /// The format named by `--format`, or else by the file's extension
+    ///
+    /// Standard input and output are always JSON lines.
+    fn of(path: &str, explicit: Option<String>) -> Result<Self, String> {
+        let name = match explicit {
+            Some(name) => name,
+            None if path == "-" => "jsonl".to_string(),
+            None => Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase(),
+        };
+        let format = match name.as_str() {
+            "npy" => Format::Npy,
+            "fvecs" => Format::Fvecs,
+            "jsonl" | "ndjson" | "json" => Format::Jsonl,
+            _ => return Err(format!("Cannot tell the format of '{}', pass --format npy, fvecs or jsonl", path)),
+        };
+        if path == "-" && format != Format::Jsonl {
+            return Err(format!("Standard input and output only carry JSON lines, not {}; pass a file name", name));
+        }
+        Ok(format)
+    }
+}
+
+fn open_input(path: &str) -> Result<Box<dyn BufRead>, String> {
+    if path == "-" {
+        return Ok(Box::new(BufReader::new(io::stdin())));
+    }
+    let file = File::open(path).map_err(|e| format!("Failed to open '{}': {}", path, e))?;
+    Ok(Box::new(BufReader::new(file)))
+}
+
+fn create_output(path: &str) -> Result<Box<dyn Write>, String> {
+    if path == "-" {
+        return Ok(Box::new(BufWriter::new(io::stdout())));
+    }
+    let file = File::create(path).map_err(|e| format!("Failed to create '{}': {}", path, e))?;
+    Ok(Box::new(BufWriter::new(file)))
+}
+
+/// Read the rows of a .npy or .fvecs file as vectors
+fn read_rows(path: &str, format: Format) -> Result<Vec<Vec<f32>>, String> {
+    match format {
+        // Rows are converted straight from the mapped file, without building a matrix first
+        Format::Npy => {
+            let rows = NpyRows::open(path)?;
+            Ok((0..rows.rows()).filter_map(|i| rows.row(i)).collect())
+        }
+        Format::Fvecs => Ok(read_fvecs(path)?.iter_rows().map(<[f32]>::to_vec).collect()),
+        Format::Jsonl => unreachable!("JSON lines are read a line at a time"),
+    }
+}
+
+/// Read the rows of a .npy or .fvecs file, with their ids
+fn read_matrix_records(path: &str, format: Format, ids: Option<String>) -> Result<Records, String> {
+    let vectors = read_rows(path, format)?;
+
+    let ids = match ids {
+        Some(ids_path) => {
+            let ids: Vec<String> = open_input(&ids_path)?
+                .lines()
+                .collect::<Result<_, _>>()
+                .map_err(|e| format!("Failed to read '{}': {}", ids_path, e))?;
+            if ids.len() != vectors.len() {
+                return Err(format!("'{}' has {} ids for {} rows", ids_path, ids.len(), vectors.len()));
+            }
+            ids
+        }
+        None => (0..vectors.len()).map(|i| i.to_string()).collect(),
     };
-    
-    let doc2 = Document {
-        id: "doc2".to_string(),
-        data: "This is document 2".to_string(),
+    Ok(ids.into_iter().zip(vectors).map(|(id, vector)| (id, vector, None)).collect())
+}
+
+/// Insert the vectors of an input file, reporting every one that could not be inserted
+fn ingest(index: &mut Index, input: &str, format: Format, ids: Option<String>, chunk_size: usize) -> Result<(), String> {
+    if format == Format::Jsonl {
+        if ids.is_some() {
+            return Err("--ids only applies to .npy and .fvecs files, .jsonl records carry their own".to_string());
+        }
+        let report = index.insert_jsonl_file(input, chunk_size)?;
+        for (line, e) in &report.errors {
+            eprintln!("{}:{}: {}", input, line, e);
+        }
+        println!("Inserted {} records, skipped {} lines", report.inserted, report.errors.len());
+        return Ok(());
+    }
+
+    let records = read_matrix_records(input, format, ids)?;
+    let total = records.len();
+    let mut inserted = 0;
+    for result in index.insert_parallel(records)? {
+        match result {
+            Ok(()) => inserted += 1,
+            Err(e) => eprintln!("Failed to insert a vector: {}", e),
+        }
+    }
+    println!("Inserted {} of {} vectors", inserted, total);
+    Ok(())
+}
+
+/// Parse query vectors: one per line, as a JSON array, numbers separated by spaces or
+/// commas, or a JSON lines record whose vector is used
+fn parse_queries(text: &str) -> Result<Vec<Vec<f32>>, String> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(number, line)| {
+            let line = line.trim();
+            let vector = if line.starts_with('[') {
+                serde_json::from_str(line).map_err(|e| e.to_string())
+            } else if line.starts_with('{') {
+                serde_json::from_str::<JsonlRecord<Value>>(line).map(|record| record.vector).map_err(|e| e.to_string())
+            } else {
+                line.split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|value| !value.is_empty())
+                    .map(|value| value.parse::<f32>().map_err(|_| format!("invalid number '{}'", value)))
+                    .collect()
+            };
+            vector.map_err(|e| format!("Query on line {}: {}", number + 1, e))
+        })
+        .collect()
+}
+
+fn read_queries(path: &str, format: Option<String>) -> Result<Vec<Vec<f32>>, String> {
+    match Format::of(path, format) {
+        Ok(format @ (Format::Npy | Format::Fvecs)) => read_rows(path, format),
+        Err(e) if path == "-" => Err(e),
+        // JSON lines and plain text are both read a line at a time
+        _ if path == "-" => {
+            let mut text = String::new();
+            io::stdin().read_to_string(&mut text).map_err(|e| format!("Failed to read standard input: {}", e))?;
+            parse_queries(&text)
+        }
+        _ => {
+            let text = std::fs::read_to_string(path).map_err(|e| format!("Failed to read '{}': {}", path, e))?;
+            parse_queries(&text)
+        }
+    }
+}
+
+fn save(index: &Index, path: &str) -> Result<(), String> {
+    index.save(path)?;
+    println!("Saved {} nodes to {}", index.len(), path);
+    Ok(())
+}
+
+fn build(mut args: Args) -> Result<(), String> {
+    let input = args.positional("input file")?;
+    let output = args.option("--output").ok_or("Missing --output for the index file")?;
+    let format = Format::of(&input, args.option("--format"))?;
+    let ids = args.option("--ids");
+    let chunk_size = args.parsed("--chunk-size")?.unwrap_or(DEFAULT_CHUNK_SIZE);
+    let m = args.parsed("--m")?.unwrap_or(16);
+    let ef_construction = args.parsed("--ef-construction")?.unwrap_or(200);
+    let metric: DistanceMetric = match args.option("--metric") {
+        Some(name) => name.parse()?,
+        None => DistanceMetric::Euclidean,
+    };
+    args.finish()?;
+    if m == 0 || ef_construction == 0 {
+        return Err("--m and --ef-construction must be greater than zero".to_string());
+    }
+
+    // The dimension is set by the first vector inserted
+    let mut index = Index::new_with_distance(0, m, ef_construction, metric);
+    ingest(&mut index, &input, format, ids, chunk_size)?;
+    if index.is_empty() {
+        return Err(format!("'{}' holds no vectors that could be inserted", input));
+    }
+    save(&index, &output)
+}
+
+fn add(mut args: Args) -> Result<(), String> {
+    let index_path = args.positional("index file")?;
+    let input = args.positional("input file")?;
+    let output = args.option("--output").unwrap_or_else(|| index_path.clone());
+    let format = Format::of(&input, args.option("--format"))?;
+    let ids = args.option("--ids");
+    let chunk_size = args.parsed("--chunk-size")?.unwrap_or(DEFAULT_CHUNK_SIZE);
+    args.finish()?;
+
+    let mut index = Index::load(&index_path)?;
+    ingest(&mut index, &input, format, ids, chunk_size)?;
+    save(&index, &output)
+}
+
+fn remove(mut args: Args) -> Result<(), String> {
+    let index_path = args.positional("index file")?;
+    let output = args.option("--output").unwrap_or_else(|| index_path.clone());
+    let ids: Vec<String> = args.positional.drain(..).collect();
+    args.finish()?;
+    if ids.is_empty() {
+        return Err("Missing ids to remove".to_string());
+    }
+
+    let mut index = Index::load(&index_path)?;
+    let ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+    // Nothing is removed unless every id exists
+    index.remove_multiple(&ids)?;
+    println!("Removed {} nodes", ids.len());
+    save(&index, &output)
+}
+
+fn query(mut args: Args) -> Result<(), String> {
+    let index_path = args.positional("index file")?;
+    let vector = args.option("--vector").unwrap_or_else(|| "-".to_string());
+    let format = args.option("--format");
+    let k = args.parsed("--k")?.unwrap_or(10);
+    let ef_search = args.parsed("--ef-search")?;
+    let as_json = args.switch("--json");
+    args.finish()?;
+
+    let mut index = Index::load(&index_path)?;
+    // Without --ef-search, searches keep the ef_search saved with the index
+    if let Some(ef_search) = ef_search {
+        index.ef_search = Some(ef_search);
+    }
+    let queries = read_queries(&vector, format)?;
+    if queries.is_empty() {
+        return Err("No query vectors given".to_string());
+    }
+
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    let write_err = |e: io::Error| format!("Failed to write results: {}", e);
+    for (i, query) in queries.iter().enumerate() {
+        let results = index.search(query, k);
+        if as_json {
+            let results: Vec<Value> = results
+                .into_iter()
+                .map(|(id, distance, document)| json!({ "id": id, "distance": distance, "document": document.map(|d| &d.data) }))
+                .collect();
+            writeln!(out, "{}", Value::Array(results)).map_err(write_err)?;
+            continue;
+        }
+
+        if queries.len() > 1 {
+            writeln!(out, "Query {}:", i).map_err(write_err)?;
+        }
+        if results.is_empty() {
+            writeln!(out, "  no results (the index is empty or the query has the wrong dimension)").map_err(write_err)?;
+        }
+        for (rank, (id, distance, document)) in results.into_iter().enumerate() {
+            match document {
+                Some(document) => writeln!(out, "{:>4}  {}  {:.6}  {}", rank + 1, id, distance, document.data),
+                None => writeln!(out, "{:>4}  {}  {:.6}", rank + 1, id, distance),
+            }
+            .map_err(write_err)?;
+        }
+    }
+    out.flush().map_err(write_err)
+}
+
+fn stats(mut args: Args) -> Result<(), String> {
+    let index_path = args.positional("index file")?;
+    let as_json = args.switch("--json");
+    args.finish()?;
+
+    let index = Index::load(&index_path)?;
+    let ids = index.get_all_ids();
+    let dimension = index.dimension().unwrap_or(0);
+    let entry_point = index.entry_point.map(|ep| ids[ep].clone());
+    let storage = index.storage_mode();
+    let layers = index.layer_counts();
+    let memory = index.memory_usage();
+
+    if as_json {
+        let stats = json!({
+            "nodes": index.len(),
+            "dimension": dimension,
+            "metric": format!("{:?}", index.distance_metric),
+            "m": index.m,
+            "m_max": index.m_max,
+            "ef_construction": index.ef_construction,
+            "ef_search": index.ef_search,
+            "entry_point": entry_point,
+            "storage": storage,
+            "quantized_only": index.quantized_only(),
+            "nodes_per_layer": layers,
+            "memory": {
+                "total": memory.total(),
+                "vectors": memory.vectors,
+                "quantized": memory.quantized,
+                "adjacency": memory.adjacency(),
+                "adjacency_per_layer": memory.adjacency,
+                "nodes": memory.nodes,
+                "id_map": memory.id_map,
+                "documents": memory.documents,
+            },
+        });
+        println!("{}", stats);
+        return Ok(());
+    }
+
+    let mb = |bytes: usize| bytes as f64 / (1024.0 * 1024.0);
+    println!("Nodes:            {}", index.len());
+    println!("Dimension:        {}", dimension);
+    println!("Metric:           {:?}", index.distance_metric);
+    println!("M:                {} (layer 0: {})", index.m, index.m_max);
+    println!("ef_construction:  {}", index.ef_construction);
+    println!("ef_search:        {}", index.ef_search.unwrap_or(index.ef_construction));
+    println!("Entry point:      {}", entry_point.as_deref().unwrap_or("none"));
+    println!("Storage:          {}{}", storage, if index.quantized_only() { ", full-precision vectors dropped" } else { "" });
+    println!("Layers:           {}", layers.len());
+    for (layer, count) in layers.iter().enumerate() {
+        println!("  layer {:<3}       {} nodes", layer, count);
+    }
+    println!("Memory:           {:.2}MB", mb(memory.total()));
+    println!("  vectors         {:.2}MB", mb(memory.vectors));
+    println!("  quantized       {:.2}MB", mb(memory.quantized));
+    println!("  adjacency       {:.2}MB", mb(memory.adjacency()));
+    println!("  nodes           {:.2}MB", mb(memory.nodes));
+    println!("  id map          {:.2}MB", mb(memory.id_map));
+    println!("  documents       {:.2}MB", mb(memory.documents));
+    Ok(())
+}
+
+fn validate(mut args: Args) -> Result<(), String> {
+    let index_path = args.positional("index file")?;
+    args.finish()?;
+
+    // Loading already rejects files whose graph or vectors cannot be used
+    let index = Index::load(&index_path)?;
+    let problems = index.validate();
+    for problem in &problems {
+        println!("{}", problem);
+    }
+    if !problems.is_empty() {
+        return Err(format!("{} problems found in '{}'", problems.len(), index_path));
+    }
+    println!("'{}' is valid: {} nodes", index_path, index.len());
+    Ok(())
+}
+
+fn export(mut args: Args) -> Result<(), String> {
+    let index_path = args.positional("index file")?;
+    let output = args.option("--output").ok_or("Missing --output for the exported file")?;
+    let format = Format::of(&output, args.option("--format"))?;
+    let ids_path = args.option("--ids");
+    args.finish()?;
+
+    let index = Index::load(&index_path)?;
+    let write_err = |e: io::Error| format!("Failed to write '{}': {}", output, e);
+    let (matrix, ids): (NpyMatrix, Vec<String>) = index.to_matrix()?;
+    match format {
+        Format::Npy => write_npy(&output, &matrix)?,
+        Format::Fvecs => write_fvecs(&output, &matrix)?,
+        Format::Jsonl => {
+            if ids_path.is_some() {
+                return Err("--ids only applies to .npy and .fvecs files, .jsonl records carry their own".to_string());
+            }
+            let mut out = create_output(&output)?;
+            for (id, row) in ids.iter().zip(matrix.iter_rows()) {
+                let document = index.get_node(id).and_then(|node| node.document.as_ref()).map(|d| &d.data);
+                let record = json!({ "id": id, "vector": row, "document": document });
+                writeln!(out, "{}", record).map_err(write_err)?;
+            }
+            out.flush().map_err(write_err)?;
+        }
+    }
+
+    if let Some(ids_path) = ids_path {
+        let mut out = create_output(&ids_path)?;
+        for id in &ids {
+            writeln!(out, "{}", id).map_err(|e| format!("Failed to write '{}': {}", ids_path, e))?;
+        }
+        out.flush().map_err(|e| format!("Failed to write '{}': {}", ids_path, e))?;
+    } else if format != Format::Jsonl {
+        eprintln!("Ids are not written without --ids; rows are in index order");
+    }
+    if output != "-" {
+        println!("Exported {} vectors to {}", ids.len(), output);
+    }
+    Ok(())
+}
+
+fn run(args: Vec<String>) -> Result<(), String> {
+    let mut args = Args::parse(args)?;
+    let command = match args.positional.pop_front() {
+        Some(command) if !args.switch("--help") => command,
+        _ => {
+            print!("{}", USAGE);
+            return Ok(());
+        }
     };
-    
-    // Generate some random embeddings
-    let mut rng = rand::thread_rng();
-    let embedding1: Vec<f32> = (0..128).map(|_| rng.gen_range(-1.0..1.0)).collect();
-    let embedding2: Vec<f32> = (0..128).map(|_| rng.gen_range(-1.0..1.0)).collect();
-    
-    // Insert documents into the index
-    index.insert("node1".to_string(), embedding1.clone(), Some(doc1)).unwrap();
-    index.insert("node2".to_string(), embedding2.clone(), Some(doc2)).unwrap();
-    
-    println!("Index created with {} nodes", index.len());
-    
-    // Search for nearest neighbors
-    let query: Vec<f32> = (0..128).map(|_| rng.gen_range(-1.0..1.0)).collect();
-    let results = index.search(&query, 2);
-    
-    println!("Search results:");
-    for (id, distance, document) in results {
-        println!("  ID: {}, Distance: {:.4}, Document: {:?}", id, distance, document);
-    }
-    
-    // Remove a node
-    if let Ok(removed_doc) = index.remove("node1") {
-        println!("Removed document: {:?}", removed_doc);
-    }
-    
-    println!("Index now has {} nodes", index.len());
+
+    match command.as_str() {
+        "build" => build(args),
+        "add" => add(args),
+        "remove" => remove(args),
+        "query" => query(args),
+        "stats" => stats(args),
+        "validate" => validate(args),
+        "export" => export(args),
+        _ => Err(format!("Unknown command '{}'\n\n{}", command, USAGE)),
+    }
+}
+
+fn main() {
+    if let Err(e) = run(std::env::args().skip(1).collect()) {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+use std::mem::size_of;
+use crate::index::HNSWIndex;
+use crate::types::{Document, HNSWNode};
+
+/// Heap memory owned by a value, used to account for documents
+///
+/// The default reports nothing, so a document type only needs an empty
+/// `impl HeapSize for MyType {}` to be counted by its inline size alone. Types that
+/// cannot implement it, such as those from other crates, can be measured with
+/// `memory_usage_with` instead.
+pub trait HeapSize {
+    /// Bytes this value owns on the heap, not counting its own inline size
+    fn heap_size(&self) -> usize {
+        0
+    }
+}
+
+macro_rules! impl_heap_size_inline {
+    ($($t:ty),*) => {
+        $(impl HeapSize for $t {})*
+    };
+}
+
+impl_heap_size_inline!((), bool, char, u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+impl HeapSize for String {
+    fn heap_size(&self) -> usize {
+        self.capacity()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Vec<T> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * size_of::<T>() + self.iter().map(HeapSize::heap_size).sum::<usize>()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Option<T> {
+    fn heap_size(&self) -> usize {
+        self.as_ref().map_or(0, HeapSize::heap_size)
+    }
+}
+
+impl<T: HeapSize> HeapSize for Box<T> {
+    fn heap_size(&self) -> usize {
+        size_of::<T>() + (**self).heap_size()
+    }
+}
+
+impl HeapSize for serde_json::Value {
+    fn heap_size(&self) -> usize {
+        match self {
+            serde_json::Value::String(s) => s.heap_size(),
+            serde_json::Value::Array(values) => values.heap_size(),
+            serde_json::Value::Object(map) => map
+                .iter()
+                .map(|(key, value)| key.heap_size() + size_of::<(String, serde_json::Value)>() + value.heap_size())
+                .sum(),
+            _ => 0,
+        }
+    }
+}
+
+impl<T: HeapSize> HeapSize for Document<T> {
+    fn heap_size(&self) -> usize {
+        self.id.heap_size() + self.data.heap_size()
+    }
+}
+
+/// Bytes held in memory by an index, broken down by what they store
+///
+/// Sizes count allocated capacity rather than length. Vectors moved to disk with
+/// `spill_vectors` are memory-mapped and not counted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    pub vectors: usize, // full-precision vectors: the f32 arena, half-precision copies and kept raw vectors
+    pub quantized: usize, // quantized codes and the quantizers' trained tables
+    pub adjacency: Vec<usize>, // neighbor lists of each layer, bottom layer first
+    pub nodes: usize, // node records and their ids, norms and disk rows
+    pub id_map: usize, // lookup table from id to node
+    pub documents: usize, // heap memory of the documents, whose inline size is part of `nodes`
+}
+
+impl MemoryUsage {
+    /// Total bytes across every part
+    pub fn total(&self) -> usize {
+        self.vectors + self.quantized + self.adjacency() + self.nodes + self.id_map + self.documents
+    }
+
+    /// Bytes of neighbor lists across every layer
+    pub fn adjacency(&self) -> usize {
+        self.adjacency.iter().sum()
+    }
+}
+
+impl<T: HeapSize, D> HNSWIndex<T, D> {
+    /// Measure the memory held by the index
+    pub fn memory_usage(&self) -> MemoryUsage {
+        self.memory_usage_with(HeapSize::heap_size)
+    }
+}
+
+impl<T, D> HNSWIndex<T, D> {
+    /// Measure the memory held by the index, with `document_heap` giving the heap bytes
+    /// owned by each document's data
+    ///
+    /// For document types that do not implement `HeapSize`; pass `|_| 0` to count them by
+    /// their inline size alone.
+    pub fn memory_usage_with(&self, document_heap: impl Fn(&T) -> usize) -> MemoryUsage {
+        let mut usage = MemoryUsage {
+            vectors: self.vectors.heap_size() + self.raw_vectors.heap_size() + self.half_vectors.heap_size(),
+            quantized: self.codes.heap_size(),
+            adjacency: self.graph.layer_sizes(),
+            nodes: self.nodes.capacity() * size_of::<HNSWNode<T>>() + self.norms.heap_size() + self.disk_rows.heap_size(),
+            id_map: hash_map_size(&self.node_id_to_index),
+            ..MemoryUsage::default()
+        };
+
+        for node in &self.nodes {
+            usage.nodes += node.id.heap_size();
+            if let Some(document) = &node.document {
+                usage.documents += document.id.heap_size() + document_heap(&document.data);
+            }
+        }
+
+        if let Some(quantizer) = self.scalar_quantizer() {
+            usage.quantized += quantizer.min.heap_size() + quantizer.scale.heap_size();
+        }
+        if let Some(quantizer) = self.product_quantizer() {
+            usage.quantized += quantizer.centroids.heap_size();
+        }
+
+        usage
+    }
+}
+
+/// Approximate bytes of a hash map: its buckets, one control byte each, and the keys' heap
+fn hash_map_size<V>(map: &HashMap<String, V>) -> usize {
+    // Buckets are a power of two kept at most 7/8 full
+    let buckets = if map.capacity() == 0 { 0 } else { (map.capacity() * 8 / 7).next_power_of_two() };
+    buckets * (size_of::<(String, V)>() + 1) + map.keys().map(HeapSize::heap_size).sum::<usize>()
+}
@@ -0,0 +1,125 @@
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Upper bounds of the search latency buckets, in nanoseconds
+const LATENCY_BUCKETS: [u64; 14] = [
+    10_000, 25_000, 50_000, 100_000, 250_000, 500_000, 1_000_000, 2_500_000, 5_000_000,
+    10_000_000, 25_000_000, 50_000_000, 100_000_000, 1_000_000_000,
+];
+
+/// Upper bounds of the buckets counting work done by one search
+const WORK_BUCKETS: [u64; 14] = [
+    10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 25_000, 50_000, 100_000, 1_000_000,
+];
+
+/// A histogram of integer observations with fixed bucket bounds
+///
+/// Observations are recorded with relaxed atomics, so searches running in parallel can
+/// share one histogram.
+#[derive(Debug)]
+pub struct Histogram {
+    bounds: &'static [u64],
+    buckets: Vec<AtomicU64>, // one per bound, then one for larger observations
+    sum: AtomicU64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [u64]) -> Self {
+        Self { bounds, buckets: (0..=bounds.len()).map(|_| AtomicU64::new(0)).collect(), sum: AtomicU64::new(0) }
+    }
+
+    pub fn observe(&self, value: u64) {
+        let bucket = self.bounds.partition_point(|&bound| bound < value);
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.sum.fetch_add(value, Ordering::Relaxed);
+    }
+
+    /// Number of observations
+    pub fn count(&self) -> u64 {
+        self.buckets.iter().map(|bucket| bucket.load(Ordering::Relaxed)).sum()
+    }
+
+    /// Total of all observations
+    pub fn sum(&self) -> u64 {
+        self.sum.load(Ordering::Relaxed)
+    }
+
+    /// Write the histogram in Prometheus text format, dividing values by `scale`
+    fn render(&self, out: &mut String, name: &str, help: &str, scale: f64) {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} histogram", name);
+        let mut cumulative = 0;
+        for (bound, bucket) in self.bounds.iter().zip(&self.buckets) {
+            cumulative += bucket.load(Ordering::Relaxed);
+            let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, *bound as f64 / scale, cumulative);
+        }
+        cumulative += self.buckets[self.bounds.len()].load(Ordering::Relaxed);
+        let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, cumulative);
+        let _ = writeln!(out, "{}_sum {}", name, self.sum() as f64 / scale);
+        let _ = writeln!(out, "{}_count {}", name, cumulative);
+    }
+}
+
+/// Counters and histograms recorded by an index as it is used
+///
+/// Every `HNSWIndex` keeps one, readable through `metrics()`. They count from when the
+/// index was created or loaded and are not saved with it.
+#[derive(Debug)]
+pub struct IndexMetrics {
+    pub inserts: AtomicU64, // nodes inserted
+    pub removes: AtomicU64, // nodes removed
+    pub searches: AtomicU64, // searches, filtered or not
+    pub search_latency: Histogram, // nanoseconds per search
+    pub distance_computations: Histogram, // distances computed per search, including re-ranking
+    pub nodes_visited: Histogram, // nodes whose neighbors were explored per search
+}
+
+impl Default for IndexMetrics {
+    fn default() -> Self {
+        Self {
+            inserts: AtomicU64::new(0),
+            removes: AtomicU64::new(0),
+            searches: AtomicU64::new(0),
+            search_latency: Histogram::new(&LATENCY_BUCKETS),
+            distance_computations: Histogram::new(&WORK_BUCKETS),
+            nodes_visited: Histogram::new(&WORK_BUCKETS),
+        }
+    }
+}
+
+impl IndexMetrics {
+    pub(crate) fn record_search(&self, latency: Duration, work: SearchWork) {
+        self.searches.fetch_add(1, Ordering::Relaxed);
+        self.search_latency.observe(latency.as_nanos().min(u64::MAX as u128) as u64);
+        self.distance_computations.observe(work.distances);
+        self.nodes_visited.observe(work.visited);
+    }
+
+    /// The metrics in Prometheus text exposition format, each name starting with `prefix`
+    pub fn to_prometheus(&self, prefix: &str) -> String {
+        let mut out = String::new();
+        let counters = [
+            ("inserts_total", "Nodes inserted.", &self.inserts),
+            ("removes_total", "Nodes removed.", &self.removes),
+            ("searches_total", "Searches run.", &self.searches),
+        ];
+        for (name, help, counter) in counters {
+            let _ = writeln!(out, "# HELP {}_{} {}", prefix, name, help);
+            let _ = writeln!(out, "# TYPE {}_{} counter", prefix, name);
+            let _ = writeln!(out, "{}_{} {}", prefix, name, counter.load(Ordering::Relaxed));
+        }
+
+        self.search_latency.render(&mut out, &format!("{}_search_latency_seconds", prefix), "Time taken by a search.", 1e9);
+        self.distance_computations.render(&mut out, &format!("{}_search_distance_computations", prefix), "Distances computed by a search, including re-ranking.", 1.0);
+        self.nodes_visited.render(&mut out, &format!("{}_search_nodes_visited", prefix), "Nodes whose neighbors a search explored.", 1.0);
+        out
+    }
+}
+
+/// Work done by one search, tallied as it runs
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct SearchWork {
+    pub distances: u64,
+    pub visited: u64,
+}
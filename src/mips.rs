@@ -0,0 +1,69 @@
+/// Reduction of maximum inner product search to Euclidean nearest neighbor search
+///
+/// Graphs built directly on inner product distances tend to be poorly connected, because a
+/// vector is not necessarily its own best match. Appending one coordinate,
+/// `sqrt(max_norm² - ‖x‖²)`, gives every stored vector the same norm, after which the
+/// Euclidean ordering for a query extended with `0` matches the inner product ordering.
+/// Build a `DistanceMetric::Euclidean` index over transformed vectors and convert the
+/// returned distances back with [`MipsTransform::inner_product`].
+///
+/// The index only sees the extended vectors. Its dimension is one more than the data's,
+/// every vector inserted, alone or with `insert_parallel`, goes through `transform_item`
+/// and every query through `transform_query`. `get_embedding` returns the extended vector,
+/// whose leading components are the original. A vector longer than `max_norm` cannot be
+/// added later, so fit on every vector the index will hold or leave headroom with `new`.
+/// The transform is not saved with the index; keep `max_norm` and recreate it after `load`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MipsTransform {
+    pub max_norm: f32,
+}
+
+impl MipsTransform {
+    /// Create a transform for vectors with norm at most `max_norm`
+    pub fn new(max_norm: f32) -> Self {
+        Self { max_norm }
+    }
+
+    /// Create a transform from the largest norm in a set of vectors
+    pub fn fit<'a, I: IntoIterator<Item = &'a [f32]>>(vectors: I) -> Self {
+        let max_norm = vectors
+            .into_iter()
+            .map(|v| v.iter().map(|x| x * x).sum::<f32>().sqrt())
+            .fold(0.0, f32::max);
+        Self { max_norm }
+    }
+
+    /// Extend a vector to be stored in the index
+    pub fn transform_item(&self, vector: &[f32]) -> Result<Vec<f32>, String> {
+        let norm_sq: f32 = vector.iter().map(|x| x * x).sum();
+        let max_sq = self.max_norm * self.max_norm;
+        // Allow for rounding when the vector is the one that defined max_norm
+        if norm_sq > max_sq * (1.0 + 1e-5) {
+            return Err(format!(
+                "Vector norm {} exceeds the transform's maximum norm {}",
+                norm_sq.sqrt(), self.max_norm
+            ));
+        }
+
+        let mut extended = Vec::with_capacity(vector.len() + 1);
+        extended.extend_from_slice(vector);
+        extended.push((max_sq - norm_sq).max(0.0).sqrt());
+        Ok(extended)
+    }
+
+    /// Extend a query vector
+    pub fn transform_query(&self, query: &[f32]) -> Vec<f32> {
+        let mut extended = Vec::with_capacity(query.len() + 1);
+        extended.extend_from_slice(query);
+        extended.push(0.0);
+        extended
+    }
+
+    /// Recover the inner product from the Euclidean distance between a query and a transformed item
+    ///
+    /// `‖q - x'‖² = ‖q‖² + max_norm² - 2 q·x`, so the inner product follows from the distance.
+    pub fn inner_product(&self, query: &[f32], distance: f32) -> f32 {
+        let query_sq: f32 = query.iter().map(|x| x * x).sum();
+        (query_sq + self.max_norm * self.max_norm - distance * distance) / 2.0
+    }
+}
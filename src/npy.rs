@@ -0,0 +1,415 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use memmap2::Mmap;
+use crate::index::HNSWIndex;
+use crate::types::Distance;
+
+const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+
+/// A dense 2-D float matrix read from or written to a NumPy `.npy` file
+#[derive(Debug, Clone, PartialEq)]
+pub struct NpyMatrix {
+    pub rows: usize,
+    pub cols: usize,
+    pub data: Vec<f32>, // row-major, rows * cols values
+}
+
+impl NpyMatrix {
+    /// Create a matrix from row-major data
+    pub fn new(rows: usize, cols: usize, data: Vec<f32>) -> Result<Self, String> {
+        let values = rows
+            .checked_mul(cols)
+            .ok_or_else(|| format!("Matrix of shape ({}, {}) is too large", rows, cols))?;
+        if values != data.len() {
+            return Err(format!(
+                "Matrix of shape ({}, {}) needs {} values, got {}",
+                rows, cols, values, data.len()
+            ));
+        }
+
+        Ok(Self { rows, cols, data })
+    }
+
+    /// Get a single row of the matrix
+    pub fn row(&self, i: usize) -> &[f32] {
+        &self.data[i * self.cols..(i + 1) * self.cols]
+    }
+
+    /// Iterate over the rows of the matrix
+    pub fn iter_rows(&self) -> impl Iterator<Item = &[f32]> {
+        (0..self.rows).map(move |i| self.row(i))
+    }
+}
+
+/// Element types supported when reading `.npy` files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NpyDtype {
+    F16,
+    F32,
+    F64,
+}
+
+impl NpyDtype {
+    fn size(&self) -> usize {
+        match self {
+            NpyDtype::F16 => 2,
+            NpyDtype::F32 => 4,
+            NpyDtype::F64 => 8,
+        }
+    }
+}
+
+/// Read a 2-D float array from a `.npy` file into memory
+///
+/// Supports C-order arrays of `float16`, `float32` and `float64` in either byte order.
+/// Values are converted to `f32`.
+pub fn read_npy<P: AsRef<Path>>(path: P) -> Result<NpyMatrix, String> {
+    let path = path.as_ref();
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+    parse_npy(&bytes)
+}
+
+/// Read a 2-D float array from a memory-mapped `.npy` file
+///
+/// The file is mapped instead of being copied into a buffer first, so only the
+/// converted `f32` matrix is held in memory. To insert the rows into an index without
+/// building the matrix at all, use `NpyRows` with `insert_npy_rows`.
+pub fn read_npy_mmap<P: AsRef<Path>>(path: P) -> Result<NpyMatrix, String> {
+    let path = path.as_ref();
+    let file = File::open(path).map_err(|e| format!("Failed to open '{}': {}", path.display(), e))?;
+    // Safety: the mapping is only read while parsing and the caller is expected
+    // not to modify the file concurrently
+    let mmap = unsafe { Mmap::map(&file) }
+        .map_err(|e| format!("Failed to memory-map '{}': {}", path.display(), e))?;
+    parse_npy(&mmap)
+}
+
+/// Write a matrix as a C-order `float32` `.npy` file
+pub fn write_npy<P: AsRef<Path>>(path: P, matrix: &NpyMatrix) -> Result<(), String> {
+    let path = path.as_ref();
+    let file = File::create(path).map_err(|e| format!("Failed to create '{}': {}", path.display(), e))?;
+    let mut writer = BufWriter::new(file);
+
+    let mut header = format!(
+        "{{'descr': '<f4', 'fortran_order': False, 'shape': ({}, {}), }}",
+        matrix.rows, matrix.cols
+    );
+    // Pad with spaces so the data starts on a 64-byte boundary, terminated by a newline
+    let unpadded = NPY_MAGIC.len() + 2 + 2 + header.len() + 1;
+    header.push_str(&" ".repeat((64 - unpadded % 64) % 64));
+    header.push('\n');
+
+    let write_err = |e: std::io::Error| format!("Failed to write '{}': {}", path.display(), e);
+    writer.write_all(NPY_MAGIC).map_err(write_err)?;
+    writer.write_all(&[1, 0]).map_err(write_err)?;
+    writer.write_all(&(header.len() as u16).to_le_bytes()).map_err(write_err)?;
+    writer.write_all(header.as_bytes()).map_err(write_err)?;
+    for value in &matrix.data {
+        writer.write_all(&value.to_le_bytes()).map_err(write_err)?;
+    }
+    writer.flush().map_err(write_err)?;
+
+    Ok(())
+}
+
+/// A `.npy` file kept memory-mapped, with rows converted to `f32` as they are read
+///
+/// Unlike `read_npy_mmap`, nothing is copied up front, so this suits files larger than
+/// memory that are only read a few rows at a time.
+#[derive(Debug)]
+pub struct NpyRows {
+    mmap: Mmap,
+    layout: NpyLayout,
+}
+
+impl NpyRows {
+    /// Memory-map a 2-D float `.npy` file
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|e| format!("Failed to open '{}': {}", path.display(), e))?;
+        // Safety: the file is expected not to be modified while it is mapped
+        let mmap = unsafe { Mmap::map(&file) }
+            .map_err(|e| format!("Failed to memory-map '{}': {}", path.display(), e))?;
+        let layout = parse_layout(&mmap)?;
+        Ok(Self { mmap, layout })
+    }
+
+    /// Number of rows in the file
+    pub fn rows(&self) -> usize {
+        self.layout.rows
+    }
+
+    /// Number of columns in the file
+    pub fn cols(&self) -> usize {
+        self.layout.cols
+    }
+
+    /// Read a single row, or `None` if it is out of range
+    pub fn row(&self, i: usize) -> Option<Vec<f32>> {
+        if i >= self.layout.rows {
+            return None;
+        }
+        let row_bytes = self.layout.cols * self.layout.dtype.size();
+        let start = self.layout.data_start + i * row_bytes;
+        Some(decode_values(&self.mmap[start..start + row_bytes], self.layout.dtype, self.layout.little_endian))
+    }
+}
+
+/// Where and how the array data of a `.npy` file is stored
+#[derive(Debug, Clone, Copy)]
+struct NpyLayout {
+    data_start: usize,
+    little_endian: bool,
+    dtype: NpyDtype,
+    rows: usize,
+    cols: usize,
+}
+
+/// Parse the contents of a `.npy` file
+fn parse_npy(bytes: &[u8]) -> Result<NpyMatrix, String> {
+    let layout = parse_layout(bytes)?;
+    // Cannot overflow, since parse_layout checked the payload holds this many bytes
+    let expected = layout.rows * layout.cols * layout.dtype.size();
+    let payload = &bytes[layout.data_start..layout.data_start + expected];
+    NpyMatrix::new(layout.rows, layout.cols, decode_values(payload, layout.dtype, layout.little_endian))
+}
+
+/// Parse the header of a `.npy` file and check that the data it describes is present
+fn parse_layout(bytes: &[u8]) -> Result<NpyLayout, String> {
+    if bytes.len() < 10 || &bytes[..6] != NPY_MAGIC {
+        return Err("Not a .npy file (missing magic string)".to_string());
+    }
+
+    let major = bytes[6];
+    let (header_len, header_start) = match major {
+        1 => (u16::from_le_bytes([bytes[8], bytes[9]]) as usize, 10),
+        2 | 3 => {
+            if bytes.len() < 12 {
+                return Err("Truncated .npy header".to_string());
+            }
+            (u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]) as usize, 12)
+        }
+        _ => return Err(format!("Unsupported .npy format version {}", major)),
+    };
+
+    let data_start = header_start + header_len;
+    if bytes.len() < data_start {
+        return Err("Truncated .npy header".to_string());
+    }
+    let header = std::str::from_utf8(&bytes[header_start..data_start])
+        .map_err(|_| "Invalid .npy header encoding".to_string())?;
+
+    let descr = header_value(header, "descr")?;
+    let descr = descr.trim_matches(|c| c == '\'' || c == '"');
+    let (little_endian, dtype) = parse_descr(descr)?;
+
+    if header_value(header, "fortran_order")? != "False" {
+        return Err("Fortran-order arrays are not supported".to_string());
+    }
+
+    let shape = parse_shape(&header_value(header, "shape")?)?;
+    if shape.len() != 2 {
+        return Err(format!("Expected a 2-D array, got shape {:?}", shape));
+    }
+    let (rows, cols) = (shape[0], shape[1]);
+
+    let expected = rows
+        .checked_mul(cols)
+        .and_then(|values| values.checked_mul(dtype.size()))
+        .ok_or_else(|| format!("Array of shape ({}, {}) is too large", rows, cols))?;
+    let payload = &bytes[data_start..];
+    if payload.len() < expected {
+        return Err(format!(
+            "Truncated .npy data: expected {} bytes, got {}",
+            expected, payload.len()
+        ));
+    }
+
+    Ok(NpyLayout { data_start, little_endian, dtype, rows, cols })
+}
+
+/// Convert raw array data to `f32` values
+fn decode_values(payload: &[u8], dtype: NpyDtype, little_endian: bool) -> Vec<f32> {
+    match dtype {
+        NpyDtype::F16 => payload
+            .chunks_exact(2)
+            .map(|b| {
+                let bits = [b[0], b[1]];
+                let bits = if little_endian { u16::from_le_bytes(bits) } else { u16::from_be_bytes(bits) };
+                half::f16::from_bits(bits).to_f32()
+            })
+            .collect(),
+        NpyDtype::F32 => payload
+            .chunks_exact(4)
+            .map(|b| {
+                let bits = [b[0], b[1], b[2], b[3]];
+                if little_endian { f32::from_le_bytes(bits) } else { f32::from_be_bytes(bits) }
+            })
+            .collect(),
+        NpyDtype::F64 => payload
+            .chunks_exact(8)
+            .map(|b| {
+                let bits = [b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]];
+                let value = if little_endian { f64::from_le_bytes(bits) } else { f64::from_be_bytes(bits) };
+                value as f32
+            })
+            .collect(),
+    }
+}
+
+/// Extract the raw value of a key from the header dictionary literal
+fn header_value(header: &str, key: &str) -> Result<String, String> {
+    let pattern_single = format!("'{}'", key);
+    let pattern_double = format!("\"{}\"", key);
+    let key_pos = header
+        .find(&pattern_single)
+        .or_else(|| header.find(&pattern_double))
+        .ok_or_else(|| format!("Missing '{}' in .npy header", key))?;
+
+    let rest = &header[key_pos + key.len() + 2..];
+    let rest = rest.trim_start().strip_prefix(':')
+        .ok_or_else(|| format!("Malformed '{}' entry in .npy header", key))?
+        .trim_start();
+
+    // Tuples contain commas, so read up to the closing parenthesis
+    let end = if rest.starts_with('(') {
+        rest.find(')').map(|i| i + 1)
+    } else {
+        rest.find([',', '}'])
+    }
+    .ok_or_else(|| format!("Malformed '{}' entry in .npy header", key))?;
+
+    Ok(rest[..end].trim().to_string())
+}
+
+/// Parse a dtype descriptor such as `<f4`, returning (little_endian, dtype)
+fn parse_descr(descr: &str) -> Result<(bool, NpyDtype), String> {
+    let (little_endian, kind) = match descr.as_bytes().first() {
+        Some(b'<') | Some(b'=') | Some(b'|') => (true, &descr[1..]),
+        Some(b'>') => (false, &descr[1..]),
+        _ => (true, descr),
+    };
+
+    let dtype = match kind {
+        "f2" => NpyDtype::F16,
+        "f4" => NpyDtype::F32,
+        "f8" => NpyDtype::F64,
+        _ => return Err(format!("Unsupported .npy dtype '{}', expected float16, float32 or float64", descr)),
+    };
+
+    Ok((little_endian, dtype))
+}
+
+/// Parse a shape tuple such as `(1000, 128)`
+fn parse_shape(shape: &str) -> Result<Vec<usize>, String> {
+    shape
+        .trim_start_matches('(')
+        .trim_end_matches(')')
+        .split(',')
+        .map(|dim| dim.trim())
+        .filter(|dim| !dim.is_empty())
+        .map(|dim| dim.parse::<usize>().map_err(|_| format!("Invalid dimension '{}' in .npy shape", dim)))
+        .collect()
+}
+
+impl<T: Clone + Send + Sync, D: Distance + Sync> HNSWIndex<T, D> {
+    /// Build a new index from the rows of a `.npy` file
+    ///
+    /// Row `i` gets the id `ids[i]` when ids are supplied, or its row number otherwise.
+    /// Set `mmap` to memory-map the file and convert each row straight from the mapping,
+    /// instead of reading the whole matrix into memory first. Fails with the first row
+    /// that could not be inserted.
+    pub fn from_npy<P: AsRef<Path>>(
+        path: P,
+        m: usize,
+        ef_construction: usize,
+        distance_metric: D,
+        ids: Option<Vec<String>>,
+        mmap: bool,
+    ) -> Result<Self, String> {
+        let mut index;
+        let results = if mmap {
+            let rows = NpyRows::open(path)?;
+            index = Self::new_with_distance(rows.cols(), m, ef_construction, distance_metric);
+            index.insert_npy_rows(&rows, ids)?
+        } else {
+            let matrix = read_npy(path)?;
+            index = Self::new_with_distance(matrix.cols, m, ef_construction, distance_metric);
+            index.insert_matrix(&matrix, ids)?
+        };
+        if let Some(Err(e)) = results.into_iter().find(Result::is_err) {
+            return Err(e);
+        }
+        Ok(index)
+    }
+
+    /// Insert every row of a matrix into the index using parallel insertion
+    ///
+    /// Row `i` gets the id `ids[i]` when ids are supplied, or its row number otherwise.
+    pub fn insert_matrix(&mut self, matrix: &NpyMatrix, ids: Option<Vec<String>>) -> Result<Vec<Result<(), String>>, String> {
+        let items = row_ids(ids, matrix.rows)?
+            .into_iter()
+            .zip(matrix.iter_rows())
+            .map(|(id, row)| (id, row.to_vec(), None))
+            .collect();
+
+        self.insert_parallel(items)
+    }
+
+    /// Insert every row of a memory-mapped `.npy` file using parallel insertion
+    ///
+    /// Each row is converted to `f32` straight from the mapping, so the file's data is
+    /// copied once, into the vectors handed to the index. Row `i` gets the id `ids[i]`
+    /// when ids are supplied, or its row number otherwise.
+    pub fn insert_npy_rows(&mut self, rows: &NpyRows, ids: Option<Vec<String>>) -> Result<Vec<Result<(), String>>, String> {
+        let items = row_ids(ids, rows.rows())?
+            .into_iter()
+            .enumerate()
+            .map(|(i, id)| (id, rows.row(i).expect("row is in range"), None))
+            .collect();
+
+        self.insert_parallel(items)
+    }
+}
+
+/// Check supplied ids against the number of rows, or number the rows when there are none
+fn row_ids(ids: Option<Vec<String>>, rows: usize) -> Result<Vec<String>, String> {
+    match ids {
+        Some(ids) if ids.len() != rows => Err(format!("Got {} ids for {} rows", ids.len(), rows)),
+        Some(ids) => Ok(ids),
+        None => Ok((0..rows).map(|i| i.to_string()).collect()),
+    }
+}
+
+impl<T, D: Distance> HNSWIndex<T, D> {
+    /// Collect the index's vectors into a matrix, returning it with the id of each row
+    ///
+    /// Normalized embeddings are exported as they were inserted.
+    pub fn to_matrix(&self) -> Result<(NpyMatrix, Vec<String>), String> {
+        let cols = self.dimension().unwrap_or(0);
+        let mut data = Vec::with_capacity(self.nodes.len() * cols);
+        let mut ids = Vec::with_capacity(self.nodes.len());
+
+        for (slot, node) in self.nodes.iter().enumerate() {
+            let embedding = self.original_embedding_of(slot);
+            if embedding.len() != cols {
+                return Err(format!(
+                    "Node '{}' has dimension {}, expected {}",
+                    node.id, embedding.len(), cols
+                ));
+            }
+            data.extend_from_slice(&embedding);
+            ids.push(node.id.clone());
+        }
+
+        Ok((NpyMatrix::new(self.nodes.len(), cols, data)?, ids))
+    }
+
+    /// Write the index's vectors to a `float32` `.npy` file, returning the id of each row
+    pub fn export_npy<P: AsRef<Path>>(&self, path: P) -> Result<Vec<String>, String> {
+        let (matrix, ids) = self.to_matrix()?;
+        write_npy(path, &matrix)?;
+        Ok(ids)
+    }
+}
@@ -0,0 +1,126 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use crate::arena::Graph;
+use crate::index::HNSWIndex;
+use crate::types::Distance;
+
+/// Nodes of an index saved before vectors and links moved out of the nodes into arenas
+#[derive(Deserialize)]
+struct LegacyIndex {
+    nodes: Vec<LegacyNode>,
+}
+
+#[derive(Deserialize)]
+struct LegacyNode {
+    embedding: Vec<f32>,
+    connections: Vec<Vec<usize>>, // neighbors on each layer, bottom layer first
+}
+
+impl<T: Serialize, D: Serialize> HNSWIndex<T, D> {
+    /// Save the index as JSON, including its parameters, distance metric, graph and documents
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        let file = File::create(path).map_err(|e| format!("Failed to create '{}': {}", path.display(), e))?;
+        let mut writer = BufWriter::new(file);
+
+        serde_json::to_writer(&mut writer, self)
+            .map_err(|e| format!("Failed to serialize index: {}", e))?;
+        writer.flush().map_err(|e| format!("Failed to write '{}': {}", path.display(), e))?;
+
+        Ok(())
+    }
+}
+
+impl<T: DeserializeOwned, D: DeserializeOwned + Distance> HNSWIndex<T, D> {
+    /// Load an index written by `save`
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|e| format!("Failed to open '{}': {}", path.display(), e))?;
+
+        let mut index: Self = serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| format!("Failed to parse index '{}': {}", path.display(), e))?;
+
+        // Indexes saved before vectors and links moved into arenas keep them on each node,
+        // so their nodes are read again in that layout
+        let slots = index.nodes.len();
+        let legacy = if index.graph.is_empty() && slots > 0 {
+            let file = File::open(path).map_err(|e| format!("Failed to open '{}': {}", path.display(), e))?;
+            let legacy: LegacyIndex = serde_json::from_reader(BufReader::new(file))
+                .map_err(|e| format!("Failed to parse index '{}': {}", path.display(), e))?;
+            Some(legacy.nodes)
+        } else {
+            index.vectors.validate(slots)?;
+            index.raw_vectors.validate(slots)?;
+            index.norms.validate(slots)?;
+            index.codes.validate(slots)?;
+            index.half_vectors.validate(slots)?;
+            index.disk_rows.validate(slots)?;
+            index.graph.validate(slots)?;
+            None
+        };
+
+        // Check the graph before trusting it, since search indexes into nodes unchecked
+        for (i, node) in index.nodes.iter().enumerate() {
+            if index.node_id_to_index.insert(node.id.clone(), i).is_some() {
+                return Err(format!("Duplicate node id '{}' in saved index", node.id));
+            }
+            if let Some(legacy) = &legacy {
+                if legacy[i].connections.iter().flatten().any(|&neighbor| neighbor >= slots) {
+                    return Err(format!("Node '{}' links to a node that does not exist", node.id));
+                }
+                continue;
+            }
+            let in_memory = index.vectors.get(i).is_some();
+            let has_codes = index.is_quantized() && index.codes.get(i).is_some();
+            let on_disk = index.vector_file.is_some() && index.disk_rows.get(i).is_some();
+            let has_half = index.half_precision().is_some() && index.half_vectors.get(i).is_some();
+            if !in_memory && !has_codes && !on_disk && !has_half {
+                return Err(format!("Node '{}' has neither a vector nor quantized codes", node.id));
+            }
+        }
+
+        match index.entry_point {
+            Some(ep) if ep >= index.nodes.len() => {
+                return Err(format!("Entry point {} is out of range", ep));
+            }
+            None if !index.nodes.is_empty() => {
+                return Err("Saved index has nodes but no entry point".to_string());
+            }
+            _ => {}
+        }
+
+        index.open_vector_file()?;
+
+        if let Some(legacy) = legacy {
+            // Their embeddings may also predate normalization on insert, which push_node applies
+            index.graph = Graph::new(index.m_max, index.m);
+            let nodes = std::mem::take(&mut index.nodes);
+            for (slot, (node, saved)) in nodes.into_iter().zip(legacy).enumerate() {
+                index.push_node(node, saved.embedding, saved.connections.len().max(1))?;
+                for (layer, neighbors) in saved.connections.iter().enumerate() {
+                    index.graph.set_neighbors(slot, layer, neighbors);
+                }
+            }
+        }
+
+        // Indexes saved before the dimension was kept on the index take it from a vector
+        if index.dimension == 0 && !index.nodes.is_empty() {
+            index.dimension = match index.vectors.dimension() {
+                0 => index.original_embedding_of(0).len(),
+                dimension => dimension,
+            };
+        }
+        if index.vectors.dimension() != 0 && index.vectors.dimension() != index.dimension {
+            return Err(format!("Stored vectors have dimension {}, index expects {}", index.vectors.dimension(), index.dimension));
+        }
+
+        if let Some(dimension) = index.dimension() {
+            index.distance_metric.validate(dimension)?;
+        }
+
+        Ok(index)
+    }
+}
@@ -0,0 +1,202 @@
+use rand::seq::index::sample;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::index::HNSWIndex;
+use crate::quantization::Quantizer;
+use crate::simd;
+use crate::types::{ComponentSum, Distance};
+
+/// Compresses vectors to one byte per sub-space using k-means codebooks
+///
+/// The vector is split into `subspaces` equal slices, and each slice is replaced by the
+/// index of its nearest centroid in that sub-space's codebook. `centroids` is laid out as
+/// `subspaces x centroids_per_subspace x (dimension / subspaces)`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProductQuantizer {
+    pub dimension: usize,
+    pub subspaces: usize,
+    pub centroids_per_subspace: usize,
+    pub centroids: Vec<f32>,
+}
+
+impl ProductQuantizer {
+    /// Train one codebook per sub-space with `iterations` rounds of k-means
+    ///
+    /// At most 256 centroids fit in a byte code; fewer are trained if the sample is smaller
+    /// than `centroids_per_subspace`.
+    pub fn train(sample: &[&[f32]], subspaces: usize, centroids_per_subspace: usize, iterations: usize) -> Result<Self, String> {
+        let dimension = sample.first().map(|v| v.len()).ok_or_else(|| "Cannot train a quantizer on an empty sample".to_string())?;
+        if subspaces == 0 || dimension % subspaces != 0 {
+            return Err(format!("Dimension {} is not divisible into {} sub-spaces", dimension, subspaces));
+        }
+        if centroids_per_subspace == 0 || centroids_per_subspace > 256 {
+            return Err(format!("Expected 1 to 256 centroids per sub-space, got {}", centroids_per_subspace));
+        }
+        if let Some(vector) = sample.iter().find(|v| v.len() != dimension) {
+            return Err(format!("Sample vector has dimension {}, expected {}", vector.len(), dimension));
+        }
+
+        let centroids_per_subspace = centroids_per_subspace.min(sample.len());
+        let sub_dim = dimension / subspaces;
+
+        let centroids = (0..subspaces)
+            .into_par_iter()
+            .map(|s| {
+                let points: Vec<&[f32]> = sample.iter().map(|v| &v[s * sub_dim..(s + 1) * sub_dim]).collect();
+                kmeans(&points, centroids_per_subspace, iterations)
+            })
+            .collect::<Vec<_>>()
+            .concat();
+
+        Ok(Self { dimension, subspaces, centroids_per_subspace, centroids })
+    }
+
+    /// Length of each sub-vector
+    pub fn sub_dimension(&self) -> usize {
+        self.dimension / self.subspaces
+    }
+
+    /// Size in bytes of one encoded vector
+    pub fn code_size(&self) -> usize {
+        self.subspaces
+    }
+
+    /// Encode a vector as the nearest centroid in each sub-space
+    pub fn encode(&self, vector: &[f32]) -> Result<Vec<u8>, String> {
+        if vector.len() != self.dimension {
+            return Err(format!("Vector has dimension {}, quantizer expects {}", vector.len(), self.dimension));
+        }
+
+        let sub_dim = self.sub_dimension();
+        Ok((0..self.subspaces)
+            .map(|s| nearest_centroid(self.codebook(s), &vector[s * sub_dim..(s + 1) * sub_dim], sub_dim) as u8)
+            .collect())
+    }
+
+    /// Reconstruct an approximate vector from its codes
+    pub fn decode(&self, codes: &[u8]) -> Vec<f32> {
+        let mut vector = Vec::with_capacity(self.dimension);
+        self.decode_into(codes, &mut vector);
+        vector
+    }
+
+    /// Reconstruct an approximate vector into a reusable buffer
+    pub fn decode_into(&self, codes: &[u8], out: &mut Vec<f32>) {
+        out.clear();
+        let sub_dim = self.sub_dimension();
+        for (s, &code) in codes.iter().enumerate() {
+            let start = code as usize * sub_dim;
+            out.extend_from_slice(&self.codebook(s)[start..start + sub_dim]);
+        }
+    }
+
+    /// Partial sums between each sub-vector of a query and every centroid of its sub-space
+    ///
+    /// The distance to an encoded vector is then a lookup and add per sub-space, see
+    /// `table_sum`.
+    pub fn distance_table(&self, query: &[f32], sum: ComponentSum) -> Vec<f32> {
+        let sub_dim = self.sub_dimension();
+        let mut table = Vec::with_capacity(self.subspaces * self.centroids_per_subspace);
+        for s in 0..self.subspaces {
+            let sub_query = &query[s * sub_dim..(s + 1) * sub_dim];
+            for centroid in self.codebook(s).chunks_exact(sub_dim) {
+                table.push(match sum {
+                    ComponentSum::SquaredDifference => simd::l2_squared(sub_query, centroid),
+                    ComponentSum::Product => simd::dot(sub_query, centroid),
+                });
+            }
+        }
+        table
+    }
+
+    /// Add up the table entries selected by a vector's codes
+    pub fn table_sum(&self, table: &[f32], codes: &[u8]) -> f32 {
+        codes
+            .iter()
+            .enumerate()
+            .map(|(s, &code)| table[s * self.centroids_per_subspace + code as usize])
+            .sum()
+    }
+
+    fn codebook(&self, s: usize) -> &[f32] {
+        let size = self.centroids_per_subspace * self.sub_dimension();
+        &self.centroids[s * size..(s + 1) * size]
+    }
+}
+
+/// Index of the centroid closest to a point
+fn nearest_centroid(centroids: &[f32], point: &[f32], dim: usize) -> usize {
+    centroids
+        .chunks_exact(dim)
+        .map(|centroid| simd::l2_squared(point, centroid))
+        .enumerate()
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+/// Lloyd's k-means seeded with distinct sample points, returning `k x dim` centroids
+fn kmeans(points: &[&[f32]], k: usize, iterations: usize) -> Vec<f32> {
+    let dim = points[0].len();
+    let mut rng = rand::thread_rng();
+    let mut centroids: Vec<f32> = sample(&mut rng, points.len(), k)
+        .iter()
+        .flat_map(|i| points[i].iter().copied())
+        .collect();
+
+    for _ in 0..iterations {
+        let mut sums = vec![0.0; k * dim];
+        let mut counts = vec![0usize; k];
+        for point in points {
+            let c = nearest_centroid(&centroids, point, dim);
+            counts[c] += 1;
+            for (sum, value) in sums[c * dim..(c + 1) * dim].iter_mut().zip(point.iter()) {
+                *sum += value;
+            }
+        }
+
+        // Empty clusters keep their previous centroid
+        for c in 0..k {
+            if counts[c] > 0 {
+                for (centroid, sum) in centroids[c * dim..(c + 1) * dim].iter_mut().zip(&sums[c * dim..(c + 1) * dim]) {
+                    *centroid = sum / counts[c] as f32;
+                }
+            }
+        }
+    }
+
+    centroids
+}
+
+impl<T, D: Distance> HNSWIndex<T, D> {
+    /// Compress every stored vector with a product quantizer used for graph traversal
+    ///
+    /// Codebooks are trained on up to `sample_size` randomly chosen vectors with `iterations`
+    /// rounds of k-means, giving `subspaces` bytes per vector. For Euclidean, Cosine and inner
+    /// product distances, searches compare the query to codes through a per-query distance
+    /// table; other metrics compare against decoded vectors. Unless `keep_full_precision` is
+    /// set, the f32 vectors are dropped; see `spill_vectors` to keep them on disk instead.
+    /// Fails if the index already stores half precision or another quantizer's codes.
+    pub fn quantize_pq(
+        &mut self,
+        subspaces: usize,
+        centroids_per_subspace: usize,
+        sample_size: usize,
+        iterations: usize,
+        keep_full_precision: bool,
+    ) -> Result<(), String> {
+        self.check_storage_mode("product quantized")?;
+        let vectors = self.full_vectors()?;
+        let mut rng = rand::thread_rng();
+        let picked: Vec<&[f32]> = sample(&mut rng, vectors.len(), sample_size.min(vectors.len()))
+            .iter()
+            .map(|i| vectors[i].as_ref())
+            .collect();
+        let quantizer = ProductQuantizer::train(&picked, subspaces, centroids_per_subspace, iterations)?;
+
+        let codes = vectors.iter().map(|v| quantizer.encode(v)).collect::<Result<Vec<_>, _>>()?;
+        drop(vectors);
+
+        self.apply_codes(Quantizer::Product(quantizer), codes, keep_full_precision)
+    }
+}
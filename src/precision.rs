@@ -0,0 +1,81 @@
+use half::{bf16, f16};
+use serde::{Deserialize, Serialize};
+use crate::index::HNSWIndex;
+use crate::quantization::StorageMode;
+use crate::simd;
+
+/// 16-bit float formats for keeping vectors in half the memory of f32
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HalfFormat {
+    /// IEEE 754 half precision: 10-bit mantissa, range up to 65504
+    F16,
+    /// bfloat16: the upper half of an f32, with f32's range and a 7-bit mantissa
+    BF16,
+}
+
+impl HalfFormat {
+    /// Convert a vector to 16-bit floats, rounding to nearest
+    pub fn encode(&self, vector: &[f32]) -> Vec<u16> {
+        match self {
+            HalfFormat::F16 => vector.iter().map(|&x| f16::from_f32(x).to_bits()).collect(),
+            HalfFormat::BF16 => vector.iter().map(|&x| bf16::from_f32(x).to_bits()).collect(),
+        }
+    }
+
+    /// Convert 16-bit floats back to f32
+    pub fn decode(&self, bits: &[u16]) -> Vec<f32> {
+        let mut vector = Vec::with_capacity(bits.len());
+        self.decode_into(bits, &mut vector);
+        vector
+    }
+
+    /// Convert 16-bit floats back to f32 into a reusable buffer
+    pub fn decode_into(&self, bits: &[u16], out: &mut Vec<f32>) {
+        match self {
+            HalfFormat::F16 => simd::f16_to_f32(bits, out),
+            HalfFormat::BF16 => simd::bf16_to_f32(bits, out),
+        }
+    }
+}
+
+impl<T, D> HNSWIndex<T, D> {
+    /// Store full-precision vectors as 16-bit floats, or as f32 again with `None`
+    ///
+    /// Existing in-memory vectors are converted, and vectors inserted later are stored in the
+    /// new format. Distances convert them back to f32 on the fly. Vectors moved to disk with
+    /// `spill_vectors` stay there. Fails on a quantized index, since half precision and
+    /// quantization are alternative storage modes.
+    pub fn set_half_precision(&mut self, format: Option<HalfFormat>) -> Result<(), String> {
+        if format.is_some() {
+            self.check_storage_mode("half precision")?;
+        }
+        let current = self.half_precision();
+        for slot in 0..self.nodes.len() {
+            if let (Some(current), Some(bits)) = (current, self.half_vectors.get(slot)) {
+                self.vectors.set(slot, &current.decode(bits))?;
+            }
+            if let (Some(format), Some(vector)) = (format, self.vectors.get(slot)) {
+                self.half_vectors.set(slot, &format.encode(vector))?;
+            }
+        }
+        if format.is_some() {
+            self.vectors.clear_rows();
+        } else {
+            self.half_vectors.clear();
+        }
+        if let Some(format) = format {
+            self.storage = StorageMode::Half(format);
+        } else if current.is_some() {
+            self.storage = StorageMode::Full;
+        }
+        Ok(())
+    }
+
+    /// The 16-bit format full-precision vectors are stored in, if any
+    pub fn half_precision(&self) -> Option<HalfFormat> {
+        match self.storage {
+            StorageMode::Half(format) => Some(format),
+            _ => None,
+        }
+    }
+}
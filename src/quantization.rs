@@ -0,0 +1,422 @@
+use std::borrow::Cow;
+use std::path::Path;
+use rand::seq::index::sample;
+use serde::{Deserialize, Serialize};
+use crate::bq::BinaryQuantizer;
+use crate::index::HNSWIndex;
+use crate::npy::{write_npy, NpyMatrix, NpyRows};
+use crate::pq::ProductQuantizer;
+use crate::precision::HalfFormat;
+use crate::types::Distance;
+
+/// How the value range of a scalar quantizer is trained
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuantizationRange {
+    /// One min/max shared by every dimension
+    Global,
+    /// A separate min/max for each dimension
+    PerDimension,
+}
+
+/// Maps each f32 component onto 256 evenly spaced levels between a trained min and max
+///
+/// `min` and `scale` hold one entry for a global range or one per dimension.
+/// Values outside the trained range are clamped.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScalarQuantizer {
+    pub dimension: usize,
+    pub min: Vec<f32>,
+    pub scale: Vec<f32>, // value step between consecutive codes
+}
+
+impl ScalarQuantizer {
+    /// Train the quantizer's range from a sample of vectors
+    pub fn train<'a, I: IntoIterator<Item = &'a [f32]>>(sample: I, range: QuantizationRange) -> Result<Self, String> {
+        let mut sample = sample.into_iter().peekable();
+        let dimension = sample.peek().map(|v| v.len()).ok_or_else(|| "Cannot train a quantizer on an empty sample".to_string())?;
+
+        let mut min = vec![f32::INFINITY; dimension];
+        let mut max = vec![f32::NEG_INFINITY; dimension];
+        for vector in sample {
+            if vector.len() != dimension {
+                return Err(format!("Sample vector has dimension {}, expected {}", vector.len(), dimension));
+            }
+            for (d, &value) in vector.iter().enumerate() {
+                min[d] = min[d].min(value);
+                max[d] = max[d].max(value);
+            }
+        }
+
+        if range == QuantizationRange::Global {
+            min = vec![min.iter().copied().fold(f32::INFINITY, f32::min)];
+            max = vec![max.iter().copied().fold(f32::NEG_INFINITY, f32::max)];
+        }
+
+        // A constant dimension still needs a non-zero step to avoid dividing by zero
+        let scale = min
+            .iter()
+            .zip(max.iter())
+            .map(|(lo, hi)| if hi > lo { (hi - lo) / 255.0 } else { 1.0 })
+            .collect();
+
+        Ok(Self { dimension, min, scale })
+    }
+
+    /// Whether the quantizer has a separate range per dimension
+    pub fn is_per_dimension(&self) -> bool {
+        self.min.len() > 1
+    }
+
+    /// Quantize a vector to one byte per component
+    pub fn encode(&self, vector: &[f32]) -> Result<Vec<u8>, String> {
+        if vector.len() != self.dimension {
+            return Err(format!("Vector has dimension {}, quantizer expects {}", vector.len(), self.dimension));
+        }
+
+        Ok(vector
+            .iter()
+            .enumerate()
+            .map(|(d, &value)| {
+                let (min, scale) = self.range(d);
+                ((value - min) / scale).round().clamp(0.0, 255.0) as u8
+            })
+            .collect())
+    }
+
+    /// Reconstruct an approximate vector from its codes
+    pub fn decode(&self, codes: &[u8]) -> Vec<f32> {
+        let mut vector = Vec::with_capacity(codes.len());
+        self.decode_into(codes, &mut vector);
+        vector
+    }
+
+    /// Reconstruct an approximate vector into a reusable buffer
+    pub fn decode_into(&self, codes: &[u8], out: &mut Vec<f32>) {
+        out.clear();
+        if self.is_per_dimension() {
+            out.extend(codes.iter().zip(self.min.iter().zip(self.scale.iter())).map(|(&c, (min, scale))| min + c as f32 * scale));
+        } else {
+            let (min, scale) = (self.min[0], self.scale[0]);
+            out.extend(codes.iter().map(|&c| min + c as f32 * scale));
+        }
+    }
+
+    fn range(&self, d: usize) -> (f32, f32) {
+        if self.is_per_dimension() {
+            (self.min[d], self.scale[d])
+        } else {
+            (self.min[0], self.scale[0])
+        }
+    }
+}
+
+/// How an index stores the vectors it traverses
+///
+/// Quantization and half precision are alternatives, so an index is in one mode at a time.
+/// Set through `quantize_int8`, `quantize_pq`, `quantize_binary` and `set_half_precision`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) enum StorageMode {
+    /// f32 vectors in the vector arena
+    #[default]
+    Full,
+    /// Vectors as 16-bit floats
+    Half(HalfFormat),
+    /// Codes used for traversal, with the f32 vectors dropped when `quantized_only` is set
+    Quantized { quantizer: Quantizer, quantized_only: bool },
+}
+
+/// The quantizer of a quantized index
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) enum Quantizer {
+    Scalar(ScalarQuantizer),
+    Product(ProductQuantizer),
+    Binary(BinaryQuantizer),
+}
+
+impl Quantizer {
+    pub(crate) fn encode(&self, vector: &[f32]) -> Result<Vec<u8>, String> {
+        match self {
+            Quantizer::Scalar(quantizer) => quantizer.encode(vector),
+            Quantizer::Product(quantizer) => quantizer.encode(vector),
+            Quantizer::Binary(quantizer) => quantizer.encode(vector),
+        }
+    }
+
+    pub(crate) fn decode_into(&self, codes: &[u8], out: &mut Vec<f32>) {
+        match self {
+            Quantizer::Scalar(quantizer) => quantizer.decode_into(codes, out),
+            Quantizer::Product(quantizer) => quantizer.decode_into(codes, out),
+            Quantizer::Binary(quantizer) => quantizer.decode_into(codes, out),
+        }
+    }
+}
+
+impl StorageMode {
+    /// The mode regardless of its parameters, as named by `check_storage_mode`
+    fn kind(&self) -> &'static str {
+        match self {
+            StorageMode::Half(_) => "half precision",
+            mode => mode.name(),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            StorageMode::Full => "f32",
+            StorageMode::Half(HalfFormat::F16) => "F16",
+            StorageMode::Half(HalfFormat::BF16) => "BF16",
+            StorageMode::Quantized { quantizer: Quantizer::Scalar(_), .. } => "int8",
+            StorageMode::Quantized { quantizer: Quantizer::Product(_), .. } => "product quantized",
+            StorageMode::Quantized { quantizer: Quantizer::Binary(_), .. } => "binary",
+        }
+    }
+}
+
+/// State for comparing one prepared query against stored nodes
+pub(crate) struct QueryDistance<'a> {
+    query: &'a [f32],
+    table: Option<Vec<f32>>, // product quantization distance table, if the metric allows one
+    bits: Option<Vec<u8>>,   // sign bits of the query for binary quantization
+    scratch: Vec<f32>,       // reused to decode codes without allocating for every comparison
+}
+
+impl<T, D: Distance> HNSWIndex<T, D> {
+    /// Quantize every stored vector to int8 codes, which are then used for graph traversal
+    ///
+    /// The quantizer's range is trained on up to `sample_size` randomly chosen vectors, and
+    /// vectors inserted later are encoded with the same range. Unless `keep_full_precision`
+    /// is set, the f32 vectors are dropped to save memory, which also rules out re-ranking.
+    /// Fails if the index already stores half precision or another quantizer's codes.
+    pub fn quantize_int8(&mut self, range: QuantizationRange, sample_size: usize, keep_full_precision: bool) -> Result<(), String> {
+        self.check_storage_mode("int8")?;
+        let vectors = self.full_vectors()?;
+        let mut rng = rand::thread_rng();
+        let picked = sample(&mut rng, vectors.len(), sample_size.min(vectors.len()));
+        let quantizer = ScalarQuantizer::train(picked.iter().map(|i| vectors[i].as_ref()), range)?;
+
+        let codes = vectors.iter().map(|v| quantizer.encode(v)).collect::<Result<Vec<_>, _>>()?;
+        drop(vectors);
+
+        self.apply_codes(Quantizer::Scalar(quantizer), codes, keep_full_precision)
+    }
+
+    /// Prepare to compare a prepared query against stored nodes
+    pub(crate) fn query_distance<'a>(&self, query: &'a [f32]) -> QueryDistance<'a> {
+        let (table, bits) = match (self.quantizer(), self.distance_metric.component_sum()) {
+            (Some(Quantizer::Product(quantizer)), Some(sum)) if query.len() == quantizer.dimension => {
+                (Some(quantizer.distance_table(query, sum)), None)
+            }
+            (Some(Quantizer::Binary(quantizer)), _) => (None, quantizer.encode(query).ok()),
+            _ => (None, None),
+        };
+        QueryDistance { query, table, bits, scratch: Vec::new() }
+    }
+
+    /// Distance from a query to a node, using its codes when the index is quantized
+    ///
+    /// Binary quantized indexes return the Hamming distance between sign bits.
+    pub(crate) fn node_distance(&self, query: &mut QueryDistance, slot: usize) -> f32 {
+        match (&self.storage, self.codes.get(slot), self.half_vectors.get(slot)) {
+            (StorageMode::Quantized { quantizer, .. }, Some(codes), _) => {
+                match (quantizer, &query.table, &query.bits) {
+                    (Quantizer::Binary(quantizer), _, Some(bits)) => return quantizer.hamming(bits, codes) as f32,
+                    (Quantizer::Product(quantizer), Some(table), _) => {
+                        return self.distance_metric.finish_component_sum(quantizer.table_sum(table, codes));
+                    }
+                    _ => quantizer.decode_into(codes, &mut query.scratch),
+                }
+                return self.stored_distance(query.query, &query.scratch);
+            }
+            (StorageMode::Half(format), _, Some(bits)) => {
+                format.decode_into(bits, &mut query.scratch);
+                return self.stored_distance(query.query, &query.scratch);
+            }
+            _ => {}
+        }
+        self.stored_distance(query.query, &self.stored_embedding(slot))
+    }
+
+    /// Distance between two stored nodes, on the same scale as `node_distance`
+    pub(crate) fn nodes_distance(&self, a: usize, b: usize) -> f32 {
+        if let (Some(Quantizer::Binary(quantizer)), Some(a_codes), Some(b_codes)) = (self.quantizer(), self.codes.get(a), self.codes.get(b)) {
+            return quantizer.hamming(a_codes, b_codes) as f32;
+        }
+        self.stored_distance(&self.stored_embedding(a), &self.stored_embedding(b))
+    }
+
+    /// Re-score candidates against the full-precision vectors, keeping the best `k`
+    ///
+    /// Candidates whose full-precision vector was dropped keep their approximate distance.
+    pub(crate) fn rerank(&self, query: &[f32], candidates: &mut Vec<(usize, f32)>, k: usize) {
+        for (id, dist) in candidates.iter_mut() {
+            if let Some(vector) = self.full_vector(*id) {
+                *dist = self.stored_distance(query, &vector);
+            }
+        }
+        candidates.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+        candidates.truncate(k);
+    }
+}
+
+impl<T, D> HNSWIndex<T, D> {
+    /// Whether traversal uses quantized codes
+    pub fn is_quantized(&self) -> bool {
+        self.quantizer().is_some()
+    }
+
+    /// Whether the full-precision vectors were dropped when the index was quantized
+    pub fn quantized_only(&self) -> bool {
+        matches!(self.storage, StorageMode::Quantized { quantized_only: true, .. })
+    }
+
+    /// The int8 quantizer, if the index was quantized with `quantize_int8`
+    pub fn scalar_quantizer(&self) -> Option<&ScalarQuantizer> {
+        match self.quantizer() {
+            Some(Quantizer::Scalar(quantizer)) => Some(quantizer),
+            _ => None,
+        }
+    }
+
+    /// The product quantizer, if the index was quantized with `quantize_pq`
+    pub fn product_quantizer(&self) -> Option<&ProductQuantizer> {
+        match self.quantizer() {
+            Some(Quantizer::Product(quantizer)) => Some(quantizer),
+            _ => None,
+        }
+    }
+
+    /// The binary quantizer, if the index was quantized with `quantize_binary`
+    pub fn binary_quantizer(&self) -> Option<&BinaryQuantizer> {
+        match self.quantizer() {
+            Some(Quantizer::Binary(quantizer)) => Some(quantizer),
+            _ => None,
+        }
+    }
+
+    /// Name of the mode vectors are stored in: `f32`, `F16`, `BF16`, `int8`,
+    /// `product quantized` or `binary`
+    pub fn storage_mode(&self) -> &'static str {
+        self.storage.name()
+    }
+
+    pub(crate) fn quantizer(&self) -> Option<&Quantizer> {
+        match &self.storage {
+            StorageMode::Quantized { quantizer, .. } => Some(quantizer),
+            _ => None,
+        }
+    }
+
+    /// Fail unless the index stores f32 vectors or is already in the kind of mode named
+    /// `kind`, since storage modes cannot be combined
+    pub(crate) fn check_storage_mode(&self, kind: &str) -> Result<(), String> {
+        match self.storage.kind() {
+            "f32" => Ok(()),
+            current if current == kind => Ok(()),
+            _ => Err(format!("Index already stores vectors as {}, which cannot be combined with {}", self.storage.name(), kind)),
+        }
+    }
+
+    /// Move the full-precision vectors to a `.npy` file that is memory-mapped for re-ranking
+    ///
+    /// The in-memory vectors are dropped. Quantize the index first so traversal can use codes
+    /// instead of reading the file; vectors inserted later stay in memory. The file must not
+    /// change while the index uses it, and is reopened when a saved index is loaded.
+    pub fn spill_vectors<P: AsRef<Path>>(&mut self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        let vectors = self.full_vectors()?;
+        let cols = vectors.first().map(|v| v.len()).unwrap_or(0);
+        if let Some(i) = vectors.iter().position(|v| v.len() != cols) {
+            return Err(format!("Node '{}' has dimension {}, expected {}", self.nodes[i].id, vectors[i].len(), cols));
+        }
+        let data = vectors.iter().flat_map(|v| v.iter().copied()).collect();
+        drop(vectors);
+
+        write_npy(path, &NpyMatrix::new(self.nodes.len(), cols, data)?)?;
+        let rows = NpyRows::open(path)?;
+
+        self.disk_rows.clear();
+        for slot in 0..self.nodes.len() {
+            self.disk_rows.set(slot, &[slot as u32])?;
+        }
+        self.half_vectors.clear();
+        self.vectors.clear_rows();
+        self.vector_file = Some(path.to_path_buf());
+        self.vector_rows = Some(rows);
+        Ok(())
+    }
+
+    /// Reopen the file written by `spill_vectors`
+    pub(crate) fn open_vector_file(&mut self) -> Result<(), String> {
+        if let Some(path) = &self.vector_file {
+            let rows = NpyRows::open(path)?;
+            if let Some(slot) = (0..self.nodes.len()).find(|&slot| self.disk_rows.value(slot).is_some_and(|row| row as usize >= rows.rows())) {
+                return Err(format!("Node '{}' refers to a row past the end of '{}'", self.nodes[slot].id, path.display()));
+            }
+            self.vector_rows = Some(rows);
+        }
+        Ok(())
+    }
+
+    /// Get a node's full-precision vector as used for traversal, from memory or disk
+    pub(crate) fn full_vector(&self, slot: usize) -> Option<Cow<'_, [f32]>> {
+        if let Some(vector) = self.vectors.get(slot) {
+            return Some(Cow::Borrowed(vector));
+        }
+        if let (StorageMode::Half(format), Some(bits)) = (&self.storage, self.half_vectors.get(slot)) {
+            return Some(Cow::Owned(format.decode(bits)));
+        }
+        match (&self.vector_rows, self.disk_rows.value(slot)) {
+            (Some(rows), Some(row)) => rows.row(row as usize).map(Cow::Owned),
+            _ => None,
+        }
+    }
+
+    /// Get every node's full-precision vector, failing if any has been dropped
+    pub(crate) fn full_vectors(&self) -> Result<Vec<Cow<'_, [f32]>>, String> {
+        (0..self.nodes.len())
+            .map(|slot| self.full_vector(slot).ok_or_else(|| format!("Node '{}' no longer has a full-precision vector", self.nodes[slot].id)))
+            .collect()
+    }
+
+    /// Switch to a quantizer and store the codes of every node, optionally dropping the f32
+    /// vectors
+    pub(crate) fn apply_codes(&mut self, quantizer: Quantizer, codes: Vec<Vec<u8>>, keep_full_precision: bool) -> Result<(), String> {
+        self.codes.clear();
+        for (slot, codes) in codes.iter().enumerate() {
+            self.codes.set(slot, codes)?;
+        }
+        if !keep_full_precision {
+            self.vectors.clear_rows();
+        }
+        self.storage = StorageMode::Quantized { quantizer, quantized_only: !keep_full_precision };
+        Ok(())
+    }
+
+    /// Get a node's vector as used for traversal, decoding it if only its codes are stored
+    pub(crate) fn stored_embedding(&self, slot: usize) -> Cow<'_, [f32]> {
+        if let Some(vector) = self.full_vector(slot) {
+            return vector;
+        }
+        let mut vector = Vec::new();
+        if let (Some(quantizer), Some(codes)) = (self.quantizer(), self.codes.get(slot)) {
+            quantizer.decode_into(codes, &mut vector);
+        }
+        Cow::Owned(vector)
+    }
+
+    /// Get a node's vector as it was inserted, reconstructing it if needed
+    ///
+    /// For normalized embeddings this is the kept raw vector, or the stored unit vector
+    /// scaled back by its original norm.
+    pub(crate) fn original_embedding_of(&self, slot: usize) -> Cow<'_, [f32]> {
+        if let Some(raw) = self.raw_vectors.get(slot) {
+            return Cow::Borrowed(raw);
+        }
+
+        let embedding = self.stored_embedding(slot);
+        match self.norms.value(slot) {
+            Some(norm) if norm > 0.0 => Cow::Owned(embedding.iter().map(|x| x * norm).collect()),
+            _ => embedding,
+        }
+    }
+}
@@ -1,7 +1,8 @@
+use std::sync::atomic::Ordering;
 use crate::index::HNSWIndex;
-use crate::types::Document;
+use crate::types::{Distance, Document};
 
-impl<T> HNSWIndex<T> {
+impl<T, D: Distance> HNSWIndex<T, D> {
     /// Remove a node from the index
     pub fn remove(&mut self, id: &str) -> Result<Option<Document<T>>, String> {
         let node_index = match self.node_id_to_index.remove(id) {
@@ -9,26 +10,21 @@ impl<T> HNSWIndex<T> {
             None => return Err(format!("Node with id '{}' not found", id)),
         };
         
-        let removed_node = self.nodes.remove(node_index);
+        self.repair_links(node_index);
         
-        // Update node_id_to_index for remaining nodes
-        for (_, idx) in self.node_id_to_index.iter_mut() {
-            if *idx > node_index {
-                *idx -= 1;
-            }
-        }
-        
-        // Remove connections to the removed node
-        for node in &mut self.nodes {
-            for layer_connections in &mut node.connections {
-                layer_connections.retain(|&x| x != node_index);
-                // Update indices for nodes that came after the removed node
-                for connection in layer_connections.iter_mut() {
-                    if *connection > node_index {
-                        *connection -= 1;
-                    }
-                }
-            }
+        // Move the last node into the freed slot, so no other node is renumbered
+        let slots = self.nodes.len();
+        let last = slots - 1;
+        let removed_node = self.nodes.swap_remove(node_index);
+        self.vectors.swap_remove(node_index, slots);
+        self.raw_vectors.swap_remove(node_index, slots);
+        self.norms.swap_remove(node_index, slots);
+        self.codes.swap_remove(node_index, slots);
+        self.half_vectors.swap_remove(node_index, slots);
+        self.disk_rows.swap_remove(node_index, slots);
+        self.graph.swap_remove(node_index);
+        if node_index != last {
+            self.node_id_to_index.insert(self.nodes[node_index].id.clone(), node_index);
         }
         
         // Update entry point if it was the removed node
@@ -40,8 +36,8 @@ impl<T> HNSWIndex<T> {
                 let mut best_entry_point = 0;
                 let mut max_level = 0;
                 
-                for (i, node) in self.nodes.iter().enumerate() {
-                    let level = node.connections.len().saturating_sub(1);
+                for i in 0..self.nodes.len() {
+                    let level = self.graph.layers(i).saturating_sub(1);
                     if level > max_level {
                         max_level = level;
                         best_entry_point = i;
@@ -50,12 +46,11 @@ impl<T> HNSWIndex<T> {
                 
                 Some(best_entry_point)
             };
-        } else if let Some(ep) = self.entry_point {
-            if ep > node_index {
-                self.entry_point = Some(ep - 1);
-            }
+        } else if self.entry_point == Some(last) {
+            self.entry_point = Some(node_index);
         }
         
+        self.metrics.removes.fetch_add(1, Ordering::Relaxed);
         Ok(removed_node.document)
     }
 
@@ -80,10 +75,58 @@ impl<T> HNSWIndex<T> {
         Ok(removed_docs)
     }
 
+    /// Relink every node that links to a node about to be removed, choosing among its
+    /// other neighbors and the removed node's neighbors, so that search can still reach
+    /// what was only reachable through it
+    fn repair_links(&mut self, node_index: usize) {
+        for layer in 0..self.graph.layers(node_index) {
+            let orphaned: Vec<usize> = self.graph.neighbors(node_index, layer)
+                .iter()
+                .map(|&neighbor| neighbor as usize)
+                .filter(|&neighbor| neighbor != node_index)
+                .collect();
+            let linking: Vec<usize> = (0..self.nodes.len())
+                .filter(|&other| other != node_index && self.graph.neighbors(other, layer).contains(&(node_index as u32)))
+                .collect();
+            
+            for other in linking {
+                let mut candidates: Vec<usize> = self.graph.neighbors(other, layer)
+                    .iter()
+                    .map(|&neighbor| neighbor as usize)
+                    .chain(orphaned.iter().copied())
+                    .filter(|&neighbor| neighbor != node_index && neighbor != other)
+                    .collect();
+                candidates.sort_unstable();
+                candidates.dedup();
+                
+                let mut candidates: Vec<(usize, f32)> = candidates
+                    .into_iter()
+                    .map(|neighbor| (neighbor, self.nodes_distance(other, neighbor)))
+                    .collect();
+                candidates.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+                
+                let m_layer = if layer == 0 { self.m_max } else { self.m };
+                let neighbors = self.select_neighbors(&candidates, m_layer);
+                self.graph.set_neighbors(other, layer, &neighbors);
+            }
+        }
+    }
+}
+
+impl<T, D> HNSWIndex<T, D> {
+
     /// Clear all nodes from the index
     pub fn clear(&mut self) {
         self.nodes.clear();
+        self.vectors.clear();
+        self.raw_vectors.clear();
+        self.norms.clear();
+        self.codes.clear();
+        self.half_vectors.clear();
+        self.disk_rows.clear();
+        self.graph.clear();
         self.node_id_to_index.clear();
+        self.dimension = 0;
         self.entry_point = None;
     }
 
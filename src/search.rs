@@ -1,103 +1,127 @@
-use std::collections::HashSet;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashSet};
+use std::time::Instant;
 use crate::index::HNSWIndex;
-use crate::types::Document;
+use crate::metrics::SearchWork;
+use crate::quantization::QueryDistance;
+use crate::types::{Distance, Document};
 
-impl<T> HNSWIndex<T> {
+impl<T, D: Distance> HNSWIndex<T, D> {
     /// Search for nearest neighbors in a specific layer
-    pub(crate) fn search_layer(&self, query: &[f32], entry_points: &[usize], layer: usize, k: usize) -> Vec<(usize, f32)> {
-        let mut candidates = Vec::new();
+    ///
+    /// The query must already be prepared with `prepare_query`, and `query_distance` is
+    /// built from it once per search so every layer shares its tables.
+    pub(crate) fn search_layer(&self, query_distance: &mut QueryDistance, entry_points: &[usize], layer: usize, k: usize) -> Vec<(usize, f32)> {
+        self.search_layer_filtered(query_distance, entry_points, layer, k, &|_| true, &mut SearchWork::default())
+    }
+
+    /// Search a layer, keeping only nodes accepted by `accept` as results
+    ///
+    /// Rejected nodes are still traversed, so they can lead to accepted ones. Distances
+    /// computed and nodes expanded are added to `work`.
+    pub(crate) fn search_layer_filtered(&self, query_distance: &mut QueryDistance, entry_points: &[usize], layer: usize, k: usize, accept: &dyn Fn(usize) -> bool, work: &mut SearchWork) -> Vec<(usize, f32)> {
         let mut visited = HashSet::new();
+        let mut candidates = BinaryHeap::new(); // nodes to expand, closest first
+        let mut results = BinaryHeap::new(); // best accepted nodes, furthest first
         
-        // Initialize candidates with entry points
         for &ep in entry_points {
-            if let Some(node) = self.nodes.get(ep) {
-                // For layer 0, all nodes should be accessible
-                if layer == 0 || layer < node.connections.len() {
-                    let dist = self.distance(query, &node.embedding);
-                    candidates.push((ep, dist));
-                    visited.insert(ep);
+            if ep < self.nodes.len() && layer < self.graph.layers(ep) && visited.insert(ep) {
+                let dist = self.node_distance(query_distance, ep);
+                work.distances += 1;
+                candidates.push(Reverse(Scored(dist, ep)));
+                if accept(ep) {
+                    results.push(Scored(dist, ep));
                 }
             }
         }
+        while results.len() > k {
+            results.pop();
+        }
         
-        candidates.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
-        
-        let mut dynamic_candidates = candidates.clone();
-        
-        while !dynamic_candidates.is_empty() {
-            let (current_id, _) = dynamic_candidates.remove(0);
+        while let Some(Reverse(Scored(dist, current_id))) = candidates.pop() {
+            // Every node left is further than the furthest result kept
+            if results.len() >= k && results.peek().is_some_and(|furthest: &Scored| dist > furthest.0) {
+                break;
+            }
+            work.visited += 1;
             
-            if let Some(node) = self.nodes.get(current_id) {
-                // For layer 0, check all nodes; for higher layers, check connections
-                let neighbors = if layer == 0 {
-                    // In layer 0, we can consider all nodes as potential neighbors
-                    (0..self.nodes.len()).filter(|&i| i != current_id).collect::<Vec<_>>()
-                } else if layer < node.connections.len() {
-                    node.connections[layer].clone()
-                } else {
+            for &neighbor_id in self.graph.neighbors(current_id, layer) {
+                let neighbor_id = neighbor_id as usize;
+                if !visited.insert(neighbor_id) {
                     continue;
-                };
+                }
                 
-                for neighbor_id in neighbors {
-                    if !visited.contains(&neighbor_id) {
-                        visited.insert(neighbor_id);
-                        
-                        if let Some(neighbor) = self.nodes.get(neighbor_id) {
-                            let dist = self.distance(query, &neighbor.embedding);
-                            
-                            if candidates.len() < k || dist < candidates.last().unwrap().1 {
-                                candidates.push((neighbor_id, dist));
-                                candidates.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
-                                
-                                if candidates.len() > k {
-                                    candidates.pop();
-                                }
-                                
-                                dynamic_candidates.push((neighbor_id, dist));
-                                dynamic_candidates.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
-                                
-                                if dynamic_candidates.len() > self.ef_construction {
-                                    dynamic_candidates.pop();
-                                }
-                            }
+                let dist = self.node_distance(query_distance, neighbor_id);
+                work.distances += 1;
+                if results.len() < k || results.peek().is_some_and(|furthest| dist < furthest.0) {
+                    candidates.push(Reverse(Scored(dist, neighbor_id)));
+                    if accept(neighbor_id) {
+                        results.push(Scored(dist, neighbor_id));
+                        if results.len() > k {
+                            results.pop();
                         }
                     }
                 }
             }
         }
         
-        candidates
+        // Ties are broken by node index so equidistant results come back in a stable order
+        results.into_sorted_vec().into_iter().map(|Scored(dist, id)| (id, dist)).collect()
     }
 
     /// Search for k nearest neighbors
     pub fn search(&self, query: &[f32], k: usize) -> Vec<(String, f32, Option<&Document<T>>)> {
-        if self.entry_point.is_none() || self.nodes.is_empty() {
+        self.search_filtered(query, k, |_, _| true)
+    }
+
+    /// Search for the k nearest neighbors accepted by `filter`, which sees each node's id
+    /// and document
+    ///
+    /// Upper layers are traversed without the filter; only the results on layer 0 are
+    /// restricted to accepted nodes. A query of the wrong dimension finds nothing.
+    pub fn search_filtered<F>(&self, query: &[f32], k: usize, filter: F) -> Vec<(String, f32, Option<&Document<T>>)>
+    where
+        F: Fn(&str, Option<&Document<T>>) -> bool,
+    {
+        if k == 0 || self.entry_point.is_none() || self.nodes.is_empty() || self.dimension() != Some(query.len()) {
             return Vec::new();
         }
         
-        let mut entry_points = vec![self.entry_point.unwrap()];
+        let started = Instant::now();
+        let mut work = SearchWork::default();
+        let query = self.prepare_query(query);
+        let query = query.as_ref();
+        let mut query_distance = self.query_distance(query);
+        let entry_point = self.entry_point.unwrap();
+        let mut entry_points = vec![entry_point];
         
-        // Find the highest layer that has nodes
-        let max_existing_layer = self.nodes.iter()
-            .map(|node| node.connections.len().saturating_sub(1))
-            .max()
-            .unwrap_or(0);
-        
-        // Search from the highest existing layer down to layer 1
-        for layer in (1..=max_existing_layer).rev() {
-            if !entry_points.is_empty() {
-                let candidates = self.search_layer(query, &entry_points, layer, 1);
+        // Greedily descend from the entry point's top layer down to layer 1
+        for layer in (1..self.graph.layers(entry_point)).rev() {
+            let candidates = self.search_layer_filtered(&mut query_distance, &entry_points, layer, 1, &|_| true, &mut work);
+            if !candidates.is_empty() {
                 entry_points = candidates.into_iter().map(|(id, _)| id).collect();
             }
         }
         
-        // If no higher layers exist, start from entry point
-        if max_existing_layer == 0 {
-            entry_points = vec![self.entry_point.unwrap()];
-        }
-        
-        // Search in layer 0
-        let candidates = self.search_layer(query, &entry_points, 0, k);
+        // Search in layer 0, fetching extra candidates when they will be re-ranked.
+        // Hamming distances mean little to callers, so binary codes are always rescored.
+        let rerank = self.rerank_candidates.or(self.binary_quantizer().map(|_| k));
+        let ef = self.ef_search.unwrap_or(self.ef_construction).max(k);
+        let accept = |slot: usize| filter(&self.nodes[slot].id, self.nodes[slot].document.as_ref());
+        let candidates = match rerank {
+            Some(n) if self.is_quantized() => {
+                let mut candidates = self.search_layer_filtered(&mut query_distance, &entry_points, 0, n.max(ef), &accept, &mut work);
+                work.distances += candidates.len() as u64;
+                self.rerank(query, &mut candidates, k);
+                candidates
+            }
+            _ => {
+                let mut candidates = self.search_layer_filtered(&mut query_distance, &entry_points, 0, ef, &accept, &mut work);
+                candidates.truncate(k);
+                candidates
+            }
+        };
+        self.metrics.record_search(started.elapsed(), work);
         
         candidates.into_iter().map(|(id, dist)| {
             let node = &self.nodes[id];
@@ -105,3 +129,21 @@ impl<T> HNSWIndex<T> {
         }).collect()
     }
 }
+
+/// A node and its distance from the query, ordered by distance and then by node
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Scored(f32, usize);
+
+impl Eq for Scored {}
+
+impl Ord for Scored {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0).then(self.1.cmp(&other.1))
+    }
+}
+
+impl PartialOrd for Scored {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
@@ -0,0 +1,435 @@
+use std::sync::OnceLock;
+
+/// Instruction set used for distance kernels
+///
+/// The best backend supported by the running CPU is detected once and used by the
+/// free functions in this module, which back `DistanceMetric`'s Euclidean, Cosine and
+/// inner product distances. Each backend can also be called directly, e.g. to compare
+/// against the scalar code in benchmarks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimdBackend {
+    Scalar,
+    Avx2,
+    Avx512,
+    Neon,
+}
+
+impl SimdBackend {
+    /// Detect the fastest backend supported by the running CPU
+    pub fn detect() -> Self {
+        [SimdBackend::Avx512, SimdBackend::Avx2, SimdBackend::Neon]
+            .into_iter()
+            .find(|backend| backend.is_available())
+            .unwrap_or(SimdBackend::Scalar)
+    }
+
+    /// Check whether the running CPU supports this backend
+    pub fn is_available(&self) -> bool {
+        match self {
+            SimdBackend::Scalar => true,
+            #[cfg(target_arch = "x86_64")]
+            SimdBackend::Avx2 => is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma"),
+            #[cfg(target_arch = "x86_64")]
+            SimdBackend::Avx512 => is_x86_feature_detected!("avx512f"),
+            #[cfg(target_arch = "aarch64")]
+            SimdBackend::Neon => std::arch::is_aarch64_feature_detected!("neon"),
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
+
+    /// Squared Euclidean distance, falling back to scalar code if the backend is unavailable
+    pub fn l2_squared(&self, a: &[f32], b: &[f32]) -> f32 {
+        match self.checked() {
+            #[cfg(target_arch = "x86_64")]
+            SimdBackend::Avx2 => unsafe { avx2::l2_squared(a, b) },
+            #[cfg(target_arch = "x86_64")]
+            SimdBackend::Avx512 => unsafe { avx512::l2_squared(a, b) },
+            #[cfg(target_arch = "aarch64")]
+            SimdBackend::Neon => unsafe { neon::l2_squared(a, b) },
+            _ => scalar::l2_squared(a, b),
+        }
+    }
+
+    /// Dot product, falling back to scalar code if the backend is unavailable
+    pub fn dot(&self, a: &[f32], b: &[f32]) -> f32 {
+        match self.checked() {
+            #[cfg(target_arch = "x86_64")]
+            SimdBackend::Avx2 => unsafe { avx2::dot(a, b) },
+            #[cfg(target_arch = "x86_64")]
+            SimdBackend::Avx512 => unsafe { avx512::dot(a, b) },
+            #[cfg(target_arch = "aarch64")]
+            SimdBackend::Neon => unsafe { neon::dot(a, b) },
+            _ => scalar::dot(a, b),
+        }
+    }
+
+    /// Cosine distance computed in a single pass, falling back to scalar code if the backend is unavailable
+    pub fn cosine_distance(&self, a: &[f32], b: &[f32]) -> f32 {
+        let (dot, norm_a_sq, norm_b_sq) = match self.checked() {
+            #[cfg(target_arch = "x86_64")]
+            SimdBackend::Avx2 => unsafe { avx2::dot_and_norms(a, b) },
+            #[cfg(target_arch = "x86_64")]
+            SimdBackend::Avx512 => unsafe { avx512::dot_and_norms(a, b) },
+            #[cfg(target_arch = "aarch64")]
+            SimdBackend::Neon => unsafe { neon::dot_and_norms(a, b) },
+            _ => scalar::dot_and_norms(a, b),
+        };
+
+        // A zero vector has no direction, so it is as far as an orthogonal one; this matches
+        // 1 - dot on the stored unit vectors, where normalize leaves zero vectors at zero
+        if norm_a_sq == 0.0 || norm_b_sq == 0.0 {
+            1.0
+        } else {
+            1.0 - dot / (norm_a_sq.sqrt() * norm_b_sq.sqrt())
+        }
+    }
+
+    /// Number of differing bits between two bit strings, using the popcount instruction when available
+    pub fn hamming(&self, a: &[u8], b: &[u8]) -> u32 {
+        match self.checked() {
+            #[cfg(target_arch = "x86_64")]
+            SimdBackend::Avx2 | SimdBackend::Avx512 if is_x86_feature_detected!("popcnt") => unsafe { popcnt::hamming(a, b) },
+            _ => scalar::hamming(a, b),
+        }
+    }
+
+    /// Convert IEEE half-precision bits to f32, using F16C instructions when available
+    pub fn f16_to_f32(&self, src: &[u16], out: &mut Vec<f32>) {
+        match self.checked() {
+            #[cfg(target_arch = "x86_64")]
+            SimdBackend::Avx2 | SimdBackend::Avx512 if is_x86_feature_detected!("f16c") => unsafe { f16c::f16_to_f32(src, out) },
+            _ => scalar::f16_to_f32(src, out),
+        }
+    }
+
+    /// Return this backend if the CPU supports it, or the scalar backend otherwise
+    fn checked(&self) -> SimdBackend {
+        // The detected backend is always available, so skip re-checking CPU features for it
+        if *self == backend() || self.is_available() {
+            *self
+        } else {
+            SimdBackend::Scalar
+        }
+    }
+}
+
+/// The backend detected for the running CPU
+pub fn backend() -> SimdBackend {
+    static BACKEND: OnceLock<SimdBackend> = OnceLock::new();
+    *BACKEND.get_or_init(SimdBackend::detect)
+}
+
+/// Squared Euclidean distance using the detected backend
+pub fn l2_squared(a: &[f32], b: &[f32]) -> f32 {
+    backend().l2_squared(a, b)
+}
+
+/// Dot product using the detected backend
+pub fn dot(a: &[f32], b: &[f32]) -> f32 {
+    backend().dot(a, b)
+}
+
+/// Cosine distance using the detected backend
+pub fn cosine_distance(a: &[f32], b: &[f32]) -> f32 {
+    backend().cosine_distance(a, b)
+}
+
+/// Hamming distance between bit strings using the detected backend
+pub fn hamming(a: &[u8], b: &[u8]) -> u32 {
+    backend().hamming(a, b)
+}
+
+/// Convert IEEE half-precision bits to f32 into a reusable buffer using the detected backend
+pub fn f16_to_f32(src: &[u16], out: &mut Vec<f32>) {
+    backend().f16_to_f32(src, out)
+}
+
+/// Convert bfloat16 bits to f32 into a reusable buffer
+///
+/// bfloat16 is the upper half of an f32, so this is a shift the compiler vectorizes.
+pub fn bf16_to_f32(src: &[u16], out: &mut Vec<f32>) {
+    out.clear();
+    out.extend(src.iter().map(|&bits| f32::from_bits((bits as u32) << 16)));
+}
+
+/// Scale a vector to unit length in place, returning its original norm
+///
+/// Zero vectors are left unchanged.
+pub fn normalize(vector: &mut [f32]) -> f32 {
+    let norm = dot(vector, vector).sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+    norm
+}
+
+/// Portable kernels, also used for the tails of the vectorized ones
+mod scalar {
+    pub fn l2_squared(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b.iter()).map(|(x, y)| (x - y) * (x - y)).sum()
+    }
+
+    pub fn dot(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b.iter()).map(|(x, y)| x * y).sum()
+    }
+
+    /// Compares eight bytes at a time; inlined so callers with extra target features get them too
+    #[inline(always)]
+    pub fn hamming(a: &[u8], b: &[u8]) -> u32 {
+        let len = a.len().min(b.len());
+        let (a, b) = (&a[..len], &b[..len]);
+        let words: u32 = a
+            .chunks_exact(8)
+            .zip(b.chunks_exact(8))
+            .map(|(x, y)| (u64::from_le_bytes(x.try_into().unwrap()) ^ u64::from_le_bytes(y.try_into().unwrap())).count_ones())
+            .sum();
+        let tail = len - len % 8;
+        words + a[tail..].iter().zip(&b[tail..]).map(|(x, y)| (x ^ y).count_ones()).sum::<u32>()
+    }
+
+    pub fn f16_to_f32(src: &[u16], out: &mut Vec<f32>) {
+        out.clear();
+        out.extend(src.iter().map(|&bits| half::f16::from_bits(bits).to_f32()));
+    }
+
+    pub fn dot_and_norms(a: &[f32], b: &[f32]) -> (f32, f32, f32) {
+        a.iter().zip(b.iter()).fold((0.0, 0.0, 0.0), |(dot, na, nb), (x, y)| {
+            (dot + x * y, na + x * x, nb + y * y)
+        })
+    }
+}
+
+/// Scalar Hamming distance compiled to use the popcount instruction
+#[cfg(target_arch = "x86_64")]
+mod popcnt {
+    #[target_feature(enable = "popcnt")]
+    pub unsafe fn hamming(a: &[u8], b: &[u8]) -> u32 {
+        super::scalar::hamming(a, b)
+    }
+}
+
+/// Half to single precision conversion with the F16C extension, eight values at a time
+#[cfg(target_arch = "x86_64")]
+mod f16c {
+    use std::arch::x86_64::*;
+
+    #[target_feature(enable = "avx,f16c")]
+    pub unsafe fn f16_to_f32(src: &[u16], out: &mut Vec<f32>) {
+        out.clear();
+        out.reserve(src.len());
+        let dst = out.as_mut_ptr();
+
+        let chunks = src.len() / 8;
+        for i in 0..chunks {
+            let halves = _mm_loadu_si128(src.as_ptr().add(i * 8) as *const __m128i);
+            _mm256_storeu_ps(dst.add(i * 8), _mm256_cvtph_ps(halves));
+        }
+        for (i, &bits) in src.iter().enumerate().skip(chunks * 8) {
+            *dst.add(i) = half::f16::from_bits(bits).to_f32();
+        }
+        out.set_len(src.len());
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod avx2 {
+    use std::arch::x86_64::*;
+    use super::scalar;
+
+    #[target_feature(enable = "avx2,fma")]
+    unsafe fn hsum(v: __m256) -> f32 {
+        let sum = _mm_add_ps(_mm256_castps256_ps128(v), _mm256_extractf128_ps(v, 1));
+        let sum = _mm_add_ps(sum, _mm_movehl_ps(sum, sum));
+        let sum = _mm_add_ss(sum, _mm_movehdup_ps(sum));
+        _mm_cvtss_f32(sum)
+    }
+
+    #[target_feature(enable = "avx2,fma")]
+    pub unsafe fn l2_squared(a: &[f32], b: &[f32]) -> f32 {
+        let n = a.len().min(b.len());
+        let (pa, pb) = (a.as_ptr(), b.as_ptr());
+        let mut acc0 = _mm256_setzero_ps();
+        let mut acc1 = _mm256_setzero_ps();
+        let mut i = 0;
+        while i + 16 <= n {
+            let d0 = _mm256_sub_ps(_mm256_loadu_ps(pa.add(i)), _mm256_loadu_ps(pb.add(i)));
+            let d1 = _mm256_sub_ps(_mm256_loadu_ps(pa.add(i + 8)), _mm256_loadu_ps(pb.add(i + 8)));
+            acc0 = _mm256_fmadd_ps(d0, d0, acc0);
+            acc1 = _mm256_fmadd_ps(d1, d1, acc1);
+            i += 16;
+        }
+        if i + 8 <= n {
+            let d = _mm256_sub_ps(_mm256_loadu_ps(pa.add(i)), _mm256_loadu_ps(pb.add(i)));
+            acc0 = _mm256_fmadd_ps(d, d, acc0);
+            i += 8;
+        }
+        hsum(_mm256_add_ps(acc0, acc1)) + scalar::l2_squared(&a[i..n], &b[i..n])
+    }
+
+    #[target_feature(enable = "avx2,fma")]
+    pub unsafe fn dot(a: &[f32], b: &[f32]) -> f32 {
+        let n = a.len().min(b.len());
+        let (pa, pb) = (a.as_ptr(), b.as_ptr());
+        let mut acc0 = _mm256_setzero_ps();
+        let mut acc1 = _mm256_setzero_ps();
+        let mut i = 0;
+        while i + 16 <= n {
+            acc0 = _mm256_fmadd_ps(_mm256_loadu_ps(pa.add(i)), _mm256_loadu_ps(pb.add(i)), acc0);
+            acc1 = _mm256_fmadd_ps(_mm256_loadu_ps(pa.add(i + 8)), _mm256_loadu_ps(pb.add(i + 8)), acc1);
+            i += 16;
+        }
+        if i + 8 <= n {
+            acc0 = _mm256_fmadd_ps(_mm256_loadu_ps(pa.add(i)), _mm256_loadu_ps(pb.add(i)), acc0);
+            i += 8;
+        }
+        hsum(_mm256_add_ps(acc0, acc1)) + scalar::dot(&a[i..n], &b[i..n])
+    }
+
+    #[target_feature(enable = "avx2,fma")]
+    pub unsafe fn dot_and_norms(a: &[f32], b: &[f32]) -> (f32, f32, f32) {
+        let n = a.len().min(b.len());
+        let (pa, pb) = (a.as_ptr(), b.as_ptr());
+        let mut dot = _mm256_setzero_ps();
+        let mut norm_a = _mm256_setzero_ps();
+        let mut norm_b = _mm256_setzero_ps();
+        let mut i = 0;
+        while i + 8 <= n {
+            let va = _mm256_loadu_ps(pa.add(i));
+            let vb = _mm256_loadu_ps(pb.add(i));
+            dot = _mm256_fmadd_ps(va, vb, dot);
+            norm_a = _mm256_fmadd_ps(va, va, norm_a);
+            norm_b = _mm256_fmadd_ps(vb, vb, norm_b);
+            i += 8;
+        }
+        let (tail_dot, tail_a, tail_b) = scalar::dot_and_norms(&a[i..n], &b[i..n]);
+        (hsum(dot) + tail_dot, hsum(norm_a) + tail_a, hsum(norm_b) + tail_b)
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod avx512 {
+    use std::arch::x86_64::*;
+
+    /// Mask selecting the first `len` (< 16) lanes, used to load the tail without a scalar loop
+    fn tail_mask(len: usize) -> __mmask16 {
+        ((1u32 << len) - 1) as __mmask16
+    }
+
+    #[target_feature(enable = "avx512f")]
+    pub unsafe fn l2_squared(a: &[f32], b: &[f32]) -> f32 {
+        let n = a.len().min(b.len());
+        let (pa, pb) = (a.as_ptr(), b.as_ptr());
+        let mut acc = _mm512_setzero_ps();
+        let mut i = 0;
+        while i + 16 <= n {
+            let d = _mm512_sub_ps(_mm512_loadu_ps(pa.add(i)), _mm512_loadu_ps(pb.add(i)));
+            acc = _mm512_fmadd_ps(d, d, acc);
+            i += 16;
+        }
+        if i < n {
+            let mask = tail_mask(n - i);
+            let d = _mm512_sub_ps(_mm512_maskz_loadu_ps(mask, pa.add(i)), _mm512_maskz_loadu_ps(mask, pb.add(i)));
+            acc = _mm512_fmadd_ps(d, d, acc);
+        }
+        _mm512_reduce_add_ps(acc)
+    }
+
+    #[target_feature(enable = "avx512f")]
+    pub unsafe fn dot(a: &[f32], b: &[f32]) -> f32 {
+        let n = a.len().min(b.len());
+        let (pa, pb) = (a.as_ptr(), b.as_ptr());
+        let mut acc = _mm512_setzero_ps();
+        let mut i = 0;
+        while i + 16 <= n {
+            acc = _mm512_fmadd_ps(_mm512_loadu_ps(pa.add(i)), _mm512_loadu_ps(pb.add(i)), acc);
+            i += 16;
+        }
+        if i < n {
+            let mask = tail_mask(n - i);
+            acc = _mm512_fmadd_ps(_mm512_maskz_loadu_ps(mask, pa.add(i)), _mm512_maskz_loadu_ps(mask, pb.add(i)), acc);
+        }
+        _mm512_reduce_add_ps(acc)
+    }
+
+    #[target_feature(enable = "avx512f")]
+    pub unsafe fn dot_and_norms(a: &[f32], b: &[f32]) -> (f32, f32, f32) {
+        let n = a.len().min(b.len());
+        let (pa, pb) = (a.as_ptr(), b.as_ptr());
+        let mut dot = _mm512_setzero_ps();
+        let mut norm_a = _mm512_setzero_ps();
+        let mut norm_b = _mm512_setzero_ps();
+        let mut i = 0;
+        while i < n {
+            let (va, vb) = if i + 16 <= n {
+                (_mm512_loadu_ps(pa.add(i)), _mm512_loadu_ps(pb.add(i)))
+            } else {
+                let mask = tail_mask(n - i);
+                (_mm512_maskz_loadu_ps(mask, pa.add(i)), _mm512_maskz_loadu_ps(mask, pb.add(i)))
+            };
+            dot = _mm512_fmadd_ps(va, vb, dot);
+            norm_a = _mm512_fmadd_ps(va, va, norm_a);
+            norm_b = _mm512_fmadd_ps(vb, vb, norm_b);
+            i += 16;
+        }
+        (_mm512_reduce_add_ps(dot), _mm512_reduce_add_ps(norm_a), _mm512_reduce_add_ps(norm_b))
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod neon {
+    use std::arch::aarch64::*;
+    use super::scalar;
+
+    #[target_feature(enable = "neon")]
+    pub unsafe fn l2_squared(a: &[f32], b: &[f32]) -> f32 {
+        let n = a.len().min(b.len());
+        let (pa, pb) = (a.as_ptr(), b.as_ptr());
+        let mut acc0 = vdupq_n_f32(0.0);
+        let mut acc1 = vdupq_n_f32(0.0);
+        let mut i = 0;
+        while i + 8 <= n {
+            let d0 = vsubq_f32(vld1q_f32(pa.add(i)), vld1q_f32(pb.add(i)));
+            let d1 = vsubq_f32(vld1q_f32(pa.add(i + 4)), vld1q_f32(pb.add(i + 4)));
+            acc0 = vfmaq_f32(acc0, d0, d0);
+            acc1 = vfmaq_f32(acc1, d1, d1);
+            i += 8;
+        }
+        vaddvq_f32(vaddq_f32(acc0, acc1)) + scalar::l2_squared(&a[i..n], &b[i..n])
+    }
+
+    #[target_feature(enable = "neon")]
+    pub unsafe fn dot(a: &[f32], b: &[f32]) -> f32 {
+        let n = a.len().min(b.len());
+        let (pa, pb) = (a.as_ptr(), b.as_ptr());
+        let mut acc0 = vdupq_n_f32(0.0);
+        let mut acc1 = vdupq_n_f32(0.0);
+        let mut i = 0;
+        while i + 8 <= n {
+            acc0 = vfmaq_f32(acc0, vld1q_f32(pa.add(i)), vld1q_f32(pb.add(i)));
+            acc1 = vfmaq_f32(acc1, vld1q_f32(pa.add(i + 4)), vld1q_f32(pb.add(i + 4)));
+            i += 8;
+        }
+        vaddvq_f32(vaddq_f32(acc0, acc1)) + scalar::dot(&a[i..n], &b[i..n])
+    }
+
+    #[target_feature(enable = "neon")]
+    pub unsafe fn dot_and_norms(a: &[f32], b: &[f32]) -> (f32, f32, f32) {
+        let n = a.len().min(b.len());
+        let (pa, pb) = (a.as_ptr(), b.as_ptr());
+        let mut dot = vdupq_n_f32(0.0);
+        let mut norm_a = vdupq_n_f32(0.0);
+        let mut norm_b = vdupq_n_f32(0.0);
+        let mut i = 0;
+        while i + 4 <= n {
+            let va = vld1q_f32(pa.add(i));
+            let vb = vld1q_f32(pb.add(i));
+            dot = vfmaq_f32(dot, va, vb);
+            norm_a = vfmaq_f32(norm_a, va, va);
+            norm_b = vfmaq_f32(norm_b, vb, vb);
+            i += 4;
+        }
+        let (tail_dot, tail_a, tail_b) = scalar::dot_and_norms(&a[i..n], &b[i..n]);
+        (vaddvq_f32(dot) + tail_dot, vaddvq_f32(norm_a) + tail_a, vaddvq_f32(norm_b) + tail_b)
+    }
+}
@@ -1,40 +1,269 @@
 use serde::{Deserialize, Serialize};
+use crate::simd;
 
 /// Distance metric types supported by the HNSW index
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+///
+/// Weighted Euclidean distance carries a weight per dimension, so it is a separate
+/// `Distance` type, `WeightedEuclidean`, and this enum stays `Copy`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum DistanceMetric {
     Euclidean,
     Cosine,
+    /// Negated dot product, so that larger inner products rank as closer
+    InnerProduct,
+    /// L1 distance
+    Manhattan,
+    /// L∞ distance
+    Chebyshev,
+    /// Lp distance for the given p (a metric for p >= 1)
+    Minkowski(f32),
+}
+
+// Minkowski orders are compared bit for bit, so equality is reflexive even for NaN
+impl PartialEq for DistanceMetric {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (DistanceMetric::Minkowski(p), DistanceMetric::Minkowski(q)) => p.to_bits() == q.to_bits(),
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+        }
+    }
+}
+
+impl Eq for DistanceMetric {}
+
+impl std::hash::Hash for DistanceMetric {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        if let DistanceMetric::Minkowski(p) = self {
+            p.to_bits().hash(state);
+        }
+    }
+}
+
+/// L2 distance with a weight per dimension, `sqrt(sum(w_i * (a_i - b_i)²))`
+///
+/// Needs one weight per dimension of the index's vectors.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WeightedEuclidean {
+    pub weights: Vec<f32>,
+}
+
+/// Per-component sum that a distance is computed from
+///
+/// Distances built on such a sum can be evaluated from partial sums over sub-vectors,
+/// which product quantization precomputes into lookup tables for each query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComponentSum {
+    /// `sum((a_i - b_i)²)`
+    SquaredDifference,
+    /// `sum(a_i * b_i)`
+    Product,
 }
 
 /// Trait for distance calculations between vectors
+///
+/// Implement this to plug a custom metric into `HNSWIndex`. Smaller values mean closer
+/// vectors; values may be negative, but must be comparable (not NaN).
 pub trait Distance {
     fn distance(&self, a: &[f32], b: &[f32]) -> f32;
+
+    /// Whether the index should scale vectors to unit length before using them
+    ///
+    /// When true, vectors are normalized once on insert and queries once per search, and
+    /// the index compares them with `normalized_distance` instead of `distance`.
+    fn normalizes(&self) -> bool {
+        false
+    }
+
+    /// Distance between two unit-length vectors, used when `normalizes` returns true
+    fn normalized_distance(&self, a: &[f32], b: &[f32]) -> f32 {
+        self.distance(a, b)
+    }
+
+    /// The component sum the distance between stored vectors is computed from, if any
+    fn component_sum(&self) -> Option<ComponentSum> {
+        None
+    }
+
+    /// Turn a component sum into the distance between stored vectors
+    fn finish_component_sum(&self, sum: f32) -> f32 {
+        sum
+    }
+
+    /// Check that the metric can compare vectors of the given dimension
+    ///
+    /// Indexes call this with the dimension of their first vector, and again on load.
+    fn validate(&self, _dimension: usize) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+impl<D: Distance + ?Sized> Distance for Box<D> {
+    fn distance(&self, a: &[f32], b: &[f32]) -> f32 {
+        (**self).distance(a, b)
+    }
+
+    fn normalizes(&self) -> bool {
+        (**self).normalizes()
+    }
+
+    fn normalized_distance(&self, a: &[f32], b: &[f32]) -> f32 {
+        (**self).normalized_distance(a, b)
+    }
+
+    fn component_sum(&self) -> Option<ComponentSum> {
+        (**self).component_sum()
+    }
+
+    fn finish_component_sum(&self, sum: f32) -> f32 {
+        (**self).finish_component_sum(sum)
+    }
+
+    fn validate(&self, dimension: usize) -> Result<(), String> {
+        (**self).validate(dimension)
+    }
 }
 
 impl Distance for DistanceMetric {
     fn distance(&self, a: &[f32], b: &[f32]) -> f32 {
         match self {
-            DistanceMetric::Euclidean => {
+            // Euclidean, Cosine and InnerProduct use SIMD kernels selected for the running CPU
+            DistanceMetric::Euclidean => simd::l2_squared(a, b).sqrt(),
+            DistanceMetric::Cosine => simd::cosine_distance(a, b),
+            DistanceMetric::InnerProduct => -simd::dot(a, b),
+            DistanceMetric::Manhattan => {
+                a.iter().zip(b.iter()).map(|(x, y)| (x - y).abs()).sum()
+            }
+            DistanceMetric::Chebyshev => {
+                a.iter().zip(b.iter()).map(|(x, y)| (x - y).abs()).fold(0.0, f32::max)
+            }
+            DistanceMetric::Minkowski(p) => {
                 a.iter()
                     .zip(b.iter())
-                    .map(|(x, y)| (x - y).powi(2))
+                    .map(|(x, y)| (x - y).abs().powf(*p))
                     .sum::<f32>()
-                    .sqrt()
-            }
-            DistanceMetric::Cosine => {
-                let dot_product: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
-                let norm_a: f32 = a.iter().map(|x| x.powi(2)).sum::<f32>().sqrt();
-                let norm_b: f32 = b.iter().map(|x| x.powi(2)).sum::<f32>().sqrt();
-                
-                if norm_a == 0.0 || norm_b == 0.0 {
-                    0.0
-                } else {
-                    1.0 - (dot_product / (norm_a * norm_b))
-                }
+                    .powf(1.0 / p)
             }
         }
     }
+
+    fn normalizes(&self) -> bool {
+        // Cosine indexes store unit vectors so each comparison is a single dot product
+        matches!(self, DistanceMetric::Cosine)
+    }
+
+    fn normalized_distance(&self, a: &[f32], b: &[f32]) -> f32 {
+        match self {
+            DistanceMetric::Cosine => 1.0 - simd::dot(a, b),
+            _ => self.distance(a, b),
+        }
+    }
+
+    fn component_sum(&self) -> Option<ComponentSum> {
+        match self {
+            DistanceMetric::Euclidean => Some(ComponentSum::SquaredDifference),
+            // Cosine vectors are stored normalized, so only the dot product is left
+            DistanceMetric::Cosine | DistanceMetric::InnerProduct => Some(ComponentSum::Product),
+            _ => None,
+        }
+    }
+
+    fn finish_component_sum(&self, sum: f32) -> f32 {
+        match self {
+            DistanceMetric::Euclidean => sum.max(0.0).sqrt(),
+            DistanceMetric::Cosine => 1.0 - sum,
+            DistanceMetric::InnerProduct => -sum,
+            _ => sum,
+        }
+    }
+
+    fn validate(&self, _dimension: usize) -> Result<(), String> {
+        match self {
+            DistanceMetric::Minkowski(p) => check_minkowski_order(*p),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Distance for WeightedEuclidean {
+    fn distance(&self, a: &[f32], b: &[f32]) -> f32 {
+        a.iter()
+            .zip(b.iter())
+            .zip(self.weights.iter())
+            .map(|((x, y), w)| w * (x - y).powi(2))
+            .sum::<f32>()
+            .sqrt()
+    }
+
+    fn validate(&self, dimension: usize) -> Result<(), String> {
+        check_weights(&self.weights)?;
+        if self.weights.len() != dimension {
+            return Err(format!(
+                "Weighted Euclidean has {} weights for vectors of dimension {}",
+                self.weights.len(), dimension
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl DistanceMetric {
+    /// Minkowski distance of order `p`, which must be positive
+    pub fn minkowski(p: f32) -> Result<Self, String> {
+        check_minkowski_order(p)?;
+        Ok(DistanceMetric::Minkowski(p))
+    }
+}
+
+impl WeightedEuclidean {
+    /// Weighted Euclidean distance, with one finite, non-negative weight per dimension
+    pub fn new(weights: Vec<f32>) -> Result<Self, String> {
+        check_weights(&weights)?;
+        Ok(Self { weights })
+    }
+}
+
+fn check_minkowski_order(p: f32) -> Result<(), String> {
+    if p.is_finite() && p > 0.0 {
+        Ok(())
+    } else {
+        Err(format!("Invalid Minkowski order '{}', expected a positive number", p))
+    }
+}
+
+/// Negative weights could make the sum under the square root negative, and so the distance NaN
+fn check_weights(weights: &[f32]) -> Result<(), String> {
+    match weights.iter().position(|w| !w.is_finite() || *w < 0.0) {
+        Some(i) => Err(format!("Weight {} is {}, expected a finite, non-negative number", i, weights[i])),
+        None => Ok(()),
+    }
+}
+
+impl std::str::FromStr for DistanceMetric {
+    type Err = String;
+
+    /// Parse a metric name such as `euclidean`, `cosine`, `ip` or `minkowski:3`
+    ///
+    /// Weighted Euclidean needs its weights and is a separate type, `WeightedEuclidean`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "euclidean" | "l2" => Ok(DistanceMetric::Euclidean),
+            "cosine" => Ok(DistanceMetric::Cosine),
+            "inner_product" | "ip" | "dot" => Ok(DistanceMetric::InnerProduct),
+            "manhattan" | "l1" => Ok(DistanceMetric::Manhattan),
+            "chebyshev" | "linf" => Ok(DistanceMetric::Chebyshev),
+            name => match name.strip_prefix("minkowski:") {
+                Some(p) => p
+                    .parse::<f32>()
+                    .map_err(|_| format!("Invalid Minkowski order '{}'", p))
+                    .and_then(DistanceMetric::minkowski),
+                None => Err(format!(
+                    "Unknown distance metric '{}', expected euclidean, cosine, inner_product, manhattan, chebyshev or minkowski:<p>",
+                    s
+                )),
+            },
+        }
+    }
 }
 
 /// Document structure for linking external data with embeddings
@@ -44,22 +273,48 @@ pub struct Document<T> {
     pub data: T,
 }
 
-/// HNSW Node containing embedding vector and connections
-#[derive(Debug, Clone)]
+/// Operations shared by every index, so callers can swap an approximate index for an
+/// exact one
+///
+/// Implemented by `HNSWIndex` and `FlatIndex`, whose inherent methods of the same names
+/// take precedence when the concrete type is known.
+pub trait VectorIndex<T> {
+    fn insert(&mut self, id: String, embedding: Vec<f32>, document: Option<Document<T>>) -> Result<(), String>;
+
+    fn remove(&mut self, id: &str) -> Result<Option<Document<T>>, String>;
+
+    fn search(&self, query: &[f32], k: usize) -> Vec<(String, f32, Option<&Document<T>>)>;
+
+    /// Search for the k nearest neighbors accepted by `filter`
+    fn search_filtered(
+        &self,
+        query: &[f32],
+        k: usize,
+        filter: &(dyn Fn(&str, Option<&Document<T>>) -> bool + Sync),
+    ) -> Vec<(String, f32, Option<&Document<T>>)>;
+
+    fn contains(&self, id: &str) -> bool;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// HNSW Node holding an embedding's id and document
+///
+/// The index keeps vectors, codes and neighbor lists in contiguous arenas indexed by the
+/// node's position; see `HNSWIndex::get_embedding`, `HNSWIndex::get_codes` and
+/// `HNSWIndex::get_connections`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HNSWNode<T> {
     pub id: String,
-    pub embedding: Vec<f32>,
     pub document: Option<Document<T>>,
-    pub connections: Vec<Vec<usize>>, // connections for each layer
 }
 
 impl<T> HNSWNode<T> {
-    pub fn new(id: String, embedding: Vec<f32>, document: Option<Document<T>>) -> Self {
-        Self {
-            id,
-            embedding,
-            document,
-            connections: Vec::new(),
-        }
+    pub fn new(id: String, document: Option<Document<T>>) -> Self {
+        Self { id, document }
     }
 }
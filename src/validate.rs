@@ -0,0 +1,101 @@
+use std::collections::HashSet;
+use crate::index::HNSWIndex;
+
+impl<T, D> HNSWIndex<T, D> {
+    /// Check the structure of the index, returning a description of every problem found
+    ///
+    /// Looks for id map entries that disagree with the nodes, links to the node itself, to
+    /// missing nodes or to nodes absent from the layer, repeated links, nodes with no links
+    /// on layer 0, an entry point below the top layer, vectors of the wrong dimension, and
+    /// arena rows or blocks that are shared or lost. An empty list means the index is sound.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let len = self.nodes.len();
+
+        if self.node_id_to_index.len() != len {
+            problems.push(format!("Id map has {} entries for {} nodes", self.node_id_to_index.len(), len));
+        }
+        for (slot, node) in self.nodes.iter().enumerate() {
+            if self.node_id_to_index.get(&node.id) != Some(&slot) {
+                problems.push(format!("Id map does not point node '{}' at its slot {}", node.id, slot));
+            }
+        }
+        if self.graph.len() != len {
+            problems.push(format!("Graph has {} nodes, index has {}", self.graph.len(), len));
+            return problems;
+        }
+        let arenas = [
+            self.vectors.validate(len),
+            self.raw_vectors.validate(len),
+            self.norms.validate(len),
+            self.codes.validate(len),
+            self.half_vectors.validate(len),
+            self.disk_rows.validate(len),
+            self.graph.validate(len),
+        ];
+        problems.extend(arenas.into_iter().filter_map(Result::err));
+
+        for (slot, node) in self.nodes.iter().enumerate() {
+            if self.graph.layers(slot) == 0 {
+                problems.push(format!("Node '{}' is not on any layer", node.id));
+            }
+            for layer in 0..self.graph.layers(slot) {
+                let mut seen = HashSet::new();
+                for &neighbor in self.graph.neighbors(slot, layer) {
+                    let neighbor = neighbor as usize;
+                    if neighbor == slot {
+                        problems.push(format!("Node '{}' links to itself on layer {}", node.id, layer));
+                    } else if neighbor >= len {
+                        problems.push(format!("Node '{}' links to missing node {} on layer {}", node.id, neighbor, layer));
+                    } else if self.graph.layers(neighbor) <= layer {
+                        problems.push(format!("Node '{}' links to '{}', which is not on layer {}", node.id, self.nodes[neighbor].id, layer));
+                    }
+                    if !seen.insert(neighbor) {
+                        problems.push(format!("Node '{}' links to node {} twice on layer {}", node.id, neighbor, layer));
+                    }
+                }
+            }
+        }
+
+        // Search only reaches a node through its links, so in a graph of two or more nodes
+        // every node needs some on layer 0
+        if len > 1 {
+            let unlinked: Vec<usize> = (0..len).filter(|&slot| self.graph.neighbors(slot, 0).is_empty()).collect();
+            if let Some(&first) = unlinked.first() {
+                problems.push(format!(
+                    "{} of {} nodes have no links on layer 0, including '{}'",
+                    unlinked.len(), len, self.nodes[first].id
+                ));
+            }
+        }
+
+        let entry_point = match self.entry_point {
+            Some(ep) if ep >= len => {
+                problems.push(format!("Entry point {} is out of range", ep));
+                return problems;
+            }
+            Some(ep) => ep,
+            None if len > 0 => {
+                problems.push("Index has nodes but no entry point".to_string());
+                return problems;
+            }
+            None => return problems,
+        };
+        if self.graph.layers(entry_point) < self.graph.max_layers() {
+            problems.push(format!(
+                "Entry point '{}' is on {} layers, but the graph has {}",
+                self.nodes[entry_point].id, self.graph.layers(entry_point), self.graph.max_layers()
+            ));
+        }
+
+        let dimension = self.original_embedding_of(entry_point).len();
+        for (slot, node) in self.nodes.iter().enumerate() {
+            let vector_dimension = self.original_embedding_of(slot).len();
+            if vector_dimension != dimension {
+                problems.push(format!("Node '{}' has dimension {}, expected {}", node.id, vector_dimension, dimension));
+            }
+        }
+
+        problems
+    }
+}
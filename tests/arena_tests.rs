@@ -0,0 +1,137 @@
+use swarc::arena::{Graph, VectorArena};
+use swarc::index::HNSWIndex;
+use rand::Rng;
+
+#[test]
+fn test_vector_arena_rows() {
+    let mut arena = VectorArena::default();
+    arena.push(&[1.0, 2.0]).unwrap();
+    arena.push(&[]).unwrap();
+    arena.push(&[5.0, 6.0]).unwrap();
+
+    assert_eq!(arena.len(), 3);
+    assert_eq!(arena.rows(), 2);
+    assert_eq!(arena.dimension(), 2);
+    assert_eq!(arena.get(0), Some(&[1.0, 2.0][..]));
+    assert_eq!(arena.get(1), None);
+    assert!(arena.push(&[1.0]).is_err());
+    assert_eq!(arena.len(), 3);
+
+    // Dropping a row frees it for the next vector stored, leaving other rows in place
+    arena.set(0, &[]).unwrap();
+    assert_eq!(arena.rows(), 1);
+    assert_eq!(arena.get(2), Some(&[5.0, 6.0][..]));
+    let size = arena.heap_size();
+    arena.set(1, &[3.0, 4.0]).unwrap();
+    assert_eq!(arena.heap_size(), size);
+
+    // Removing a slot moves the last slot into its place
+    arena.swap_remove(0, 3);
+    assert_eq!(arena.len(), 2);
+    assert_eq!(arena.get(0), Some(&[5.0, 6.0][..]));
+    assert_eq!(arena.get(1), Some(&[3.0, 4.0][..]));
+
+    // Slots past the end of the table have no row until they are set
+    assert_eq!(arena.get(5), None);
+    arena.set(4, &[7.0, 8.0]).unwrap();
+    assert_eq!((arena.len(), arena.get(4)), (5, Some(&[7.0, 8.0][..])));
+    arena.swap_remove(1, 5);
+    assert_eq!(arena.get(1), Some(&[7.0, 8.0][..]));
+    arena.swap_remove(3, 4);
+    assert_eq!(arena.len(), 3);
+
+    arena.clear_rows();
+    assert_eq!((arena.len(), arena.rows()), (3, 0));
+    assert_eq!(arena.get(1), None);
+
+    // Clearing every slot also forgets the dimension
+    arena.clear();
+    assert_eq!((arena.len(), arena.dimension()), (0, 0));
+    arena.push(&[1.0, 2.0, 3.0]).unwrap();
+    assert_eq!(arena.dimension(), 3);
+}
+
+#[test]
+fn test_graph_blocks() {
+    let mut graph = Graph::new(2, 1);
+    graph.push(1);
+    graph.push(3);
+    graph.push(2);
+
+    assert_eq!(graph.max_layers(), 3);
+    assert!(graph.add_neighbor(0, 0, 1));
+    assert!(graph.add_neighbor(0, 0, 2));
+    assert!(!graph.add_neighbor(0, 0, 1), "layer 0 holds two neighbors");
+    graph.set_neighbors(1, 1, &[2]);
+    graph.set_neighbors(1, 2, &[0]);
+    assert!(!graph.add_neighbor(1, 1, 0), "upper layers hold one neighbor");
+    assert_eq!(graph.neighbors(0, 0), &[1, 2]);
+    assert!(graph.neighbors(0, 1).is_empty());
+
+    // Longer lists grow every block of that kind
+    graph.set_neighbors(2, 1, &[0, 1]);
+    assert_eq!(graph.capacity(1), 2);
+    assert_eq!(graph.connections(1), vec![vec![], vec![2], vec![0]]);
+
+    // Removing a node drops links to it and moves the last node into its slot
+    graph.swap_remove(0);
+    assert_eq!(graph.len(), 2);
+    assert_eq!(graph.connections(0), vec![vec![], vec![1]]);
+    assert_eq!(graph.connections(1), vec![vec![], vec![0], vec![]]);
+
+    // A node reusing the blocks of a removed one starts without neighbors
+    graph.swap_remove(1);
+    graph.push(3);
+    assert_eq!(graph.connections(0), vec![Vec::<usize>::new(); 2]);
+    assert_eq!(graph.connections(1), vec![Vec::<usize>::new(); 3]);
+}
+
+#[test]
+fn test_removed_slots_are_reused() {
+    let mut rng = rand::thread_rng();
+    let mut index: HNSWIndex<String> = HNSWIndex::new(8, 8, 50);
+    for i in 0..300 {
+        let vector: Vec<f32> = (0..8).map(|_| rng.gen_range(-1.0..1.0)).collect();
+        index.insert(format!("node_{}", i), vector, None).unwrap();
+    }
+
+    for i in (0..300).step_by(2) {
+        index.remove(&format!("node_{}", i)).unwrap();
+    }
+    assert!(index.validate().is_empty());
+    let vectors = index.memory_usage().vectors;
+
+    // New vectors take the rows freed by removals instead of growing the buffer
+    for i in 300..450 {
+        let vector: Vec<f32> = (0..8).map(|_| rng.gen_range(-1.0..1.0)).collect();
+        index.insert(format!("node_{}", i), vector, None).unwrap();
+    }
+    assert_eq!(index.memory_usage().vectors, vectors);
+    assert!(index.validate().is_empty());
+    for id in ["node_1", "node_299", "node_300", "node_449"] {
+        let query = index.get_embedding(id).unwrap().to_vec();
+        assert_eq!(index.search(&query, 1)[0].0, id);
+    }
+}
+
+#[test]
+fn test_insert_keeps_degree_within_capacity() {
+    let mut rng = rand::thread_rng();
+    let mut index: HNSWIndex<String> = HNSWIndex::new(8, 4, 50);
+    // With only 4 links on layer 0 pruning occasionally leaves a node unreachable
+    index.m_max = 8;
+    for i in 0..300 {
+        let embedding: Vec<f32> = (0..8).map(|_| rng.gen_range(-1.0..1.0)).collect();
+        index.insert(format!("node_{}", i), embedding, None).unwrap();
+    }
+
+    for id in index.get_all_ids() {
+        for (layer, neighbors) in index.get_connections(&id).unwrap().iter().enumerate() {
+            let limit = if layer == 0 { index.m_max } else { index.m };
+            assert!(neighbors.len() <= limit, "'{}' has {} neighbors on layer {}", id, neighbors.len(), layer);
+        }
+    }
+
+    let query = index.get_embedding("node_42").unwrap().to_vec();
+    assert_eq!(index.search(&query, 1)[0].0, "node_42");
+}
@@ -0,0 +1,99 @@
+use swarc::bq::BinaryQuantizer;
+use swarc::index::HNSWIndex;
+use swarc::types::DistanceMetric;
+
+mod common;
+
+use common::{random_vectors, build_index};
+
+#[test]
+fn test_encode_decode_sign_bits() {
+    let quantizer = BinaryQuantizer::new(10);
+    assert_eq!(quantizer.code_size(), 2);
+
+    let vector = [0.5, -1.0, 0.0, 2.0, -0.1, 0.1, 0.1, -3.0, 1.0, -1.0];
+    let codes = quantizer.encode(&vector).unwrap();
+    // Zero counts as negative
+    assert_eq!(codes, vec![0b0110_1001, 0b01]);
+
+    let decoded = quantizer.decode(&codes);
+    let magnitude = 1.0 / 10f32.sqrt();
+    for (value, decoded) in vector.iter().zip(&decoded) {
+        let expected = if *value > 0.0 { magnitude } else { -magnitude };
+        assert!((decoded - expected).abs() < 1e-6);
+    }
+
+    assert!(quantizer.encode(&[1.0; 9]).is_err());
+}
+
+#[test]
+fn test_hamming_distance() {
+    let quantizer = BinaryQuantizer::new(20);
+    let a = quantizer.encode(&[1.0; 20]).unwrap();
+    let mut flipped = [1.0; 20];
+    for i in [0, 7, 8, 19] {
+        flipped[i] = -1.0;
+    }
+    let b = quantizer.encode(&flipped).unwrap();
+
+    assert_eq!(quantizer.hamming(&a, &a), 0);
+    assert_eq!(quantizer.hamming(&a, &b), 4);
+}
+
+#[test]
+fn test_binary_search_rescores_candidates() {
+    let vectors = random_vectors(1000, 64, 1);
+    let mut index = build_index(&vectors, DistanceMetric::Cosine);
+    index.quantize_binary(true).unwrap();
+    assert_eq!(index.get_codes("node_0").unwrap().len(), 8);
+
+    // Without rerank_candidates the top k are still rescored at full precision
+    let results = index.search(&vectors[3], 3);
+    assert_eq!(results[0].0, "node_3");
+    assert!(results[0].1.abs() < 1e-5);
+    for (id, dist, _) in &results {
+        let original = index.get_original_embedding(id).unwrap();
+        assert!((dist - index.distance(&vectors[3], &original)).abs() < 1e-5);
+    }
+
+    index.rerank_candidates = Some(200);
+    let mut hits = 0;
+    let queries = random_vectors(20, 64, 2);
+    for query in &queries {
+        let mut exact: Vec<(usize, f32)> = vectors.iter().enumerate().map(|(i, v)| (i, index.distance(query, v))).collect();
+        exact.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+        let expected: Vec<String> = exact.iter().take(10).map(|(i, _)| format!("node_{}", i)).collect();
+        hits += index.search(query, 10).iter().filter(|(id, _, _)| expected.contains(id)).count();
+    }
+    let recall = hits as f32 / 200.0;
+    assert!(recall >= 0.8, "recall@10 was {}", recall);
+}
+
+#[test]
+fn test_binary_quantized_only_returns_hamming_distances() {
+    let vectors = random_vectors(100, 32, 3);
+    let mut index = build_index(&vectors, DistanceMetric::Euclidean);
+    index.quantize_binary(false).unwrap();
+
+    let results = index.search(&vectors[0], 5);
+    assert_eq!(results[0].1, 0.0);
+    for (_, dist, _) in &results {
+        assert_eq!(dist.fract(), 0.0);
+        assert!(*dist <= 32.0);
+    }
+}
+
+#[test]
+fn test_binary_quantizer_is_persisted() {
+    let path = std::env::temp_dir().join(format!("swarc_bq_{}.json", std::process::id()));
+    let vectors = random_vectors(50, 16, 4);
+    let mut index = build_index(&vectors, DistanceMetric::Euclidean);
+    index.quantize_binary(true).unwrap();
+
+    index.save(&path).unwrap();
+    let loaded: HNSWIndex<String> = HNSWIndex::load(&path).unwrap();
+    assert_eq!(loaded.binary_quantizer(), Some(&BinaryQuantizer::new(16)));
+    assert_eq!(loaded.search(&vectors[4], 1)[0].0, "node_4");
+
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,197 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use serde_json::Value;
+use swarc::fvecs::read_fvecs;
+use swarc::npy::{write_npy, NpyMatrix};
+
+/// A fresh working directory for one test
+fn work_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("swarc_cli_{}_{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Run the `swarc` binary in `dir`, feeding it `stdin`
+fn swarc(dir: &Path, args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_swarc"))
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+/// Run `swarc`, expecting it to succeed, and return its standard output
+fn run_ok(dir: &Path, args: &[&str], stdin: &str) -> String {
+    let output = swarc(dir, args, stdin);
+    assert!(output.status.success(), "{:?} failed: {}", args, String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Run `swarc`, expecting it to fail, and return its standard error
+fn run_err(dir: &Path, args: &[&str], stdin: &str) -> String {
+    let output = swarc(dir, args, stdin);
+    assert!(!output.status.success(), "{:?} should have failed", args);
+    String::from_utf8(output.stderr).unwrap()
+}
+
+fn node_count(dir: &Path, index: &str) -> u64 {
+    let stats: Value = serde_json::from_str(&run_ok(dir, &["stats", index, "--json"], "")).unwrap();
+    stats["nodes"].as_u64().unwrap()
+}
+
+fn write_records(dir: &Path, name: &str, count: usize) {
+    let lines: Vec<String> = (0..count)
+        .map(|i| serde_json::json!({ "id": format!("node_{}", i), "vector": [i as f32, 1.0], "document": { "n": i } }).to_string())
+        .collect();
+    std::fs::write(dir.join(name), lines.join("\n")).unwrap();
+}
+
+#[test]
+fn test_cli_prints_usage() {
+    let dir = work_dir("usage");
+    assert!(run_ok(&dir, &[], "").starts_with("Usage: swarc"));
+    assert!(run_ok(&dir, &["query", "--help"], "").starts_with("Usage: swarc"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_cli_build_query_and_stats() {
+    let dir = work_dir("build");
+    write_records(&dir, "records.jsonl", 20);
+
+    let out = run_ok(&dir, &["build", "records.jsonl", "-o", "index.json", "--m", "4", "--metric", "l1"], "");
+    assert!(out.contains("Inserted 20 records, skipped 0 lines"));
+
+    let stats: Value = serde_json::from_str(&run_ok(&dir, &["stats", "index.json", "--json"], "")).unwrap();
+    assert_eq!(stats["nodes"], 20);
+    assert_eq!(stats["dimension"], 2);
+    assert_eq!(stats["m"], 4);
+    assert_eq!(stats["metric"], "Manhattan");
+
+    // Queries are read from standard input by default, as JSON arrays or separated numbers
+    let out = run_ok(&dir, &["query", "index.json", "-k", "2", "--json"], "[3.0, 1.0]\n\n12 1\n");
+    let results: Vec<Value> = out.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].as_array().unwrap().len(), 2);
+    assert_eq!(results[0][0]["id"], "node_3");
+    assert_eq!(results[0][0]["document"]["n"], 3);
+    assert_eq!(results[1][0]["id"], "node_12");
+
+    std::fs::write(dir.join("queries.txt"), "5,1\n").unwrap();
+    let out = run_ok(&dir, &["query", "index.json", "--vector", "queries.txt", "--k=1", "--ef-search", "50"], "");
+    assert!(out.contains("node_5"));
+
+    // A .jsonl file of records, such as an export, can hold the queries
+    write_records(&dir, "queries.jsonl", 8);
+    let out = run_ok(&dir, &["query", "index.json", "--vector", "queries.jsonl", "-k", "1", "--json"], "");
+    let results: Vec<Value> = out.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    assert_eq!(results.len(), 8);
+    assert_eq!(results[7][0]["id"], "node_7");
+
+    let out = run_ok(&dir, &["query", "index.json", "-k", "1"], "1 2 3\n");
+    assert!(out.contains("no results"));
+
+    let out = run_ok(&dir, &["validate", "index.json"], "");
+    assert!(out.contains("is valid: 20 nodes"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_cli_npy_round_trip_with_ids() {
+    let dir = work_dir("npy");
+    let matrix = NpyMatrix::new(3, 2, vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0]).unwrap();
+    write_npy(dir.join("vectors.npy"), &matrix).unwrap();
+    std::fs::write(dir.join("ids.txt"), "origin\nx\ny\n").unwrap();
+
+    run_ok(&dir, &["build", "vectors.npy", "--ids", "ids.txt", "-o", "index.json"], "");
+    let out = run_ok(&dir, &["query", "index.json", "-k", "1", "--json"], "0.9 0.1\n");
+    assert!(out.contains("\"x\""));
+
+    // Binary exports write ids alongside when asked
+    run_ok(&dir, &["export", "index.json", "-o", "out.fvecs", "--ids", "out_ids.txt"], "");
+    let exported = read_fvecs(dir.join("out.fvecs")).unwrap();
+    let ids = std::fs::read_to_string(dir.join("out_ids.txt")).unwrap();
+    let ids: Vec<&str> = ids.lines().collect();
+    assert_eq!(ids, ["origin", "x", "y"]);
+    assert_eq!(exported.data, matrix.data);
+
+    // JSON lines can be exported to standard output
+    let out = run_ok(&dir, &["export", "index.json", "-o", "-"], "");
+    let records: Vec<Value> = out.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[2]["id"], "y");
+    assert_eq!(records[2]["vector"], serde_json::json!([0.0, 1.0]));
+
+    // Rows without --ids are numbered
+    run_ok(&dir, &["build", "vectors.npy", "-o", "numbered.json"], "");
+    let out = run_ok(&dir, &["query", "numbered.json", "-k", "1", "--json"], "0 1\n");
+    assert!(out.contains("\"2\""));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_cli_add_and_remove() {
+    let dir = work_dir("add");
+    write_records(&dir, "first.jsonl", 5);
+    run_ok(&dir, &["build", "first.jsonl", "-o", "index.json"], "");
+
+    // Records already in the index are reported and skipped
+    let more = "{\"id\": \"extra\", \"vector\": [9, 9]}\n{\"id\": \"node_1\", \"vector\": [1, 1]}\n";
+    let output = swarc(&dir, &["add", "index.json", "-", "-o", "grown.json"], more);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Inserted 1 records, skipped 1 lines"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("-:2:"));
+    assert_eq!(node_count(&dir, "index.json"), 5);
+    assert_eq!(node_count(&dir, "grown.json"), 6);
+
+    run_ok(&dir, &["remove", "grown.json", "extra", "node_0"], "");
+    assert_eq!(node_count(&dir, "grown.json"), 4);
+
+    // Nothing is removed unless every id exists
+    let err = run_err(&dir, &["remove", "grown.json", "node_2", "missing"], "");
+    assert!(err.contains("'missing' not found"));
+    assert_eq!(node_count(&dir, "grown.json"), 4);
+    run_ok(&dir, &["validate", "grown.json"], "");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_cli_rejects_bad_arguments() {
+    let dir = work_dir("errors");
+    write_records(&dir, "records.jsonl", 3);
+    run_ok(&dir, &["build", "records.jsonl", "-o", "index.json"], "");
+
+    assert!(run_err(&dir, &["frobnicate"], "").contains("Unknown command 'frobnicate'"));
+    assert!(run_err(&dir, &["stats", "index.json", "--verbose", "1"], "").contains("Unknown option '--verbose'"));
+    assert!(run_err(&dir, &["stats", "index.json", "extra"], "").contains("Unexpected argument 'extra'"));
+    assert!(run_err(&dir, &["stats", "-x", "index.json"], "").contains("Unknown option '-x'"));
+    assert!(run_err(&dir, &["query", "index.json", "-k", "-1"], "").contains("Invalid value '-1' for --k"));
+    assert!(run_err(&dir, &["query", "index.json", "-k"], "").contains("Missing value for --k"));
+    assert!(run_err(&dir, &["query", "index.json", "-k", "many"], "").contains("Invalid value 'many' for --k"));
+    assert!(run_err(&dir, &["query", "index.json"], "1 2\n1 x\n").contains("Query on line 2: invalid number 'x'"));
+    assert!(run_err(&dir, &["query", "index.json"], "").contains("No query vectors given"));
+    assert!(run_err(&dir, &["build", "records.jsonl"], "").contains("Missing --output"));
+    assert!(run_err(&dir, &["build", "records.jsonl", "-o", "x.json", "--m", "0"], "").contains("greater than zero"));
+    assert!(run_err(&dir, &["build", "records.jsonl", "-o", "x.json", "--metric", "minkowski:0"], "").contains("Minkowski order"));
+    assert!(run_err(&dir, &["build", "records.jsonl", "-o", "x.json", "--ids", "ids.txt"], "").contains("--ids only applies"));
+    assert!(run_err(&dir, &["export", "index.json", "-o", "vectors.bin"], "").contains("Cannot tell the format of 'vectors.bin'"));
+    assert!(run_err(&dir, &["remove", "index.json"], "").contains("Missing ids to remove"));
+
+    // Binary formats cannot go through standard input or output
+    for format in ["npy", "fvecs"] {
+        let err = run_err(&dir, &["export", "index.json", "-o", "-", "--format", format], "");
+        assert!(err.contains("only carry JSON lines"), "{}", err);
+        assert!(run_err(&dir, &["add", "index.json", "-", "--format", format], "").contains("only carry JSON lines"));
+        assert!(run_err(&dir, &["query", "index.json", "--format", format], "").contains("only carry JSON lines"));
+    }
+    assert!(!dir.join("-").exists());
+    assert_eq!(node_count(&dir, "index.json"), 3);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,38 @@
+// Helpers shared by the integration tests; each test binary uses only some
+#![allow(dead_code)]
+
+use swarc::index::HNSWIndex;
+use swarc::types::DistanceMetric;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Vectors with components in [-1, 1), the same for the same seed so recall checks
+/// do not depend on the run
+pub fn random_vectors(count: usize, dim: usize, seed: u64) -> Vec<Vec<f32>> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..count).map(|_| (0..dim).map(|_| rng.gen_range(-1.0..1.0)).collect()).collect()
+}
+
+/// Index the vectors in parallel as `node_0`, `node_1`, ...
+pub fn build_index(vectors: &[Vec<f32>], metric: DistanceMetric) -> HNSWIndex<String> {
+    let mut index = HNSWIndex::new_with_distance(vectors[0].len(), 16, 100, metric);
+    let items = vectors
+        .iter()
+        .enumerate()
+        .map(|(i, v)| (format!("node_{}", i), v.clone(), None))
+        .collect();
+    index.insert_parallel(items).unwrap();
+    index
+}
+
+/// Fraction of each query's exact 10 nearest vectors that the index returns
+pub fn recall_at_10(index: &HNSWIndex<String>, vectors: &[Vec<f32>], queries: &[Vec<f32>]) -> f32 {
+    let mut hits = 0;
+    for query in queries {
+        let mut exact: Vec<(usize, f32)> = vectors.iter().enumerate().map(|(i, v)| (i, index.distance(query, v))).collect();
+        exact.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+        let expected: Vec<String> = exact.iter().take(10).map(|(i, _)| format!("node_{}", i)).collect();
+        hits += index.search(query, 10).iter().filter(|(id, _, _)| expected.contains(id)).count();
+    }
+    hits as f32 / (queries.len() * 10) as f32
+}
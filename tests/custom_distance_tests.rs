@@ -0,0 +1,91 @@
+use swarc::index::HNSWIndex;
+use swarc::types::{Distance, DistanceMetric, Document};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Mahalanobis distance `sqrt((a - b)ᵀ M (a - b))` for a learned positive definite matrix M
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Mahalanobis {
+    dim: usize,
+    matrix: Vec<f32>, // row-major dim x dim
+}
+
+impl Distance for Mahalanobis {
+    fn distance(&self, a: &[f32], b: &[f32]) -> f32 {
+        let diff: Vec<f32> = a.iter().zip(b.iter()).map(|(x, y)| x - y).collect();
+        let mut total = 0.0;
+        for i in 0..self.dim {
+            for j in 0..self.dim {
+                total += diff[i] * self.matrix[i * self.dim + j] * diff[j];
+            }
+        }
+        total.max(0.0).sqrt()
+    }
+}
+
+/// Ignores every dimension except the first
+struct FirstCoordinate;
+
+impl Distance for FirstCoordinate {
+    fn distance(&self, a: &[f32], b: &[f32]) -> f32 {
+        (a[0] - b[0]).abs()
+    }
+}
+
+#[test]
+fn test_index_with_custom_distance() {
+    // Stretch the second dimension so differences along it count ten times as much
+    let metric = Mahalanobis { dim: 2, matrix: vec![1.0, 0.0, 0.0, 100.0] };
+    let mut index = HNSWIndex::new_with_distance(2, 16, 200, metric);
+
+    index.insert("along_x".to_string(), vec![3.0, 0.0], Some(Document { id: "x".to_string(), data: 1 })).unwrap();
+    index.insert("along_y".to_string(), vec![0.0, 1.0], Some(Document { id: "y".to_string(), data: 2 })).unwrap();
+
+    assert!((index.distance(&[0.0, 0.0], &[0.0, 1.0]) - 10.0).abs() < 1e-6);
+
+    // Plain Euclidean distance would pick along_y
+    let results = index.search(&[0.0, 0.0], 2);
+    assert_eq!(results[0].0, "along_x");
+    assert!((results[0].1 - 3.0).abs() < 1e-6);
+    assert_eq!(results[1].0, "along_y");
+}
+
+#[test]
+fn test_index_with_boxed_distance() {
+    let metrics: Vec<Box<dyn Distance + Send + Sync>> = vec![
+        Box::new(DistanceMetric::Euclidean),
+        Box::new(FirstCoordinate),
+    ];
+    let expected = ["diagonal", "far_in_y"];
+
+    for (metric, expected) in metrics.into_iter().zip(expected) {
+        let mut index: HNSWIndex<String, Box<dyn Distance + Send + Sync>> = HNSWIndex::new_with_distance(2, 16, 200, metric);
+        let items = vec![
+            ("diagonal".to_string(), vec![1.0, 1.0], None),
+            ("far_in_y".to_string(), vec![0.0, 50.0], None),
+        ];
+        index.insert_parallel(items).unwrap();
+
+        let results = index.search(&[0.0, 0.0], 1);
+        assert_eq!(results[0].0, expected);
+
+        index.remove(expected).unwrap();
+        assert_eq!(index.len(), 1);
+    }
+}
+
+#[test]
+fn test_custom_distance_is_persisted() {
+    let path: PathBuf = std::env::temp_dir().join(format!("swarc_custom_distance_{}.json", std::process::id()));
+    let metric = Mahalanobis { dim: 2, matrix: vec![2.0, 0.5, 0.5, 1.0] };
+    let mut index: HNSWIndex<String, Mahalanobis> = HNSWIndex::new_with_distance(2, 16, 200, metric.clone());
+    index.insert("a".to_string(), vec![1.0, 2.0], None).unwrap();
+
+    index.save(&path).unwrap();
+    let loaded: HNSWIndex<String, Mahalanobis> = HNSWIndex::load(&path).unwrap();
+
+    assert_eq!(loaded.distance_metric, metric);
+    assert_eq!(loaded.search(&[1.0, 2.0], 1)[0].0, "a");
+
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,153 @@
+use swarc::flat::FlatIndex;
+use swarc::index::HNSWIndex;
+use swarc::types::{Document, DistanceMetric, VectorIndex};
+use rand::Rng;
+
+fn random_vectors(count: usize, dim: usize) -> Vec<Vec<f32>> {
+    let mut rng = rand::thread_rng();
+    (0..count).map(|_| (0..dim).map(|_| rng.gen_range(-1.0..1.0)).collect()).collect()
+}
+
+fn fill(index: &mut dyn VectorIndex<usize>, vectors: &[Vec<f32>]) {
+    for (i, vector) in vectors.iter().enumerate() {
+        let doc = Document { id: format!("doc_{}", i), data: i };
+        index.insert(format!("node_{}", i), vector.clone(), Some(doc)).unwrap();
+    }
+}
+
+#[test]
+fn test_flat_search_is_exact() {
+    let vectors = random_vectors(500, 16);
+    let mut index = FlatIndex::new(16);
+    fill(&mut index, &vectors);
+
+    let query = &vectors[0];
+    let mut expected: Vec<(usize, f32)> = vectors.iter().enumerate().map(|(i, v)| (i, index.distance(query, v))).collect();
+    expected.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+
+    let results = index.search(query, 10);
+    assert_eq!(results.len(), 10);
+    for ((id, dist, doc), (i, expected_dist)) in results.iter().zip(&expected) {
+        assert_eq!(id, &format!("node_{}", i));
+        assert!((dist - expected_dist).abs() < 1e-5);
+        assert_eq!(doc.unwrap().data, *i);
+    }
+}
+
+#[test]
+fn test_flat_insert_and_remove() {
+    let mut index: FlatIndex<String> = FlatIndex::new_with_distance(2, DistanceMetric::Cosine);
+    index.insert("a".to_string(), vec![1.0, 0.0], None).unwrap();
+    index.insert("b".to_string(), vec![0.0, 3.0], None).unwrap();
+    index.insert("c".to_string(), vec![1.0, 1.0], None).unwrap();
+
+    assert!(index.insert("a".to_string(), vec![1.0, 0.0], None).is_err());
+    assert!(index.insert("d".to_string(), vec![1.0, 0.0, 0.0], None).is_err());
+    assert_eq!(index.get_embedding("b"), Some(&[0.0, 1.0][..]));
+
+    index.remove("a").unwrap();
+    assert!(index.remove("a").is_err());
+    assert_eq!(index.len(), 2);
+    assert_eq!(index.search(&[0.0, 2.0], 1)[0].0, "b");
+    assert_eq!(index.search(&[2.0, 0.0], 1)[0].0, "c");
+
+    assert!(index.remove_multiple(&["b", "missing"]).is_err());
+    assert_eq!(index.len(), 2);
+    index.clear();
+    assert!(index.is_empty() && index.search(&[1.0, 0.0], 1).is_empty());
+}
+
+#[test]
+fn test_flat_insert_parallel_rejects_bad_batches() {
+    let mut index: FlatIndex<String> = FlatIndex::new(2);
+    let batch = vec![
+        ("a".to_string(), vec![1.0, 0.0], None),
+        ("a".to_string(), vec![0.0, 1.0], None),
+    ];
+    assert!(index.insert_parallel(batch).is_err());
+
+    let batch = vec![
+        ("a".to_string(), vec![1.0, 0.0], None),
+        ("b".to_string(), vec![0.0, 1.0, 2.0], None),
+    ];
+    assert!(index.insert_parallel(batch).is_err());
+    assert!(index.is_empty());
+
+    let batch = vec![
+        ("a".to_string(), vec![1.0, 0.0], None),
+        ("b".to_string(), vec![0.0, 1.0], None),
+    ];
+    assert!(index.insert_parallel(batch).unwrap().iter().all(|r| r.is_ok()));
+    assert_eq!(index.search(&[0.0, 1.0], 1)[0].0, "b");
+}
+
+#[test]
+fn test_filtered_search_matches_across_indexes() {
+    let vectors = random_vectors(300, 8);
+    let mut flat = FlatIndex::new(8);
+    let mut hnsw = HNSWIndex::new(8, 16, 200);
+    fill(&mut flat, &vectors);
+    fill(&mut hnsw, &vectors);
+
+    let indexes: [&dyn VectorIndex<usize>; 2] = [&flat, &hnsw];
+    let odd = |_: &str, doc: Option<&Document<usize>>| doc.is_some_and(|doc| doc.data % 2 == 1);
+    for index in indexes {
+        let results = index.search_filtered(&vectors[0], 5, &odd);
+        assert_eq!(results.len(), 5);
+        assert!(results.iter().all(|(_, _, doc)| doc.unwrap().data % 2 == 1));
+        assert!(results.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+    }
+
+    // The flat index is the ground truth for the HNSW results
+    let exact: Vec<String> = flat.search_filtered(&vectors[0], 5, odd).into_iter().map(|(id, _, _)| id).collect();
+    let approximate: Vec<String> = hnsw.search_filtered(&vectors[0], 5, odd).into_iter().map(|(id, _, _)| id).collect();
+    assert_eq!(exact, approximate);
+}
+
+#[test]
+fn test_indexes_agree_on_empty_searches() {
+    let vectors = random_vectors(50, 4);
+    let mut flat = FlatIndex::new(4);
+    let mut hnsw = HNSWIndex::new(4, 16, 200);
+    fill(&mut flat, &vectors);
+    fill(&mut hnsw, &vectors);
+
+    let indexes: [&dyn VectorIndex<usize>; 2] = [&flat, &hnsw];
+    for index in indexes {
+        // No results are asked for, even with a filter that rejects the entry point
+        assert!(index.search(&vectors[0], 0).is_empty());
+        assert!(index.search_filtered(&vectors[0], 0, &|_, _| false).is_empty());
+        assert!(index.search_filtered(&vectors[0], 3, &|_, _| false).is_empty());
+
+        // Queries of the wrong dimension find nothing
+        assert!(index.search(&[0.5, 0.5], 3).is_empty());
+        assert!(index.search(&[0.5; 6], 3).is_empty());
+
+        // A NaN query gives NaN distances, which are ordered without panicking
+        assert!(index.search(&[f32::NAN; 4], 3).iter().all(|(_, dist, _)| dist.is_nan()));
+    }
+}
+
+#[test]
+fn test_cosine_zero_vector_ranks_the_same_everywhere() {
+    let vectors = vec![vec![0.0, 0.0, 0.0], vec![1.0, 0.0, 0.0], vec![-1.0, 0.0, 0.0], vec![0.0, 1.0, 0.0]];
+    let mut flat = FlatIndex::new_with_distance(3, DistanceMetric::Cosine);
+    let mut hnsw = HNSWIndex::new_with_distance(3, 16, 200, DistanceMetric::Cosine);
+    fill(&mut flat, &vectors);
+    fill(&mut hnsw, &vectors);
+
+    // A zero vector is as far as an orthogonal one, whether compared raw or as stored
+    let query = [1.0, 0.0, 0.0];
+    assert_eq!(hnsw.distance(&query, &vectors[0]), 1.0);
+    assert_eq!(hnsw.distance(&vectors[0], &vectors[0]), 1.0);
+    let indexes: [&dyn VectorIndex<usize>; 2] = [&flat, &hnsw];
+    for index in indexes {
+        let results: Vec<(String, f32)> = index.search(&query, 4).into_iter().map(|(id, dist, _)| (id, dist)).collect();
+        assert_eq!(results, vec![
+            ("node_1".to_string(), 0.0),
+            ("node_0".to_string(), 1.0),
+            ("node_3".to_string(), 1.0),
+            ("node_2".to_string(), 2.0),
+        ]);
+    }
+}
@@ -0,0 +1,46 @@
+use swarc::fvecs::{read_fvecs, write_fvecs};
+use swarc::npy::NpyMatrix;
+use std::path::PathBuf;
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("swarc_fvecs_{}_{}.fvecs", std::process::id(), name))
+}
+
+#[test]
+fn test_write_and_read_roundtrip() {
+    let path = temp_path("roundtrip");
+    let matrix = NpyMatrix::new(2, 3, vec![1.0, 2.0, 3.0, -4.0, 5.5, 0.0]).unwrap();
+
+    write_fvecs(&path, &matrix).unwrap();
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), 2 * (4 + 3 * 4));
+    assert_eq!(read_fvecs(&path).unwrap(), matrix);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_read_rejects_malformed_files() {
+    let path = temp_path("malformed");
+    let vector = |dimension: i32, values: &[f32]| -> Vec<u8> {
+        let mut bytes = dimension.to_le_bytes().to_vec();
+        bytes.extend(values.iter().flat_map(|v| v.to_le_bytes()));
+        bytes
+    };
+
+    // A second vector claiming a different dimension, in a file of the right length
+    let mut bytes = vector(2, &[1.0, 2.0]);
+    bytes.extend(vector(1, &[3.0, 4.0]));
+    std::fs::write(&path, &bytes).unwrap();
+    assert!(read_fvecs(&path).is_err());
+
+    // A truncated last vector
+    let mut bytes = vector(2, &[1.0, 2.0]);
+    bytes.extend(&vector(2, &[3.0, 4.0])[..8]);
+    std::fs::write(&path, &bytes).unwrap();
+    assert!(read_fvecs(&path).is_err());
+
+    std::fs::write(&path, vector(0, &[])).unwrap();
+    assert!(read_fvecs(&path).is_err());
+
+    std::fs::remove_file(&path).unwrap();
+}
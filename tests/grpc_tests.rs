@@ -0,0 +1,152 @@
+#![cfg(feature = "grpc")]
+
+// The server modules are compiled into this test so the service can be called directly
+#[allow(dead_code)]
+#[path = "../server/grpc.rs"]
+mod grpc;
+#[allow(dead_code)]
+#[path = "../server/routes.rs"]
+mod routes;
+#[allow(dead_code)]
+#[path = "../server/state.rs"]
+mod state;
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio_stream::StreamExt;
+use tonic::{Code, Request};
+use grpc::proto::index_server::Index as _;
+use grpc::proto::{DeleteRequest, Record, SearchBatchRequest, SearchRequest, StatsRequest, UpsertRequest};
+use grpc::Service;
+use state::{Index, State};
+
+/// How often open streams check for shutdown, as in the server binary
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+fn new_service() -> (Service, Arc<AtomicBool>) {
+    let state = State { index: RwLock::new(Index::new(0, 8, 50)), path: PathBuf::from("unused.json") };
+    let shutdown = Arc::new(AtomicBool::new(false));
+    (Service::new(Arc::new(state), Arc::clone(&shutdown)), shutdown)
+}
+
+fn record(id: &str, vector: Vec<f32>, document: &str) -> Record {
+    Record { id: id.to_string(), vector, document: document.to_string() }
+}
+
+/// Upsert twelve points along a line, ids `p0` to `p11`
+async fn fill(service: &Service) {
+    let records = (0..12).map(|i| record(&format!("p{}", i), vec![i as f32, 0.0], "")).collect();
+    let response = service.upsert(Request::new(UpsertRequest { records })).await.unwrap().into_inner();
+    assert_eq!(response.inserted, 12);
+}
+
+fn runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Runtime::new().unwrap()
+}
+
+#[test]
+fn test_grpc_upsert_reports_bad_documents() {
+    runtime().block_on(async {
+        let (service, _) = new_service();
+        let records = vec![
+            record("a", vec![0.0, 0.0], r#"{"title": "A"}"#),
+            record("b", vec![1.0, 0.0], "{not json"),
+            record("c", vec![2.0, 0.0], ""),
+            record("d", vec![1.0, 2.0, 3.0], ""),
+        ];
+        let response = service.upsert(Request::new(UpsertRequest { records })).await.unwrap().into_inner();
+        assert_eq!((response.inserted, response.replaced), (2, 0));
+        let failed: Vec<&str> = response.errors.iter().map(|error| error.id.as_str()).collect();
+        assert_eq!(failed, ["b", "d"]);
+        assert!(response.errors[0].error.starts_with("Invalid document"));
+
+        // Documents come back as JSON text, and no document as an empty string
+        let request = SearchRequest { vector: vec![0.0, 0.0], k: 2 };
+        let response = service.search(Request::new(request)).await.unwrap().into_inner();
+        assert_eq!(response.neighbors[0].id, "a");
+        assert_eq!(response.neighbors[0].document, r#"{"title":"A"}"#);
+        assert_eq!(response.neighbors[1].id, "c");
+        assert_eq!(response.neighbors[1].document, "");
+
+        let records = vec![record("a", vec![5.0, 5.0], "")];
+        let response = service.upsert(Request::new(UpsertRequest { records })).await.unwrap().into_inner();
+        assert_eq!((response.inserted, response.replaced), (1, 1));
+
+        let request = DeleteRequest { ids: vec!["a".to_string(), "missing".to_string()] };
+        let response = service.delete(Request::new(request)).await.unwrap().into_inner();
+        assert_eq!(response.removed, 1);
+        assert_eq!(response.missing, ["missing"]);
+    });
+}
+
+#[test]
+fn test_grpc_search_defaults_k() {
+    runtime().block_on(async {
+        let (service, _) = new_service();
+        fill(&service).await;
+
+        let response = service.search(Request::new(SearchRequest { vector: vec![0.0, 0.0], k: 0 })).await.unwrap().into_inner();
+        assert_eq!(response.neighbors.len(), routes::DEFAULT_K);
+        let response = service.search(Request::new(SearchRequest { vector: vec![0.0, 0.0], k: 3 })).await.unwrap().into_inner();
+        let ids: Vec<&str> = response.neighbors.iter().map(|neighbor| neighbor.id.as_str()).collect();
+        assert_eq!(ids, ["p0", "p1", "p2"]);
+
+        let status = service.search(Request::new(SearchRequest { vector: vec![0.0], k: 3 })).await.unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+    });
+}
+
+#[test]
+fn test_grpc_search_batch_splits_queries() {
+    runtime().block_on(async {
+        let (service, _) = new_service();
+        fill(&service).await;
+
+        let request = SearchBatchRequest { vectors: vec![3.0, 0.0, 10.0, 0.0, 7.0, 0.0], dimension: 2, k: 2 };
+        let response = service.search_batch(Request::new(request)).await.unwrap().into_inner();
+        let nearest: Vec<&str> = response.results.iter().map(|result| result.neighbors[0].id.as_str()).collect();
+        assert_eq!(nearest, ["p3", "p10", "p7"]);
+        assert!(response.results.iter().all(|result| result.neighbors.len() == 2));
+
+        let request = SearchBatchRequest { vectors: vec![1.0, 0.0], dimension: 2, k: 0 };
+        let response = service.search_batch(Request::new(request)).await.unwrap().into_inner();
+        assert_eq!(response.results[0].neighbors.len(), routes::DEFAULT_K);
+
+        // Values that do not split into whole queries of the index's dimension are refused
+        for (vectors, dimension) in [(vec![1.0, 0.0], 0), (vec![1.0, 0.0, 2.0], 2), (vec![1.0, 0.0, 2.0], 3)] {
+            let request = SearchBatchRequest { vectors, dimension, k: 1 };
+            let status = service.search_batch(Request::new(request)).await.unwrap_err();
+            assert_eq!(status.code(), Code::InvalidArgument, "{}", status.message());
+        }
+    });
+}
+
+#[test]
+fn test_grpc_stats_stream() {
+    runtime().block_on(async {
+        let (service, shutdown) = new_service();
+        fill(&service).await;
+
+        // An interval of zero sends one report and ends the stream
+        let mut stream = service.stats(Request::new(StatsRequest { interval_ms: 0 })).await.unwrap().into_inner();
+        let stats = stream.next().await.unwrap().unwrap();
+        assert_eq!((stats.nodes, stats.dimension, stats.m), (12, 2, 8));
+        assert_eq!(stats.metric, "Euclidean");
+        assert_eq!(stats.nodes_per_layer[0], 12);
+        assert!(stats.memory_bytes > 0);
+        assert!(stream.next().await.is_none());
+
+        // Otherwise reports repeat until shutdown
+        let mut stream = service.stats(Request::new(StatsRequest { interval_ms: 5 })).await.unwrap().into_inner();
+        for _ in 0..3 {
+            assert_eq!(stream.next().await.unwrap().unwrap().nodes, 12);
+        }
+        shutdown.store(true, Ordering::SeqCst);
+        let ended = tokio::time::timeout(Duration::from_secs(5), async {
+            while stream.next().await.is_some() {}
+        });
+        assert!(ended.await.is_ok());
+    });
+}
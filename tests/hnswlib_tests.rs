@@ -0,0 +1,189 @@
+use swarc::index::HNSWIndex;
+use swarc::types::{Document, DistanceMetric};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use rand::Rng;
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("swarc_hnswlib_{}_{}.bin", std::process::id(), name))
+}
+
+fn push_usize(out: &mut Vec<u8>, value: usize) {
+    out.extend_from_slice(&(value as u64).to_le_bytes());
+}
+
+/// Lay out a two-dimensional, three-element index exactly as hnswlib's saveIndex does,
+/// with maxM = maxM0 = 2, element 2 on level 1 and element `deleted` marked as deleted
+fn handcrafted_hnswlib_file(deleted: usize) -> Vec<u8> {
+    let (max_m, max_m0, dim) = (2usize, 2usize, 2usize);
+    let offset_data = 4 + max_m0 * 4;
+    let label_offset = offset_data + dim * 4;
+    let size_data_per_element = label_offset + 8;
+
+    let mut out = Vec::new();
+    push_usize(&mut out, 0);
+    push_usize(&mut out, 3);
+    push_usize(&mut out, 3);
+    push_usize(&mut out, size_data_per_element);
+    push_usize(&mut out, label_offset);
+    push_usize(&mut out, offset_data);
+    out.extend_from_slice(&1i32.to_le_bytes()); // maxlevel
+    out.extend_from_slice(&2u32.to_le_bytes()); // entry point
+    push_usize(&mut out, max_m);
+    push_usize(&mut out, max_m0);
+    push_usize(&mut out, 2); // M
+    out.extend_from_slice(&(1.0f64 / 2f64.ln()).to_le_bytes());
+    push_usize(&mut out, 100);
+
+    // (level 0 links, deleted, vector, label)
+    let elements: [(&[u32], bool, [f32; 2], u64); 3] = [
+        (&[1, 2], deleted == 0, [0.0, 0.0], 10),
+        (&[0], deleted == 1, [1.0, 0.0], 11),
+        (&[0], deleted == 2, [5.0, 5.0], 12),
+    ];
+    for (links, deleted, vector, label) in elements {
+        out.extend_from_slice(&(links.len() as u16).to_le_bytes());
+        out.push(if deleted { 1 } else { 0 });
+        out.push(0);
+        for slot in 0..max_m0 {
+            out.extend_from_slice(&links.get(slot).copied().unwrap_or(0).to_le_bytes());
+        }
+        for value in vector {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        out.extend_from_slice(&label.to_le_bytes());
+    }
+
+    // Upper-level link lists: only element 2 has a level-1 list, which is empty
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&((4 + max_m * 4) as u32).to_le_bytes());
+    out.extend_from_slice(&[0u8; 12]);
+
+    out
+}
+
+#[test]
+fn test_load_handcrafted_hnswlib_file() {
+    let path = temp_path("handcrafted");
+    std::fs::write(&path, handcrafted_hnswlib_file(1)).unwrap();
+
+    let index: HNSWIndex<String> = HNSWIndex::load_hnswlib(&path, DistanceMetric::Euclidean, None).unwrap();
+
+    // The deleted element is dropped and labels become decimal ids
+    assert_eq!(index.len(), 2);
+    assert!(index.contains("10") && index.contains("12"));
+    assert!(!index.contains("11"));
+    assert_eq!(index.m, 2);
+    assert_eq!(index.ef_construction, 100);
+    assert_eq!(index.get_embedding("12").unwrap(), &[5.0, 5.0]);
+    assert_eq!(index.get_connections("12").unwrap().len(), 2);
+    assert_eq!(index.get_all_ids()[index.entry_point.unwrap()], "12");
+    assert_eq!(index.get_connections("10").unwrap(), vec![vec![1]]);
+
+    let results = index.search(&[4.0, 4.0], 1);
+    assert_eq!(results[0].0, "12");
+
+    // Labels can be mapped to arbitrary ids
+    let labels: HashMap<u64, String> = [(10, "origin"), (11, "deleted"), (12, "far")]
+        .into_iter()
+        .map(|(label, id)| (label, id.to_string()))
+        .collect();
+    let index: HNSWIndex<String> = HNSWIndex::load_hnswlib(&path, DistanceMetric::Euclidean, Some(&labels)).unwrap();
+    assert!(index.contains("origin") && index.contains("far"));
+
+    let mut missing = labels.clone();
+    missing.remove(&12);
+    let result: Result<HNSWIndex<String>, String> = HNSWIndex::load_hnswlib(&path, DistanceMetric::Euclidean, Some(&missing));
+    assert!(result.unwrap_err().contains("12"));
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_load_hnswlib_with_deleted_entry_point() {
+    let path = temp_path("deleted_entry_point");
+    std::fs::write(&path, handcrafted_hnswlib_file(2)).unwrap();
+
+    let index: HNSWIndex<String> = HNSWIndex::load_hnswlib(&path, DistanceMetric::Euclidean, None).unwrap();
+
+    // Element 0 linked to the deleted entry point, whose only neighbor was element 0 itself
+    assert_eq!(index.get_all_ids(), vec!["10", "11"]);
+    assert_eq!(index.get_all_ids()[index.entry_point.unwrap()], "10");
+    assert_eq!(index.get_connections("10").unwrap(), vec![vec![1]]);
+    assert_eq!(index.search(&[1.0, 0.0], 1)[0].0, "11");
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_save_and_load_roundtrip() {
+    let path = temp_path("roundtrip");
+    let mut rng = rand::thread_rng();
+    let mut index: HNSWIndex<String> = HNSWIndex::new(8, 8, 50);
+
+    for i in 0..100 {
+        let embedding: Vec<f32> = (0..8).map(|_| rng.gen_range(-1.0..1.0)).collect();
+        index.insert(i.to_string(), embedding, None).unwrap();
+    }
+
+    let labels = index.save_hnswlib(&path).unwrap();
+    assert_eq!(labels.len(), 100);
+    assert!(labels.iter().all(|(label, id)| label.to_string() == *id));
+
+    let loaded: HNSWIndex<String> = HNSWIndex::load_hnswlib(&path, DistanceMetric::Euclidean, None).unwrap();
+    assert_eq!(loaded.len(), index.len());
+    assert_eq!(loaded.entry_point, index.entry_point);
+
+    for id in index.get_all_ids() {
+        assert_eq!(index.get_embedding(&id), loaded.get_embedding(&id));
+        assert_eq!(index.get_connections(&id), loaded.get_connections(&id));
+    }
+
+    let query: Vec<f32> = (0..8).map(|_| rng.gen_range(-1.0..1.0)).collect();
+    let expected: Vec<String> = index.search(&query, 5).into_iter().map(|(id, _, _)| id).collect();
+    let actual: Vec<String> = loaded.search(&query, 5).into_iter().map(|(id, _, _)| id).collect();
+    assert_eq!(expected, actual);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_save_with_string_ids_and_cosine() {
+    let path = temp_path("string_ids");
+    let mut index = HNSWIndex::new_with_distance(2, 16, 200, DistanceMetric::Cosine);
+    let doc = Document { id: "d".to_string(), data: 1 };
+    index.insert("alpha".to_string(), vec![3.0, 4.0], Some(doc)).unwrap();
+    index.insert("beta".to_string(), vec![0.0, 2.0], None).unwrap();
+
+    // Non-numeric ids fall back to positional labels
+    let labels = index.save_hnswlib(&path).unwrap();
+    assert_eq!(labels, vec![(0, "alpha".to_string()), (1, "beta".to_string())]);
+
+    let mapping: HashMap<u64, String> = labels.into_iter().collect();
+    let loaded: HNSWIndex<i32> = HNSWIndex::load_hnswlib(&path, DistanceMetric::Cosine, Some(&mapping)).unwrap();
+
+    // Cosine vectors are stored normalized, as in hnswlib's cosine space
+    let alpha = loaded.get_embedding("alpha").unwrap();
+    assert!((alpha[0] - 0.6).abs() < 1e-6 && (alpha[1] - 0.8).abs() < 1e-6);
+    assert!(loaded.get_node("alpha").unwrap().document.is_none());
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_load_rejects_invalid_files() {
+    let path = temp_path("invalid");
+
+    std::fs::write(&path, [0u8; 10]).unwrap();
+    let result: Result<HNSWIndex<String>, String> = HNSWIndex::load_hnswlib(&path, DistanceMetric::Euclidean, None);
+    assert!(result.is_err());
+
+    let mut truncated = handcrafted_hnswlib_file(1);
+    truncated.truncate(truncated.len() - 4);
+    std::fs::write(&path, truncated).unwrap();
+    let result: Result<HNSWIndex<String>, String> = HNSWIndex::load_hnswlib(&path, DistanceMetric::Euclidean, None);
+    assert!(result.unwrap_err().contains("Truncated"));
+
+    std::fs::remove_file(&path).unwrap();
+}
@@ -1,5 +1,6 @@
 use swarc::index::HNSWIndex;
-use swarc::types::{Document, DistanceMetric};
+use swarc::FlatIndex;
+use swarc::types::{Document, DistanceMetric, WeightedEuclidean};
 
 #[test]
 fn test_index_creation() {
@@ -81,11 +82,6 @@ fn test_level_generation() {
     // Generate multiple levels and check they're reasonable
     let levels: Vec<usize> = (0..100).map(|_| index.generate_level()).collect();
     
-    // All levels should be non-negative
-    for level in &levels {
-        assert!(*level >= 0); // This is always true for usize, but kept for clarity
-    }
-    
     // Most levels should be small (exponential distribution)
     let small_levels = levels.iter().filter(|&&l| l < 10).count();
     assert!(small_levels > 80); // Most levels should be small
@@ -210,3 +206,124 @@ fn test_distance_metric_consistency() {
     let dist_ba_cosine = index_cosine.distance(&b, &a);
     assert!((dist_ab_cosine - dist_ba_cosine).abs() < 1e-6);
 }
+
+#[test]
+fn test_inner_product_distance() {
+    let index: HNSWIndex<String> = HNSWIndex::new_with_distance(3, 16, 200, DistanceMetric::InnerProduct);
+    
+    let a = vec![1.0, 2.0, 3.0];
+    let b = vec![4.0, -5.0, 6.0];
+    
+    // Distance is the negated dot product: -(4 - 10 + 18)
+    assert!((index.distance(&a, &b) + 12.0).abs() < 1e-6);
+    assert!((index.distance(&a, &b) - index.distance(&b, &a)).abs() < 1e-6);
+    
+    // Unlike Euclidean, a vector is not necessarily closest to itself
+    let long = vec![2.0, 4.0, 6.0];
+    assert!(index.distance(&a, &long) < index.distance(&a, &a));
+}
+
+#[test]
+fn test_lp_distances() {
+    let a = vec![1.0, 2.0, 3.0];
+    let b = vec![4.0, -2.0, 3.0];
+    
+    let manhattan: HNSWIndex<String> = HNSWIndex::new_with_distance(3, 16, 200, DistanceMetric::Manhattan);
+    assert!((manhattan.distance(&a, &b) - 7.0).abs() < 1e-6);
+    
+    let chebyshev: HNSWIndex<String> = HNSWIndex::new_with_distance(3, 16, 200, DistanceMetric::Chebyshev);
+    assert!((chebyshev.distance(&a, &b) - 4.0).abs() < 1e-6);
+    
+    // Minkowski reduces to Manhattan for p = 1 and Euclidean for p = 2
+    let minkowski_1: HNSWIndex<String> = HNSWIndex::new_with_distance(3, 16, 200, DistanceMetric::Minkowski(1.0));
+    assert!((minkowski_1.distance(&a, &b) - 7.0).abs() < 1e-5);
+    
+    let minkowski_2: HNSWIndex<String> = HNSWIndex::new_with_distance(3, 16, 200, DistanceMetric::Minkowski(2.0));
+    assert!((minkowski_2.distance(&a, &b) - 5.0).abs() < 1e-5);
+    
+    let minkowski_3: HNSWIndex<String> = HNSWIndex::new_with_distance(3, 16, 200, DistanceMetric::Minkowski(3.0));
+    assert!((minkowski_3.distance(&a, &b) - 91.0_f32.powf(1.0 / 3.0)).abs() < 1e-5);
+}
+
+#[test]
+fn test_weighted_euclidean_distance() {
+    let a = vec![1.0, 2.0, 3.0];
+    let b = vec![4.0, -2.0, 3.0];
+    
+    // Unit weights are plain Euclidean distance
+    let unit: HNSWIndex<String, WeightedEuclidean> = HNSWIndex::new_with_distance(3, 16, 200, WeightedEuclidean { weights: vec![1.0, 1.0, 1.0] });
+    assert!((unit.distance(&a, &b) - 5.0).abs() < 1e-6);
+    
+    // A zero weight ignores a dimension entirely
+    let weighted: HNSWIndex<String, WeightedEuclidean> = HNSWIndex::new_with_distance(3, 16, 200, WeightedEuclidean { weights: vec![0.0, 4.0, 1.0] });
+    assert!((weighted.distance(&a, &b) - 8.0).abs() < 1e-6);
+}
+
+#[test]
+fn test_invalid_metric_parameters_are_rejected() {
+    // Negative or non-finite weights, or one weight too few, are caught on the first insert
+    for weights in [vec![1.0, -1.0, 1.0], vec![1.0, f32::NAN, 1.0], vec![1.0, 1.0]] {
+        let mut index: HNSWIndex<String, WeightedEuclidean> = HNSWIndex::new_with_distance(3, 16, 200, WeightedEuclidean { weights: weights.clone() });
+        assert!(index.insert("a".to_string(), vec![1.0, 2.0, 3.0], None).is_err(), "{:?}", weights);
+        assert!(index.insert_parallel(vec![("a".to_string(), vec![1.0, 2.0, 3.0], None)]).is_err(), "{:?}", weights);
+        assert!(index.is_empty());
+
+        let mut flat: FlatIndex<String, WeightedEuclidean> = FlatIndex::new_with_distance(3, WeightedEuclidean { weights: weights.clone() });
+        assert!(flat.insert("a".to_string(), vec![1.0, 2.0, 3.0], None).is_err(), "{:?}", weights);
+    }
+
+    let mut index: HNSWIndex<String> = HNSWIndex::new_with_distance(3, 16, 200, DistanceMetric::Minkowski(0.0));
+    assert!(index.insert("a".to_string(), vec![1.0, 2.0, 3.0], None).is_err());
+
+    // The checked constructors reject bad parameters up front
+    assert!(DistanceMetric::minkowski(-1.0).is_err());
+    assert!(DistanceMetric::minkowski(f32::INFINITY).is_err());
+    assert_eq!(DistanceMetric::minkowski(3.0), Ok(DistanceMetric::Minkowski(3.0)));
+    assert!(WeightedEuclidean::new(vec![1.0, -0.5]).is_err());
+    assert!(WeightedEuclidean::new(vec![0.0, 2.0]).is_ok());
+}
+
+#[test]
+fn test_distance_metric_is_copy_and_hashable() {
+    use std::collections::HashSet;
+    
+    let metric = DistanceMetric::Minkowski(3.0);
+    let copied = metric;
+    assert_eq!(metric, copied);
+    assert_ne!(metric, DistanceMetric::Minkowski(4.0));
+    assert_ne!(DistanceMetric::Euclidean, DistanceMetric::Cosine);
+    // Orders are compared bit for bit, so even a NaN order equals itself
+    assert_eq!(DistanceMetric::Minkowski(f32::NAN), DistanceMetric::Minkowski(f32::NAN));
+    
+    let metrics: HashSet<DistanceMetric> = [metric, copied, DistanceMetric::Euclidean, DistanceMetric::Minkowski(4.0)].into_iter().collect();
+    assert_eq!(metrics.len(), 3);
+}
+
+#[test]
+fn test_mismatched_dimensions_are_rejected_under_every_storage_mode() {
+    use swarc::precision::HalfFormat;
+    use swarc::quantization::QuantizationRange;
+
+    type Enable = Box<dyn Fn(&mut HNSWIndex<String>)>;
+    let modes: Vec<(&str, Enable)> = vec![
+        ("f32", Box::new(|_| {})),
+        ("f16", Box::new(|index| index.set_half_precision(Some(HalfFormat::F16)).unwrap())),
+        ("bf16", Box::new(|index| index.set_half_precision(Some(HalfFormat::BF16)).unwrap())),
+        ("int8 only", Box::new(|index| index.quantize_int8(QuantizationRange::Global, 10, false).unwrap())),
+        ("binary only", Box::new(|index| index.quantize_binary(false).unwrap())),
+    ];
+
+    // Apart from f32, each mode leaves the vector arena without a row to read the dimension from
+    for (name, enable) in modes {
+        let mut index: HNSWIndex<String> = HNSWIndex::new(4, 16, 200);
+        index.insert("a".to_string(), vec![0.0, 1.0, 2.0, 3.0], None).unwrap();
+        enable(&mut index);
+        assert_eq!(index.dimension(), Some(4), "{}", name);
+
+        assert!(index.insert("short".to_string(), vec![1.0, 2.0, 3.0], None).is_err(), "{}", name);
+        assert!(index.insert("long".to_string(), vec![1.0; 5], None).is_err(), "{}", name);
+        assert!(index.insert_parallel(vec![("short".to_string(), vec![1.0, 2.0, 3.0], None)]).is_err(), "{}", name);
+        assert_eq!(index.len(), 1, "{}", name);
+        assert!(index.validate().is_empty(), "{}", name);
+    }
+}
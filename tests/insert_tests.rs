@@ -1,5 +1,5 @@
 use swarc::index::HNSWIndex;
-use swarc::types::Document;
+use swarc::types::{Document, DistanceMetric};
 
 #[test]
 fn test_insert_single_node() {
@@ -88,7 +88,7 @@ fn test_insert_different_embedding_dimensions() {
     let mut index = HNSWIndex::new(5, 16, 200);
     
     // Insert nodes with different embedding dimensions
-    let embeddings = vec![
+    let embeddings = [
         vec![1.0, 2.0, 3.0, 4.0, 5.0],
         vec![0.0, 0.0, 0.0, 0.0, 0.0],
         vec![-1.0, -2.0, -3.0, -4.0, -5.0],
@@ -213,6 +213,26 @@ fn test_insert_large_dataset() {
     }
 }
 
+#[test]
+fn test_insert_links_nodes_on_their_layers() {
+    let mut index: HNSWIndex<String> = HNSWIndex::new(2, 8, 50);
+    for i in 0..200 {
+        index.insert(format!("node_{}", i), vec![(i % 20) as f32, (i / 20) as f32], None).unwrap();
+    }
+    
+    // No node is left unlinked or linked to itself
+    assert_eq!(index.validate(), Vec::<String>::new());
+    
+    // Nodes above layer 0 are linked there as well
+    let upper_links: usize = index
+        .get_all_ids()
+        .iter()
+        .flat_map(|id| index.get_connections(id).unwrap().into_iter().skip(1))
+        .map(|neighbors| neighbors.len())
+        .sum();
+    assert!(upper_links > 0);
+}
+
 #[test]
 fn test_insert_with_zero_embedding() {
     let mut index: HNSWIndex<String> = HNSWIndex::new(3, 16, 200);
@@ -226,8 +246,7 @@ fn test_insert_with_zero_embedding() {
     let result = index.insert("zero_node".to_string(), zero_embedding, Some(doc));
     assert!(result.is_ok());
     
-    let node = index.get_node("zero_node").unwrap();
-    assert_eq!(node.embedding, vec![0.0, 0.0, 0.0]);
+    assert_eq!(index.get_embedding("zero_node").unwrap(), &[0.0, 0.0, 0.0]);
 }
 
 #[test]
@@ -243,6 +262,63 @@ fn test_insert_with_negative_embeddings() {
     let result = index.insert("negative_node".to_string(), negative_embedding, Some(doc));
     assert!(result.is_ok());
     
-    let node = index.get_node("negative_node").unwrap();
-    assert_eq!(node.embedding, vec![-1.0, -2.0, -3.0]);
+    assert_eq!(index.get_embedding("negative_node").unwrap(), &[-1.0, -2.0, -3.0]);
+}
+
+#[test]
+fn test_insert_with_nan_embeddings() {
+    let mut index: HNSWIndex<String> = HNSWIndex::new(2, 2, 20);
+    
+    // NaN distances are ordered rather than panicking when full neighbor lists are pruned
+    for i in 0..20 {
+        let embedding = if i % 4 == 0 { vec![f32::NAN, i as f32] } else { vec![i as f32, 0.0] };
+        index.insert(format!("node_{}", i), embedding, None).unwrap();
+    }
+    
+    assert_eq!(index.len(), 20);
+    assert_eq!(index.search(&[5.0, 0.0], 1)[0].0, "node_5");
+}
+
+#[test]
+fn test_select_neighbors_with_negative_distances() {
+    let mut index: HNSWIndex<String> = HNSWIndex::new_with_distance(2, 16, 200, DistanceMetric::InnerProduct);
+    
+    // Two near-duplicates and one vector in a different direction
+    index.insert("a".to_string(), vec![1.0, 0.0], None).unwrap();
+    index.insert("a_copy".to_string(), vec![1.0, 0.01], None).unwrap();
+    index.insert("b".to_string(), vec![0.0, 1.0], None).unwrap();
+    
+    // Inner product distances to the query (0.7, 0.7) are negative
+    let candidates = vec![(0, -0.7), (1, -0.707), (2, -0.7)];
+    let mut sorted = candidates.clone();
+    sorted.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+    
+    let selected = index.select_neighbors(&sorted, 2);
+    
+    // The closest candidate is kept, and the diverse one is preferred over its near-duplicate
+    assert_eq!(selected, vec![1, 2]);
+}
+
+#[test]
+fn test_select_neighbors_prefers_diverse_directions() {
+    let mut index: HNSWIndex<String> = HNSWIndex::new(2, 16, 200);
+    
+    // Three nodes on one side of the query and a farther one on the other
+    let embeddings = [vec![1.0, 0.0], vec![1.1, 0.0], vec![-1.2, 0.0], vec![1.3, 0.0]];
+    for (i, embedding) in embeddings.iter().enumerate() {
+        index.insert(format!("node_{}", i), embedding.clone(), None).unwrap();
+    }
+    
+    let query = [0.0, 0.0];
+    let candidates: Vec<(usize, f32)> = embeddings
+        .iter()
+        .enumerate()
+        .map(|(id, embedding)| (id, index.distance(&query, embedding)))
+        .collect();
+    
+    // Nodes 1 and 3 are nearer to node 0 than to the query, so node 2 is kept over them
+    assert_eq!(index.select_neighbors(&candidates, 2), vec![0, 2]);
+    
+    // Slots left over are filled with the closest pruned candidates
+    assert_eq!(index.select_neighbors(&candidates, 3), vec![0, 2, 1]);
 }
@@ -181,7 +181,7 @@ fn test_search_accuracy() {
     assert_eq!(results[0].0, "node_4");
     
     // Calculate expected distances
-    let expected_distances = vec![
+    let expected_distances = [
         ((1.0, 1.0), 0.707), // sqrt(0.5^2 + 0.5^2)
         ((1.0, 2.0), 0.707), // sqrt(0.5^2 + 0.5^2)
         ((2.0, 1.0), 0.707), // sqrt(0.5^2 + 0.5^2)
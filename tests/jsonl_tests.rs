@@ -0,0 +1,73 @@
+use swarc::index::HNSWIndex;
+use std::io::Cursor;
+
+fn record(id: &str, vector: &[f32], document: Option<&str>) -> String {
+    serde_json::json!({ "id": id, "vector": vector, "document": document }).to_string()
+}
+
+#[test]
+fn test_insert_jsonl_in_chunks() {
+    let mut lines: Vec<String> = (0..25)
+        .map(|i| record(&format!("node_{}", i), &[i as f32, 1.0, 0.0], (i % 2 == 0).then_some("even")))
+        .collect();
+    lines.insert(3, String::new());
+    lines.push(r#"{"id": "bare", "vector": [0.5, 0.5, 0.5]}"#.to_string());
+
+    let mut index: HNSWIndex<String> = HNSWIndex::new(3, 8, 50);
+    let report = index.insert_jsonl(Cursor::new(lines.join("\n")), 4).unwrap();
+
+    assert_eq!(report.inserted, 26);
+    assert!(report.errors.is_empty());
+    assert_eq!(index.len(), 26);
+    assert_eq!(index.get_node("node_4").unwrap().document.as_ref().unwrap().data, "even");
+    assert!(index.get_node("node_5").unwrap().document.is_none());
+    assert!(index.get_node("bare").unwrap().document.is_none());
+
+    let results = index.search(&[7.0, 1.0, 0.0], 1);
+    assert_eq!(results[0].0, "node_7");
+}
+
+#[test]
+fn test_insert_jsonl_reports_bad_lines() {
+    let mut index: HNSWIndex<String> = HNSWIndex::new(2, 8, 50);
+    index.insert("existing".to_string(), vec![0.0, 0.0], None).unwrap();
+
+    let lines = [
+        record("a", &[1.0, 2.0], None),
+        "{not json".to_string(),
+        record("b", &[1.0, 2.0, 3.0], None),
+        record("existing", &[1.0, 1.0], None),
+        record("a", &[3.0, 3.0], None),
+        record("c", &[], None),
+        r#"{"id": "d", "vector": [1.0, "x"]}"#.to_string(),
+        record("e", &[4.0, 4.0], None),
+    ];
+    let report = index.insert_jsonl(Cursor::new(lines.join("\n")), 100).unwrap();
+
+    assert_eq!(report.inserted, 2);
+    let lines: Vec<usize> = report.errors.iter().map(|(line, _)| *line).collect();
+    assert_eq!(lines, vec![2, 3, 4, 5, 6, 7]);
+    assert!(report.errors[1].1.contains("dimension 3, expected 2"));
+    assert!(report.errors[2].1.contains("already exists"));
+    assert_eq!(index.len(), 3);
+    assert!(index.contains("a") && index.contains("e"));
+}
+
+#[test]
+fn test_insert_jsonl_links_the_first_chunk() {
+    let lines: Vec<String> = (0..40)
+        .map(|i| record(&format!("node_{}", i), &[i as f32, (i % 5) as f32], None))
+        .collect();
+
+    // The whole stream is one chunk into an empty index
+    let mut index: HNSWIndex<String> = HNSWIndex::new(2, 4, 20);
+    let report = index.insert_jsonl(Cursor::new(lines.join("\n")), 100).unwrap();
+
+    assert_eq!(report.inserted, 40);
+    assert_eq!(index.validate(), Vec::<String>::new());
+    for i in 0..40 {
+        let id = format!("node_{}", i);
+        assert!(!index.get_connections(&id).unwrap()[0].is_empty(), "{} has no links", id);
+        assert_eq!(index.search(&[i as f32, (i % 5) as f32], 1)[0].0, id);
+    }
+}
@@ -0,0 +1,89 @@
+use swarc::index::HNSWIndex;
+use swarc::memory::HeapSize;
+use swarc::precision::HalfFormat;
+use swarc::types::Document;
+use rand::Rng;
+
+fn build_index(count: usize, dim: usize) -> HNSWIndex<String> {
+    let mut rng = rand::thread_rng();
+    let mut index = HNSWIndex::new(dim, 8, 50);
+    for i in 0..count {
+        let embedding: Vec<f32> = (0..dim).map(|_| rng.gen_range(-1.0..1.0)).collect();
+        let doc = Document { id: format!("doc_{}", i), data: "x".repeat(100) };
+        index.insert(format!("node_{}", i), embedding, Some(doc)).unwrap();
+    }
+    index
+}
+
+#[test]
+fn test_memory_usage_breakdown() {
+    let index = build_index(200, 32);
+    let usage = index.memory_usage();
+
+    assert!(usage.vectors >= 200 * 32 * 4);
+    assert!(usage.vectors < 2 * 200 * 32 * 4);
+    assert!(usage.documents >= 200 * 100);
+    assert!(usage.id_map > 0 && usage.nodes > 0);
+    assert_eq!(usage.quantized, 0);
+
+    // Layer 0 holds a block for every node, higher layers only for the nodes that reach them
+    assert!(usage.adjacency[0] >= 200 * (1 + index.m_max) * 4);
+    assert!(usage.adjacency.windows(2).all(|pair| pair[0] >= pair[1]));
+    assert_eq!(
+        usage.total(),
+        usage.vectors + usage.quantized + usage.adjacency() + usage.nodes + usage.id_map + usage.documents
+    );
+}
+
+#[test]
+fn test_memory_usage_follows_storage_format() {
+    let mut index = build_index(100, 64);
+    let f32_bytes = index.memory_usage().vectors;
+
+    index.set_half_precision(Some(HalfFormat::F16)).unwrap();
+    let half_bytes = index.memory_usage().vectors;
+    assert!(half_bytes < f32_bytes);
+    assert!(half_bytes >= 100 * 64 * 2);
+
+    index.clear();
+    let usage = index.memory_usage();
+    assert_eq!((usage.vectors, usage.documents, usage.adjacency()), (0, 0, 0));
+}
+
+#[test]
+fn test_memory_usage_with_documents_without_heap_size() {
+    // A document type that does not implement HeapSize
+    #[derive(Clone)]
+    struct Tags(Vec<String>);
+
+    let mut index: HNSWIndex<Tags> = HNSWIndex::new(2, 8, 50);
+    for i in 0..10 {
+        let doc = Document { id: format!("doc_{}", i), data: Tags(vec!["x".repeat(50)]) };
+        index.insert(format!("node_{}", i), vec![i as f32, 0.0], Some(doc)).unwrap();
+    }
+
+    let inline = index.memory_usage_with(|_| 0);
+    let measured = index.memory_usage_with(|tags| tags.0.iter().map(String::capacity).sum());
+    assert_eq!(measured.documents - inline.documents, 10 * 50);
+    assert_eq!(measured.vectors, inline.vectors);
+
+    // memory_usage is memory_usage_with measuring documents by HeapSize
+    let index = build_index(20, 4);
+    assert_eq!(index.memory_usage(), index.memory_usage_with(HeapSize::heap_size));
+}
+
+#[test]
+fn test_heap_size_of_documents() {
+    assert_eq!(7u32.heap_size(), 0);
+    assert_eq!(String::with_capacity(40).heap_size(), 40);
+    assert_eq!(Vec::<u64>::with_capacity(10).heap_size(), 80);
+    assert_eq!(Some(String::with_capacity(5)).heap_size(), 5);
+
+    // Custom types are counted by their inline size unless they report more
+    struct Opaque;
+    impl HeapSize for Opaque {}
+    assert_eq!(Opaque.heap_size(), 0);
+
+    let value = serde_json::json!({ "title": "hello" });
+    assert!(value.heap_size() >= "title".len() + "hello".len());
+}
@@ -0,0 +1,61 @@
+use swarc::index::HNSWIndex;
+use std::sync::atomic::Ordering;
+
+fn build(n: usize) -> HNSWIndex<String> {
+    let mut index = HNSWIndex::new(2, 8, 50);
+    for i in 0..n {
+        index.insert(format!("node_{}", i), vec![i as f32, (i % 3) as f32], None).unwrap();
+    }
+    index
+}
+
+#[test]
+fn test_metrics_count_inserts_removes_and_searches() {
+    let mut index = build(20);
+    index.remove("node_3").unwrap();
+    assert!(index.remove("missing").is_err());
+    let items = vec![("batch_0".to_string(), vec![0.5, 0.5], None), ("batch_1".to_string(), vec![1.5, 0.5], None)];
+    index.insert_parallel(items).unwrap();
+    for i in 0..5 {
+        index.search(&[i as f32, 0.0], 3);
+    }
+
+    let metrics = index.metrics();
+    assert_eq!(metrics.inserts.load(Ordering::Relaxed), 22);
+    assert_eq!(metrics.removes.load(Ordering::Relaxed), 1);
+    assert_eq!(metrics.searches.load(Ordering::Relaxed), 5);
+    assert_eq!(metrics.search_latency.count(), 5);
+    assert_eq!(metrics.distance_computations.count(), 5);
+    assert_eq!(metrics.nodes_visited.count(), 5);
+    // Every search computes at least the distance to the entry point and expands it
+    assert!(metrics.distance_computations.sum() >= 5);
+    assert!(metrics.nodes_visited.sum() >= 5);
+
+    // An empty index returns without searching
+    let empty: HNSWIndex<String> = HNSWIndex::new(2, 8, 50);
+    empty.search(&[0.0, 0.0], 3);
+    assert_eq!(empty.metrics().searches.load(Ordering::Relaxed), 0);
+}
+
+#[test]
+fn test_metrics_prometheus_text() {
+    let index = build(10);
+    index.search(&[1.0, 1.0], 2);
+    let text = index.metrics().to_prometheus("swarc");
+
+    assert!(text.contains("# TYPE swarc_inserts_total counter\nswarc_inserts_total 10\n"));
+    assert!(text.contains("swarc_searches_total 1\n"));
+    assert!(text.contains("# TYPE swarc_search_latency_seconds histogram\n"));
+    assert!(text.contains("swarc_search_latency_seconds_bucket{le=\"+Inf\"} 1\n"));
+    assert!(text.contains("swarc_search_latency_seconds_count 1\n"));
+    assert!(text.contains("swarc_search_nodes_visited_count 1\n"));
+
+    // Buckets are cumulative and end at the total count
+    let buckets: Vec<u64> = text
+        .lines()
+        .filter(|line| line.starts_with("swarc_search_distance_computations_bucket"))
+        .map(|line| line.rsplit(' ').next().unwrap().parse().unwrap())
+        .collect();
+    assert!(buckets.windows(2).all(|pair| pair[0] <= pair[1]));
+    assert_eq!(*buckets.last().unwrap(), 1);
+}
@@ -0,0 +1,110 @@
+use swarc::index::HNSWIndex;
+use swarc::mips::MipsTransform;
+use swarc::types::DistanceMetric;
+use rand::Rng;
+
+mod common;
+
+use common::random_vectors;
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b.iter()).map(|(x, y)| x * y).sum()
+}
+
+#[test]
+fn test_transform_equalizes_norms() {
+    let vectors = [vec![3.0, 4.0], vec![1.0, 0.0], vec![0.0, 0.0]];
+    let transform = MipsTransform::fit(vectors.iter().map(|v| v.as_slice()));
+
+    assert!((transform.max_norm - 5.0).abs() < 1e-6);
+
+    for vector in &vectors {
+        let extended = transform.transform_item(vector).unwrap();
+        assert_eq!(extended.len(), 3);
+        assert_eq!(&extended[..2], vector.as_slice());
+        assert!((dot(&extended, &extended).sqrt() - 5.0).abs() < 1e-5);
+    }
+
+    assert_eq!(transform.transform_query(&[1.0, 2.0]), vec![1.0, 2.0, 0.0]);
+}
+
+#[test]
+fn test_transform_rejects_vectors_above_max_norm() {
+    let transform = MipsTransform::new(1.0);
+
+    assert!(transform.transform_item(&[0.6, 0.8]).is_ok());
+    assert!(transform.transform_item(&[2.0, 0.0]).is_err());
+}
+
+#[test]
+fn test_transformed_euclidean_search_matches_inner_product_ranking() {
+    let mut rng = rand::thread_rng();
+    let vectors: Vec<Vec<f32>> = (0..50)
+        .map(|_| (0..4).map(|_| rng.gen_range(-2.0..2.0)).collect())
+        .collect();
+    let transform = MipsTransform::fit(vectors.iter().map(|v| v.as_slice()));
+
+    let mut index: HNSWIndex<String> = HNSWIndex::new_with_distance(5, 16, 200, DistanceMetric::Euclidean);
+    for (i, vector) in vectors.iter().enumerate() {
+        index.insert(i.to_string(), transform.transform_item(vector).unwrap(), None).unwrap();
+    }
+
+    let query: Vec<f32> = (0..4).map(|_| rng.gen_range(-2.0..2.0)).collect();
+    let results = index.search(&transform.transform_query(&query), 5);
+
+    let mut expected: Vec<(usize, f32)> = vectors.iter().enumerate().map(|(i, v)| (i, dot(&query, v))).collect();
+    expected.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+
+    for ((id, distance, _), (expected_id, expected_ip)) in results.iter().zip(expected.iter()) {
+        assert_eq!(*id, expected_id.to_string());
+        assert!((transform.inner_product(&query, *distance) - expected_ip).abs() < 1e-3);
+    }
+}
+
+#[test]
+fn test_mips_flow_through_index() {
+    let path = std::env::temp_dir().join(format!("swarc_mips_{}_flow.json", std::process::id()));
+    // Norms vary between vectors, so inner product and Euclidean rankings differ
+    let vectors: Vec<Vec<f32>> = random_vectors(500, 16, 1)
+        .into_iter()
+        .enumerate()
+        .map(|(i, v)| v.into_iter().map(|x| x * (1 + i % 4) as f32).collect())
+        .collect();
+    let transform = MipsTransform::fit(vectors.iter().map(|v| v.as_slice()));
+
+    let mut index: HNSWIndex<String> = HNSWIndex::new_with_distance(17, 16, 200, DistanceMetric::Euclidean);
+    let items = vectors
+        .iter()
+        .enumerate()
+        .map(|(i, v)| (format!("node_{}", i), transform.transform_item(v).unwrap(), None))
+        .collect();
+    index.insert_parallel(items).unwrap();
+    assert_eq!(&index.get_embedding("node_3").unwrap()[..16], vectors[3].as_slice());
+
+    // A later vector beyond the fitted norm is rejected before it reaches the index
+    let long: Vec<f32> = vectors[0].iter().map(|x| x * 10.0).collect();
+    assert!(transform.transform_item(&long).is_err());
+
+    // Only max_norm needs to be kept to search a loaded index
+    index.save(&path).unwrap();
+    let loaded: HNSWIndex<String> = HNSWIndex::load(&path).unwrap();
+    let transform = MipsTransform::new(transform.max_norm);
+    std::fs::remove_file(&path).unwrap();
+
+    let mut hits = 0;
+    let queries = random_vectors(20, 16, 2);
+    for query in &queries {
+        let mut exact: Vec<(usize, f32)> = vectors.iter().enumerate().map(|(i, v)| (i, dot(query, v))).collect();
+        exact.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        let expected: Vec<String> = exact.iter().take(10).map(|(i, _)| format!("node_{}", i)).collect();
+
+        let results = loaded.search(&transform.transform_query(query), 10);
+        assert_eq!(results.len(), 10);
+        for (id, distance, _) in &results {
+            let i: usize = id["node_".len()..].parse().unwrap();
+            assert!((transform.inner_product(query, *distance) - dot(query, &vectors[i])).abs() < 1e-2);
+        }
+        hits += results.iter().filter(|(id, _, _)| expected.contains(id)).count();
+    }
+    assert!(hits as f32 / (queries.len() * 10) as f32 > 0.9);
+}
@@ -0,0 +1,170 @@
+use swarc::index::HNSWIndex;
+use swarc::npy::{read_npy, read_npy_mmap, write_npy, NpyMatrix, NpyRows};
+use swarc::types::DistanceMetric;
+use std::path::PathBuf;
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("swarc_npy_{}_{}.npy", std::process::id(), name))
+}
+
+/// Build a version 1.0 .npy file by hand from a header dict and raw payload
+fn raw_npy(header: &str, payload: &[u8]) -> Vec<u8> {
+    let mut header = header.to_string();
+    header.push('\n');
+    let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+    bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(header.as_bytes());
+    bytes.extend_from_slice(payload);
+    bytes
+}
+
+#[test]
+fn test_write_and_read_roundtrip() {
+    let path = temp_path("roundtrip");
+    let matrix = NpyMatrix::new(2, 3, vec![1.0, 2.0, 3.0, -4.0, 5.5, 0.0]).unwrap();
+
+    write_npy(&path, &matrix).unwrap();
+
+    // The data section of a written file starts on a 64-byte boundary
+    let bytes = std::fs::read(&path).unwrap();
+    assert_eq!((bytes.len() - 6 * 4) % 64, 0);
+
+    assert_eq!(read_npy(&path).unwrap(), matrix);
+    assert_eq!(read_npy_mmap(&path).unwrap(), matrix);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_read_f64_and_f16() {
+    let f64_path = temp_path("f64");
+    let payload: Vec<u8> = [0.5f64, -1.25, 2.0, 3.0].iter().flat_map(|v| v.to_le_bytes()).collect();
+    std::fs::write(&f64_path, raw_npy("{'descr': '<f8', 'fortran_order': False, 'shape': (2, 2), }", &payload)).unwrap();
+
+    let matrix = read_npy(&f64_path).unwrap();
+    assert_eq!((matrix.rows, matrix.cols), (2, 2));
+    assert_eq!(matrix.data, vec![0.5, -1.25, 2.0, 3.0]);
+
+    let f16_path = temp_path("f16");
+    // 1.0, -2.0 and 0.5 in IEEE half precision
+    let payload: Vec<u8> = [0x3c00u16, 0xc000, 0x3800].iter().flat_map(|v| v.to_le_bytes()).collect();
+    std::fs::write(&f16_path, raw_npy("{'descr': '<f2', 'fortran_order': False, 'shape': (1, 3), }", &payload)).unwrap();
+
+    let matrix = read_npy_mmap(&f16_path).unwrap();
+    assert_eq!((matrix.rows, matrix.cols), (1, 3));
+    assert_eq!(matrix.row(0), &[1.0, -2.0, 0.5]);
+
+    std::fs::remove_file(&f64_path).unwrap();
+    std::fs::remove_file(&f16_path).unwrap();
+}
+
+#[test]
+fn test_read_big_endian() {
+    let path = temp_path("big_endian");
+    let payload: Vec<u8> = [1.5f32, -3.0].iter().flat_map(|v| v.to_be_bytes()).collect();
+    std::fs::write(&path, raw_npy("{'descr': '>f4', 'fortran_order': False, 'shape': (1, 2), }", &payload)).unwrap();
+
+    assert_eq!(read_npy(&path).unwrap().data, vec![1.5, -3.0]);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_read_rejects_unsupported_arrays() {
+    let payload = vec![0u8; 16];
+    let cases = [
+        ("fortran", "{'descr': '<f4', 'fortran_order': True, 'shape': (2, 2), }", "Fortran"),
+        ("one_dim", "{'descr': '<f4', 'fortran_order': False, 'shape': (4,), }", "2-D"),
+        ("int", "{'descr': '<i4', 'fortran_order': False, 'shape': (2, 2), }", "dtype"),
+        ("truncated", "{'descr': '<f4', 'fortran_order': False, 'shape': (4, 4), }", "Truncated"),
+        ("overflow", "{'descr': '<f8', 'fortran_order': False, 'shape': (4611686018427387904, 8), }", "too large"),
+    ];
+
+    for (name, header, expected) in cases {
+        let path = temp_path(name);
+        std::fs::write(&path, raw_npy(header, &payload)).unwrap();
+
+        let err = read_npy(&path).unwrap_err();
+        assert!(err.contains(expected), "{}: {}", name, err);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    let path = temp_path("not_npy");
+    std::fs::write(&path, b"definitely not numpy").unwrap();
+    assert!(read_npy(&path).is_err());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_matrix_rejects_overflowing_shape() {
+    let err = NpyMatrix::new(usize::MAX, 2, Vec::new()).unwrap_err();
+    assert!(err.contains("too large"), "{}", err);
+    assert!(NpyMatrix::new(2, 2, vec![0.0; 3]).is_err());
+}
+
+#[test]
+fn test_build_index_from_npy() {
+    let path = temp_path("build");
+    let matrix = NpyMatrix::new(3, 2, vec![0.0, 0.0, 1.0, 0.0, 10.0, 10.0]).unwrap();
+    write_npy(&path, &matrix).unwrap();
+
+    // Generated ids are the row numbers
+    let index: HNSWIndex<String> = HNSWIndex::from_npy(&path, 16, 200, DistanceMetric::Euclidean, None, false).unwrap();
+    assert_eq!(index.len(), 3);
+    assert!(index.contains("0") && index.contains("1") && index.contains("2"));
+
+    let results = index.search(&[9.0, 9.0], 1);
+    assert_eq!(results[0].0, "2");
+
+    // Supplied ids are used in row order
+    let ids = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+    let index: HNSWIndex<String> = HNSWIndex::from_npy(&path, 16, 200, DistanceMetric::Euclidean, Some(ids), true).unwrap();
+    assert_eq!(index.get_embedding("b").unwrap(), &[1.0, 0.0]);
+
+    // The number of ids must match the number of rows
+    for mmap in [false, true] {
+        let result: Result<HNSWIndex<String>, String> =
+            HNSWIndex::from_npy(&path, 16, 200, DistanceMetric::Euclidean, Some(vec!["a".to_string()]), mmap);
+        assert!(result.is_err());
+    }
+
+    // Rows of a mapped file can be added to an existing index
+    let mut index: HNSWIndex<String> = HNSWIndex::new(2, 16, 200);
+    index.insert("x".to_string(), vec![5.0, 5.0], None).unwrap();
+    let rows = NpyRows::open(&path).unwrap();
+    assert!(index.insert_npy_rows(&rows, None).unwrap().iter().all(Result::is_ok));
+    assert_eq!(index.len(), 4);
+    assert_eq!(index.get_embedding("2").unwrap(), &[10.0, 10.0]);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_export_npy() {
+    let path = temp_path("export");
+    let mut index: HNSWIndex<String> = HNSWIndex::new(2, 16, 200);
+    index.insert("x".to_string(), vec![1.0, 2.0], None).unwrap();
+    index.insert("y".to_string(), vec![3.0, 4.0], None).unwrap();
+
+    let ids = index.export_npy(&path).unwrap();
+    let matrix = read_npy(&path).unwrap();
+
+    assert_eq!(ids.len(), 2);
+    for (i, id) in ids.iter().enumerate() {
+        assert_eq!(index.get_embedding(id).unwrap(), matrix.row(i));
+    }
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_export_keeps_dimension_of_emptied_index() {
+    let mut index: HNSWIndex<String> = HNSWIndex::new(3, 16, 200);
+    index.insert("x".to_string(), vec![1.0, 2.0, 3.0], None).unwrap();
+    index.remove("x").unwrap();
+
+    let (matrix, ids) = index.to_matrix().unwrap();
+    assert!(ids.is_empty());
+    assert_eq!((matrix.rows, matrix.cols), (0, 3));
+}
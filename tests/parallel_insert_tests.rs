@@ -177,6 +177,47 @@ fn test_parallel_insert_large_dataset() {
     assert!(index.contains("node_999"));
 }
 
+#[test]
+fn test_parallel_insert_links_every_node() {
+    let mut index: HNSWIndex<String> = HNSWIndex::new(16, 8, 50);
+    let items: Vec<_> = (0..300).map(|i| (format!("node_{}", i), generate_random_embedding(16), None)).collect();
+    let embeddings: Vec<Vec<f32>> = items.iter().map(|(_, embedding, _)| embedding.clone()).collect();
+    
+    // The first batch into an empty index is linked, not just the ones after it
+    index.insert_parallel(items).unwrap();
+    assert_eq!(index.validate(), Vec::<String>::new());
+    
+    // Nodes keep the order they were given in
+    let ids: Vec<String> = (0..300).map(|i| format!("node_{}", i)).collect();
+    assert_eq!(index.get_all_ids(), ids);
+    
+    // Neighbors link back to most of the nodes that chose them
+    let mut links = 0;
+    let mut returned = 0;
+    for (slot, id) in ids.iter().enumerate() {
+        for (layer, neighbors) in index.get_connections(id).unwrap().iter().enumerate() {
+            for &neighbor in neighbors {
+                links += 1;
+                if index.get_connections(&ids[neighbor]).unwrap()[layer].contains(&slot) {
+                    returned += 1;
+                }
+            }
+        }
+    }
+    assert!(returned * 2 > links, "{} of {} links are returned", returned, links);
+    
+    // A second batch links into the existing graph
+    let more: Vec<_> = (300..400).map(|i| (format!("node_{}", i), generate_random_embedding(16), None)).collect();
+    index.insert_parallel(more).unwrap();
+    assert_eq!(index.validate(), Vec::<String>::new());
+    
+    // A wide search reaches every node through the links
+    index.ef_search = Some(200);
+    for (i, embedding) in embeddings.iter().enumerate().step_by(30) {
+        assert_eq!(index.search(embedding, 1)[0].0, format!("node_{}", i));
+    }
+}
+
 #[test]
 fn test_parallel_vs_sequential_insertion() {
     let dimension = 64;
@@ -271,15 +312,34 @@ fn test_parallel_insertion_with_different_dimensions() {
 fn test_parallel_insertion_error_handling() {
     let mut index: HNSWIndex<String> = HNSWIndex::new(3, 16, 200);
     
-    // Test with invalid embedding dimensions (should not panic, but may have issues)
+    // Vectors share one buffer, so a batch mixing dimensions is rejected as a whole
     let items = vec![
         ("node1".to_string(), vec![1.0, 2.0], None), // Wrong dimension
         ("node2".to_string(), vec![3.0, 4.0, 5.0], None), // Correct dimension
     ];
     
-    // This should still work, but the search might not be optimal
     let result = index.insert_parallel(items);
-    assert!(result.is_ok());
+    assert!(result.is_err());
+    assert_eq!(index.len(), 0);
     
-    assert_eq!(index.len(), 2);
+    // Later vectors must match the dimension of the first one stored
+    index.insert("node2".to_string(), vec![3.0, 4.0, 5.0], None).unwrap();
+    assert!(index.insert("node1".to_string(), vec![1.0, 2.0], None).is_err());
+    assert_eq!(index.len(), 1);
+}
+
+#[test]
+fn test_parallel_insertion_error_leaves_quantized_index_unchanged() {
+    let mut index: HNSWIndex<String> = HNSWIndex::new(3, 16, 200);
+    index.insert("node0".to_string(), vec![1.0, 2.0, 3.0], None).unwrap();
+    index.quantize_binary(false).unwrap();
+    
+    // Only the last vector is rejected, after the others could have been encoded
+    let mut items: Vec<_> = (1..5).map(|i| (format!("node{}", i), vec![i as f32; 3], None)).collect();
+    items.push(("node5".to_string(), vec![5.0; 4], None));
+    assert!(index.insert_parallel(items).is_err());
+    
+    assert_eq!(index.len(), 1);
+    assert!(!index.contains("node1"));
+    assert!(index.validate().is_empty());
 }
@@ -0,0 +1,162 @@
+use swarc::index::HNSWIndex;
+use swarc::types::{Document, DistanceMetric, WeightedEuclidean};
+use std::path::PathBuf;
+use rand::Rng;
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("swarc_persist_{}_{}.json", std::process::id(), name))
+}
+
+#[test]
+fn test_save_and_load_roundtrip() {
+    let path = temp_path("roundtrip");
+    let mut rng = rand::thread_rng();
+    let mut index = HNSWIndex::new(4, 8, 100);
+
+    for i in 0..50 {
+        let embedding: Vec<f32> = (0..4).map(|_| rng.gen_range(-1.0..1.0)).collect();
+        let doc = Document {
+            id: format!("doc_{}", i),
+            data: format!("content_{}", i),
+        };
+        index.insert(format!("node_{}", i), embedding, Some(doc)).unwrap();
+    }
+
+    index.save(&path).unwrap();
+    let loaded: HNSWIndex<String> = HNSWIndex::load(&path).unwrap();
+
+    assert_eq!(loaded.len(), index.len());
+    assert_eq!(loaded.m, index.m);
+    assert_eq!(loaded.ef_construction, index.ef_construction);
+    assert_eq!(loaded.entry_point, index.entry_point);
+    assert_eq!(loaded.distance_metric, index.distance_metric);
+
+    for id in index.get_all_ids() {
+        let original = index.get_node(&id).unwrap();
+        let restored = loaded.get_node(&id).unwrap();
+        assert_eq!(index.get_embedding(&id), loaded.get_embedding(&id));
+        assert_eq!(index.get_connections(&id), loaded.get_connections(&id));
+        assert_eq!(original.document.as_ref().unwrap().data, restored.document.as_ref().unwrap().data);
+    }
+
+    let query: Vec<f32> = (0..4).map(|_| rng.gen_range(-1.0..1.0)).collect();
+    let expected: Vec<String> = index.search(&query, 5).into_iter().map(|(id, _, _)| id).collect();
+    let actual: Vec<String> = loaded.search(&query, 5).into_iter().map(|(id, _, _)| id).collect();
+    assert_eq!(expected, actual);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_empty_embeddings_are_rejected_so_saves_load_back() {
+    let path = temp_path("empty_embedding");
+    let mut index: HNSWIndex<String> = HNSWIndex::new(2, 8, 100);
+
+    // Neither a first node nor a later one may be empty
+    assert!(index.insert("empty".to_string(), Vec::new(), None).unwrap_err().contains("empty"));
+    index.insert("a".to_string(), vec![1.0, 0.0], None).unwrap();
+    assert!(index.insert("empty".to_string(), Vec::new(), None).is_err());
+    let batch = vec![("b".to_string(), vec![0.0, 1.0], None), ("empty".to_string(), Vec::new(), None)];
+    assert!(index.insert_parallel(batch).is_err());
+    assert_eq!(index.len(), 1);
+
+    index.save(&path).unwrap();
+    let loaded: HNSWIndex<String> = HNSWIndex::load(&path).unwrap();
+    assert_eq!(loaded.get_all_ids(), vec!["a"]);
+    assert_eq!(loaded.get_embedding("a").unwrap(), &[1.0, 0.0]);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_weighted_metric_is_persisted() {
+    let path = temp_path("weighted");
+    let metric = WeightedEuclidean::new(vec![0.5, 2.0]).unwrap();
+    let mut index: HNSWIndex<String, WeightedEuclidean> = HNSWIndex::new_with_distance(2, 16, 200, metric.clone());
+    index.insert("a".to_string(), vec![1.0, 1.0], None).unwrap();
+
+    index.save(&path).unwrap();
+    let loaded: HNSWIndex<String, WeightedEuclidean> = HNSWIndex::load(&path).unwrap();
+
+    assert_eq!(loaded.distance_metric, metric);
+    // sqrt(0.5 * 2² + 2.0 * 1²)
+    assert!((loaded.distance(&[0.0, 0.0], &[2.0, 1.0]) - 2.0).abs() < 1e-6);
+
+    // Weights edited to be negative or of the wrong length are rejected on load
+    let saved = std::fs::read_to_string(&path).unwrap();
+    for weights in ["[-0.5,2.0]", "[0.5,2.0,1.0]"] {
+        std::fs::write(&path, saved.replace("[0.5,2.0]", weights)).unwrap();
+        let result: Result<HNSWIndex<String, WeightedEuclidean>, String> = HNSWIndex::load(&path);
+        assert!(result.is_err(), "{}", weights);
+    }
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_load_empty_index() {
+    let path = temp_path("empty");
+    let index: HNSWIndex<String> = HNSWIndex::new_with_distance(8, 16, 200, DistanceMetric::Minkowski(3.0));
+
+    index.save(&path).unwrap();
+    let loaded: HNSWIndex<String> = HNSWIndex::load(&path).unwrap();
+
+    assert!(loaded.is_empty());
+    assert!(loaded.entry_point.is_none());
+    assert_eq!(loaded.distance_metric, DistanceMetric::Minkowski(3.0));
+    assert!(loaded.search(&[0.0; 8], 3).is_empty());
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_load_rejects_invalid_files() {
+    let path = temp_path("invalid");
+
+    std::fs::write(&path, "not json").unwrap();
+    let result: Result<HNSWIndex<String>, String> = HNSWIndex::load(&path);
+    assert!(result.is_err());
+
+    // A link to a node that does not exist
+    let mut index: HNSWIndex<String> = HNSWIndex::new(2, 16, 200);
+    index.insert("a".to_string(), vec![1.0, 1.0], None).unwrap();
+    index.save(&path).unwrap();
+    let mut json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    json["graph"]["layer0"][0] = serde_json::json!(1);
+    json["graph"]["layer0"][1] = serde_json::json!(7);
+    std::fs::write(&path, json.to_string()).unwrap();
+
+    let result: Result<HNSWIndex<String>, String> = HNSWIndex::load(&path);
+    assert!(result.unwrap_err().contains("does not exist"));
+
+    let missing: Result<HNSWIndex<String>, String> = HNSWIndex::load(temp_path("missing"));
+    assert!(missing.is_err());
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_load_normalizes_legacy_cosine_index() {
+    let path = temp_path("legacy_cosine");
+    let mut index: HNSWIndex<String> = HNSWIndex::new_with_distance(2, 16, 200, DistanceMetric::Cosine);
+    index.insert("a".to_string(), vec![3.0, 4.0], None).unwrap();
+    index.save(&path).unwrap();
+
+    // Rewrite the file the way it looked before embeddings were normalized on insert,
+    // which was also before vectors and links moved out of the nodes
+    let mut json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    json.as_object_mut().unwrap().remove("vectors");
+    json.as_object_mut().unwrap().remove("graph");
+    json.as_object_mut().unwrap().remove("norms");
+    let node = &mut json["nodes"][0];
+    node["embedding"] = serde_json::json!([3.0, 4.0]);
+    node["connections"] = serde_json::json!([[]]);
+    std::fs::write(&path, json.to_string()).unwrap();
+
+    let loaded: HNSWIndex<String> = HNSWIndex::load(&path).unwrap();
+    let a = loaded.get_embedding("a").unwrap();
+    assert!((a[0] - 0.6).abs() < 1e-6 && (a[1] - 0.8).abs() < 1e-6);
+    assert_eq!(loaded.get_norm("a"), Some(5.0));
+
+    std::fs::remove_file(&path).unwrap();
+}
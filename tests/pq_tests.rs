@@ -0,0 +1,135 @@
+use swarc::index::HNSWIndex;
+use swarc::pq::ProductQuantizer;
+use swarc::types::{ComponentSum, DistanceMetric};
+use std::path::PathBuf;
+
+mod common;
+
+use common::{random_vectors, build_index, recall_at_10};
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("swarc_pq_{}_{}", std::process::id(), name))
+}
+
+#[test]
+fn test_train_encode_decode() {
+    let vectors = random_vectors(16, 12, 1);
+    let sample: Vec<&[f32]> = vectors.iter().map(|v| v.as_slice()).collect();
+
+    // With a centroid per training vector, every training vector is reconstructed exactly
+    let quantizer = ProductQuantizer::train(&sample, 4, 16, 5).unwrap();
+    assert_eq!(quantizer.sub_dimension(), 3);
+    assert_eq!(quantizer.code_size(), 4);
+    for vector in &vectors {
+        let codes = quantizer.encode(vector).unwrap();
+        assert_eq!(codes.len(), 4);
+        assert_eq!(&quantizer.decode(&codes), vector);
+    }
+
+    // A NaN component is still encoded, as some centroid, rather than panicking
+    assert_eq!(quantizer.encode(&[f32::NAN; 12]).unwrap().len(), 4);
+
+    // Asking for more centroids than sample vectors trains one per vector
+    let quantizer = ProductQuantizer::train(&sample, 3, 256, 5).unwrap();
+    assert_eq!(quantizer.centroids_per_subspace, 16);
+}
+
+#[test]
+fn test_train_rejects_bad_parameters() {
+    let vectors = random_vectors(10, 12, 2);
+    let sample: Vec<&[f32]> = vectors.iter().map(|v| v.as_slice()).collect();
+
+    assert!(ProductQuantizer::train(&sample, 5, 8, 5).is_err());
+    assert!(ProductQuantizer::train(&sample, 0, 8, 5).is_err());
+    assert!(ProductQuantizer::train(&sample, 4, 257, 5).is_err());
+    assert!(ProductQuantizer::train(&[], 4, 8, 5).is_err());
+
+    let quantizer = ProductQuantizer::train(&sample, 4, 8, 5).unwrap();
+    assert!(quantizer.encode(&[0.0; 8]).is_err());
+}
+
+#[test]
+fn test_distance_table_matches_decoded_vectors() {
+    let vectors = random_vectors(200, 16, 3);
+    let sample: Vec<&[f32]> = vectors.iter().map(|v| v.as_slice()).collect();
+    let quantizer = ProductQuantizer::train(&sample, 8, 32, 10).unwrap();
+    let query = &random_vectors(1, 16, 4)[0];
+
+    let l2_table = quantizer.distance_table(query, ComponentSum::SquaredDifference);
+    let dot_table = quantizer.distance_table(query, ComponentSum::Product);
+    assert_eq!(l2_table.len(), 8 * 32);
+
+    for vector in vectors.iter().take(20) {
+        let codes = quantizer.encode(vector).unwrap();
+        let decoded = quantizer.decode(&codes);
+        let l2: f32 = query.iter().zip(&decoded).map(|(a, b)| (a - b) * (a - b)).sum();
+        let dot: f32 = query.iter().zip(&decoded).map(|(a, b)| a * b).sum();
+        assert!((quantizer.table_sum(&l2_table, &codes) - l2).abs() < 1e-4);
+        assert!((quantizer.table_sum(&dot_table, &codes) - dot).abs() < 1e-4);
+    }
+}
+
+#[test]
+fn test_pq_search_with_rerank() {
+    let vectors = random_vectors(1000, 32, 5);
+    let queries = random_vectors(20, 32, 6);
+
+    for metric in [DistanceMetric::Euclidean, DistanceMetric::Cosine, DistanceMetric::Manhattan] {
+        let mut index = build_index(&vectors, metric);
+        index.quantize_pq(8, 64, 1000, 10, true).unwrap();
+        assert!(index.is_quantized());
+        assert_eq!(index.get_codes("node_0").unwrap().len(), 8);
+
+        // Traversal alone ranks by approximate distances
+        let approximate = recall_at_10(&index, &vectors, &queries);
+        assert!(approximate > 0.3, "{:?} recall@10 without re-ranking was {}", metric, approximate);
+
+        index.rerank_candidates = Some(200);
+        let reranked = recall_at_10(&index, &vectors, &queries);
+        assert!(reranked >= 0.9, "{:?} recall@10 with re-ranking was {}", metric, reranked);
+
+        let results = index.search(&queries[0], 5);
+        let exact = index.distance(&queries[0], &index.get_original_embedding(&results[0].0).unwrap());
+        assert!((results[0].1 - exact).abs() < 1e-5);
+    }
+}
+
+#[test]
+fn test_rerank_from_spilled_vectors() {
+    let index_path = temp_path("index.json");
+    let vector_path = temp_path("vectors.npy");
+    let vectors = random_vectors(300, 16, 7);
+    let mut index = build_index(&vectors, DistanceMetric::Euclidean);
+
+    index.quantize_pq(4, 32, 300, 10, true).unwrap();
+    index.spill_vectors(&vector_path).unwrap();
+    index.rerank_candidates = Some(100);
+    assert!(index.get_embedding("node_5").is_none());
+    assert_eq!(index.dimension(), Some(16));
+
+    // Re-ranked distances are read back from the file at full precision
+    let results = index.search(&vectors[5], 1);
+    assert_eq!(results[0].0, "node_5");
+    assert_eq!(results[0].1, 0.0);
+
+    // Vectors inserted after spilling stay in memory
+    index.insert("late".to_string(), vec![5.0; 16], None).unwrap();
+    assert_eq!(index.get_embedding("late").unwrap(), &[5.0; 16]);
+    assert_eq!(index.search(&[5.0; 16], 1)[0].1, 0.0);
+
+    // Loading an index reopens its vector file
+    index.save(&index_path).unwrap();
+    let loaded: HNSWIndex<String> = HNSWIndex::load(&index_path).unwrap();
+    assert_eq!(loaded.dimension(), Some(16));
+    let results = loaded.search(&vectors[9], 1);
+    assert_eq!(results[0].0, "node_9");
+    assert_eq!(results[0].1, 0.0);
+
+    let (matrix, ids) = loaded.to_matrix().unwrap();
+    let row = ids.iter().position(|id| id == "node_9").unwrap();
+    assert_eq!(matrix.row(row), vectors[9].as_slice());
+
+    std::fs::remove_file(&vector_path).unwrap();
+    assert!(HNSWIndex::<String>::load(&index_path).is_err());
+    std::fs::remove_file(&index_path).unwrap();
+}
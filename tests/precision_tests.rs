@@ -0,0 +1,102 @@
+use swarc::index::HNSWIndex;
+use swarc::precision::HalfFormat;
+use swarc::types::DistanceMetric;
+use std::path::PathBuf;
+
+mod common;
+
+use common::{random_vectors, build_index, recall_at_10};
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("swarc_precision_{}_{}", std::process::id(), name))
+}
+
+#[test]
+fn test_encode_decode_round_trip() {
+    let vector = vec![0.0, 1.0, -2.5, 0.333, 1e-3, 70000.0];
+
+    let f16 = HalfFormat::F16.decode(&HalfFormat::F16.encode(&vector));
+    assert_eq!(&f16[..3], &[0.0, 1.0, -2.5]);
+    assert!((f16[3] - 0.333).abs() < 1e-3);
+    assert!(f16[5].is_infinite(), "f16 overflows past 65504");
+
+    let bf16 = HalfFormat::BF16.decode(&HalfFormat::BF16.encode(&vector));
+    assert_eq!(&bf16[..3], &[0.0, 1.0, -2.5]);
+    assert!((bf16[5] - 70000.0).abs() / 70000.0 < 1e-2, "bf16 keeps f32's range");
+}
+
+#[test]
+fn test_half_precision_search() {
+    let vectors = random_vectors(1000, 32, 1);
+    let queries = random_vectors(20, 32, 2);
+
+    for metric in [DistanceMetric::Euclidean, DistanceMetric::Cosine] {
+        let mut index = build_index(&vectors, metric);
+        let baseline = recall_at_10(&index, &vectors, &queries);
+
+        for format in [HalfFormat::F16, HalfFormat::BF16] {
+            index.set_half_precision(Some(format)).unwrap();
+            assert_eq!(index.half_precision(), Some(format));
+            assert!(index.get_embedding("node_0").is_none());
+            assert_eq!(index.get_half_embedding("node_0").unwrap().len(), 32);
+
+            let recall = recall_at_10(&index, &vectors, &queries);
+            assert!(recall >= baseline - 0.05, "{:?} {:?} recall@10 was {}, f32 was {}", metric, format, recall, baseline);
+        }
+
+        // Switching back restores f32 vectors, rounded through the last format
+        index.set_half_precision(None).unwrap();
+        assert!(index.get_half_embedding("node_3").is_none());
+        let restored = index.get_original_embedding("node_3").unwrap();
+        assert!(restored.iter().zip(&vectors[3]).all(|(a, b)| (a - b).abs() < 1e-2));
+    }
+}
+
+#[test]
+fn test_inserts_and_persistence_keep_half_precision() {
+    let path = temp_path("index.json");
+    let vectors = random_vectors(200, 16, 3);
+    let mut index = build_index(&vectors, DistanceMetric::Euclidean);
+    index.set_half_precision(Some(HalfFormat::F16)).unwrap();
+
+    index.insert("late".to_string(), vec![0.5; 16], None).unwrap();
+    assert!(index.get_embedding("late").is_none());
+    let results = index.search(&[0.5; 16], 1);
+    assert_eq!(results[0].0, "late");
+    assert_eq!(results[0].1, 0.0);
+
+    index.save(&path).unwrap();
+    let loaded: HNSWIndex<String> = HNSWIndex::load(&path).unwrap();
+    assert_eq!(loaded.half_precision(), Some(HalfFormat::F16));
+    assert_eq!(loaded.search(&[0.5; 16], 1)[0].0, "late");
+
+    let (matrix, ids) = loaded.to_matrix().unwrap();
+    let row = ids.iter().position(|id| id == "late").unwrap();
+    assert_eq!(matrix.row(row), &[0.5; 16]);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_half_precision_and_quantization_are_exclusive() {
+    use swarc::quantization::QuantizationRange;
+
+    let vectors = random_vectors(200, 16, 4);
+    let mut index = build_index(&vectors, DistanceMetric::Euclidean);
+    index.set_half_precision(Some(HalfFormat::BF16)).unwrap();
+    assert!(index.quantize_binary(true).is_err());
+    assert!(index.quantize_int8(QuantizationRange::Global, 100, true).is_err());
+    assert!(index.quantize_pq(4, 16, 100, 5, true).is_err());
+    assert_eq!(index.storage_mode(), "BF16");
+
+    // Switching between half formats is allowed, and going back to f32 frees the index
+    index.set_half_precision(Some(HalfFormat::F16)).unwrap();
+    index.set_half_precision(None).unwrap();
+    index.quantize_binary(true).unwrap();
+    assert_eq!(index.storage_mode(), "binary");
+    assert!(index.set_half_precision(Some(HalfFormat::F16)).is_err());
+    assert!(index.quantize_int8(QuantizationRange::Global, 100, true).is_err());
+    assert_eq!(index.half_precision(), None);
+
+    index.rerank_candidates = Some(100);
+    assert_eq!(index.search(&vectors[7], 1)[0].0, "node_7");
+}
@@ -0,0 +1,124 @@
+use swarc::index::HNSWIndex;
+use swarc::quantization::{QuantizationRange, ScalarQuantizer};
+use swarc::types::DistanceMetric;
+
+mod common;
+
+use common::{random_vectors, build_index};
+
+fn exact_top_k(index: &HNSWIndex<String>, vectors: &[Vec<f32>], query: &[f32], k: usize) -> Vec<String> {
+    let mut scored: Vec<(usize, f32)> = vectors.iter().enumerate().map(|(i, v)| (i, index.distance(query, v))).collect();
+    scored.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+    scored.into_iter().take(k).map(|(i, _)| format!("node_{}", i)).collect()
+}
+
+#[test]
+fn test_quantizer_roundtrip_error_is_bounded() {
+    let vectors = random_vectors(200, 16, 1);
+
+    for range in [QuantizationRange::Global, QuantizationRange::PerDimension] {
+        let quantizer = ScalarQuantizer::train(vectors.iter().map(|v| v.as_slice()), range).unwrap();
+        assert_eq!(quantizer.is_per_dimension(), range == QuantizationRange::PerDimension);
+
+        for vector in &vectors {
+            let decoded = quantizer.decode(&quantizer.encode(vector).unwrap());
+            for (d, (x, y)) in vector.iter().zip(decoded.iter()).enumerate() {
+                let step = if quantizer.is_per_dimension() { quantizer.scale[d] } else { quantizer.scale[0] };
+                assert!((x - y).abs() <= step / 2.0 + 1e-6);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_quantizer_clamps_and_validates() {
+    let sample = [vec![0.0, 0.0], vec![1.0, 2.0]];
+    let quantizer = ScalarQuantizer::train(sample.iter().map(|v| v.as_slice()), QuantizationRange::PerDimension).unwrap();
+
+    assert_eq!(quantizer.encode(&[0.0, 2.0]).unwrap(), vec![0, 255]);
+    assert_eq!(quantizer.encode(&[-5.0, 9.0]).unwrap(), vec![0, 255]);
+    assert!(quantizer.encode(&[0.0]).is_err());
+
+    assert!(ScalarQuantizer::train(std::iter::empty(), QuantizationRange::Global).is_err());
+
+    // A constant dimension decodes back to its value
+    let constant = [vec![3.0], vec![3.0]];
+    let quantizer = ScalarQuantizer::train(constant.iter().map(|v| v.as_slice()), QuantizationRange::Global).unwrap();
+    assert_eq!(quantizer.decode(&quantizer.encode(&[3.0]).unwrap()), vec![3.0]);
+}
+
+#[test]
+fn test_quantized_search_with_rerank() {
+    let vectors = random_vectors(500, 32, 2);
+    let mut index = build_index(&vectors, DistanceMetric::Euclidean);
+    index.quantize_int8(QuantizationRange::PerDimension, 200, true).unwrap();
+    index.rerank_candidates = Some(50);
+
+    assert_eq!(index.get_codes("node_0").unwrap().len(), 32);
+    assert_eq!(index.get_embedding("node_0").unwrap(), vectors[0].as_slice());
+
+    let mut hits = 0;
+    for query in random_vectors(20, 32, 3) {
+        let results = index.search(&query, 10);
+        // Re-ranked distances are exact
+        for (id, dist, _) in &results {
+            let exact = index.distance(&query, index.get_embedding(id).unwrap());
+            assert!((dist - exact).abs() < 1e-5);
+        }
+        let expected = exact_top_k(&index, &vectors, &query, 10);
+        hits += results.iter().filter(|(id, _, _)| expected.contains(id)).count();
+    }
+    assert!(hits as f32 / 200.0 >= 0.9, "recall@10 was {}", hits as f32 / 200.0);
+
+    // A NaN component makes every re-ranked distance NaN, which is ordered without panicking
+    let mut query = vectors[0].clone();
+    query[0] = f32::NAN;
+    let results = index.search(&query, 10);
+    assert!(!results.is_empty() && results.iter().all(|(_, dist, _)| dist.is_nan()));
+}
+
+#[test]
+fn test_quantized_only_index() {
+    let vectors = random_vectors(300, 16, 4);
+    let mut index = build_index(&vectors, DistanceMetric::Cosine);
+    index.quantize_int8(QuantizationRange::Global, 1000, false).unwrap();
+    index.rerank_candidates = Some(50);
+
+    assert!(index.quantized_only());
+    assert!(index.get_embedding("node_0").is_none());
+
+    // Vectors inserted later are quantized too
+    index.insert("late".to_string(), vec![0.5; 16], None).unwrap();
+    assert!(index.get_embedding("late").is_none() && index.get_codes("late").is_some());
+
+    let results = index.search(&[1.0; 16], 1);
+    assert_eq!(results[0].0, "late");
+    assert!(results[0].1.abs() < 0.01);
+
+    // Exported vectors are reconstructed from the codes
+    let (matrix, ids) = index.to_matrix().unwrap();
+    let row = ids.iter().position(|id| id == "node_3").unwrap();
+    for (x, y) in matrix.row(row).iter().zip(vectors[3].iter()) {
+        assert!((x - y).abs() < 0.02);
+    }
+
+    // Dropped vectors cannot be quantized again
+    assert!(index.quantize_int8(QuantizationRange::Global, 100, true).is_err());
+}
+
+#[test]
+fn test_quantized_index_is_persisted() {
+    let path = std::env::temp_dir().join(format!("swarc_quantized_{}.json", std::process::id()));
+    let vectors = random_vectors(50, 8, 5);
+    let mut index = build_index(&vectors, DistanceMetric::Euclidean);
+    index.quantize_int8(QuantizationRange::PerDimension, 50, false).unwrap();
+
+    index.save(&path).unwrap();
+    let loaded: HNSWIndex<String> = HNSWIndex::load(&path).unwrap();
+
+    assert_eq!(loaded.scalar_quantizer(), index.scalar_quantizer());
+    assert_eq!(loaded.get_codes("node_7"), index.get_codes("node_7"));
+    assert_eq!(loaded.search(&vectors[7], 1)[0].0, index.search(&vectors[7], 1)[0].0);
+
+    std::fs::remove_file(&path).unwrap();
+}
@@ -120,6 +120,12 @@ fn test_clear_index() {
     assert_eq!(index.len(), 0);
     assert!(index.is_empty());
     assert!(index.get_all_ids().is_empty());
+    assert_eq!(index.dimension(), None);
+    
+    // A cleared index takes vectors of a new dimension
+    index.insert("node_0".to_string(), vec![1.0, 2.0], None).unwrap();
+    assert_eq!(index.dimension(), Some(2));
+    assert_eq!(index.search(&[1.0, 2.0], 1)[0].0, "node_0");
 }
 
 #[test]
@@ -322,3 +328,22 @@ fn test_remove_all_nodes() {
     let results = index.search(&query, 5);
     assert!(results.is_empty());
 }
+
+#[test]
+fn test_remove_keeps_remaining_nodes_reachable() {
+    // With two links per node, points on a line form little more than a chain
+    let mut index: HNSWIndex<()> = HNSWIndex::new(2, 2, 10);
+    for i in 0..60 {
+        index.insert(format!("node_{}", i), vec![i as f32, 0.0], None).unwrap();
+    }
+    
+    for i in (0..60).filter(|i| i % 3 != 0) {
+        index.remove(&format!("node_{}", i)).unwrap();
+    }
+    
+    assert!(index.validate().is_empty(), "{:?}", index.validate());
+    for i in (0..60).step_by(3) {
+        let results = index.search(&[i as f32, 0.0], 1);
+        assert_eq!(results[0].0, format!("node_{}", i));
+    }
+}
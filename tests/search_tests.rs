@@ -1,5 +1,5 @@
 use swarc::index::HNSWIndex;
-use swarc::types::Document;
+use swarc::types::{Document, DistanceMetric};
 
 #[test]
 fn test_search_empty_index() {
@@ -37,7 +37,7 @@ fn test_search_multiple_nodes() {
     let mut index: HNSWIndex<String> = HNSWIndex::new(3, 16, 200);
     
     // Insert nodes with known distances
-    let embeddings = vec![
+    let embeddings = [
         vec![0.0, 0.0, 0.0],  // node0
         vec![1.0, 0.0, 0.0],  // node1 - distance 1 from origin
         vec![2.0, 0.0, 0.0],  // node2 - distance 2 from origin
@@ -125,7 +125,7 @@ fn test_search_layer() {
     }
     
     let query = vec![0.0, 0.0, 0.0];
-    let _entry_points = vec![0, 1, 2];
+    let _entry_points = [0, 1, 2];
     
     // Test search_layer (this is a private method, so we test it indirectly)
     // by checking that search works correctly
@@ -144,7 +144,7 @@ fn test_search_with_zero_query() {
     let mut index: HNSWIndex<String> = HNSWIndex::new(3, 16, 200);
     
     // Insert nodes with different embeddings
-    let embeddings = vec![
+    let embeddings = [
         vec![1.0, 1.0, 1.0],
         vec![2.0, 2.0, 2.0],
         vec![3.0, 3.0, 3.0],
@@ -268,3 +268,90 @@ fn test_search_large_k() {
         assert!(results[i-1].1 <= results[i].1);
     }
 }
+
+#[test]
+fn test_search_inner_product() {
+    let mut index: HNSWIndex<String> = HNSWIndex::new_with_distance(2, 16, 200, DistanceMetric::InnerProduct);
+    
+    // The longest vector pointing along the query wins, even over a closer one
+    index.insert("short".to_string(), vec![1.0, 0.0], None).unwrap();
+    index.insert("long".to_string(), vec![5.0, 1.0], None).unwrap();
+    index.insert("opposite".to_string(), vec![-3.0, 0.0], None).unwrap();
+    index.insert("orthogonal".to_string(), vec![0.0, 2.0], None).unwrap();
+    
+    let results = index.search(&[1.0, 0.0], 4);
+    let ids: Vec<&str> = results.iter().map(|(id, _, _)| id.as_str()).collect();
+    
+    assert_eq!(ids, vec!["long", "short", "orthogonal", "opposite"]);
+    assert!((results[0].1 + 5.0).abs() < 1e-6);
+    assert!((results[3].1 - 3.0).abs() < 1e-6);
+}
+
+#[test]
+fn test_search_cosine_normalized_storage() {
+    let mut index: HNSWIndex<String> = HNSWIndex::new_with_distance(2, 16, 200, DistanceMetric::Cosine);
+    
+    index.insert("a".to_string(), vec![3.0, 4.0], None).unwrap();
+    let items = vec![
+        ("b".to_string(), vec![0.0, 2.0], None),
+        ("c".to_string(), vec![-1.0, 0.0], None),
+    ];
+    index.insert_parallel(items).unwrap();
+    
+    // Embeddings are stored at unit length with their original norm
+    let stored = index.get_embedding("a").unwrap();
+    assert!((stored[0] - 0.6).abs() < 1e-6 && (stored[1] - 0.8).abs() < 1e-6);
+    assert_eq!(index.get_norm("a"), Some(5.0));
+    assert!(index.get_raw_embedding("a").is_none());
+    let original = index.get_original_embedding("a").unwrap();
+    assert!((original[0] - 3.0).abs() < 1e-5 && (original[1] - 4.0).abs() < 1e-5);
+    assert_eq!(index.get_norm("b"), Some(2.0));
+    
+    // Queries of any length give the same cosine distances as the unnormalized vectors
+    let query = [30.0, 0.0];
+    let results = index.search(&query, 3);
+    let ids: Vec<&str> = results.iter().map(|(id, _, _)| id.as_str()).collect();
+    assert_eq!(ids, vec!["a", "b", "c"]);
+    assert!((results[0].1 - index.distance(&query, &[3.0, 4.0])).abs() < 1e-6);
+    assert!((results[1].1 - 1.0).abs() < 1e-6);
+    assert!((results[2].1 - 2.0).abs() < 1e-6);
+}
+
+#[test]
+fn test_search_cosine_keeps_raw_vectors() {
+    let mut index: HNSWIndex<String> = HNSWIndex::new_with_distance(2, 16, 200, DistanceMetric::Cosine);
+    index.keep_raw_vectors = true;
+    
+    index.insert("a".to_string(), vec![3.0, 4.0], None).unwrap();
+    
+    assert_eq!(index.get_raw_embedding("a"), Some(&[3.0, 4.0][..]));
+    assert_eq!(index.get_original_embedding("a").unwrap().as_ref(), &[3.0, 4.0]);
+    
+    // Other metrics leave embeddings untouched
+    let mut euclidean: HNSWIndex<String> = HNSWIndex::new(2, 16, 200);
+    euclidean.keep_raw_vectors = true;
+    euclidean.insert("a".to_string(), vec![3.0, 4.0], None).unwrap();
+    assert_eq!(euclidean.get_embedding("a").unwrap(), &[3.0, 4.0]);
+    assert!(euclidean.get_norm("a").is_none() && euclidean.get_raw_embedding("a").is_none());
+}
+
+#[test]
+fn test_search_ef_search_returns_k_results() {
+    let mut index: HNSWIndex<String> = HNSWIndex::new(2, 16, 200);
+    for i in 0..50 {
+        index.insert(format!("node_{}", i), vec![i as f32, 0.0], None).unwrap();
+    }
+    
+    let baseline: Vec<String> = index.search(&[10.2, 0.0], 3).into_iter().map(|(id, _, _)| id).collect();
+    
+    // A larger candidate list still returns only the k closest, in order
+    index.ef_search = Some(40);
+    let results = index.search(&[10.2, 0.0], 3);
+    let ids: Vec<String> = results.iter().map(|(id, _, _)| id.clone()).collect();
+    assert_eq!(ids, vec!["node_10", "node_11", "node_9"]);
+    assert_eq!(ids, baseline);
+    
+    // A candidate list smaller than k is raised to k
+    index.ef_search = Some(1);
+    assert_eq!(index.search(&[10.2, 0.0], 3).len(), 3);
+}
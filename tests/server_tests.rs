@@ -0,0 +1,255 @@
+// The server modules are compiled into this test so their routes can be called directly
+#[allow(dead_code)]
+#[path = "../server/config.rs"]
+mod config;
+#[allow(dead_code)]
+#[path = "../server/routes.rs"]
+mod routes;
+#[allow(dead_code)]
+#[path = "../server/state.rs"]
+mod state;
+
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use serde_json::{json, Value};
+use swarc::DistanceMetric;
+use config::ServerConfig;
+use routes::{Body, Response};
+use state::{Index, State};
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("swarc_server_{}_{}.json", std::process::id(), name))
+}
+
+fn new_state(name: &str) -> State {
+    State { index: RwLock::new(Index::new(0, 8, 50)), path: temp_path(name) }
+}
+
+fn parse_config(args: &[&str]) -> Result<Option<ServerConfig>, String> {
+    ServerConfig::parse(args.iter().map(|arg| arg.to_string()))
+}
+
+/// Send a request, returning its status and JSON body
+fn request(state: &State, method: &str, path: &str, body: Value) -> (u16, Value) {
+    let response = routes::handle(state, method, path, body.to_string().as_bytes());
+    match response.body {
+        Body::Json(body) => (response.status, body),
+        Body::Metrics(_) => panic!("{} {} returned metrics", method, path),
+    }
+}
+
+fn error_status(result: Result<Vec<u8>, Response>) -> u16 {
+    match result {
+        Ok(_) => panic!("the body should have been rejected"),
+        Err(response) => response.status,
+    }
+}
+
+#[test]
+fn test_server_config_parse() {
+    let config = parse_config(&["--index", "index.json"]).unwrap().unwrap();
+    assert_eq!(config.index, PathBuf::from("index.json"));
+    assert_eq!(config.bind, "127.0.0.1:7070");
+    assert_eq!((config.m, config.ef_construction, config.ef_search), (16, 200, None));
+    assert_eq!(config.metric, DistanceMetric::Euclidean);
+    assert_eq!(config.max_body, 64 << 20);
+    assert!(config.grpc.is_none());
+    assert!(config.threads > 0);
+
+    let config = parse_config(&[
+        "--index=data/index.json", "--bind", "0.0.0.0:8080", "--threads", "3", "--m=8", "--ef-construction", "100",
+        "--metric", "minkowski:3", "--ef-search", "40", "--max-body", "2", "--grpc", "127.0.0.1:7071",
+    ])
+    .unwrap()
+    .unwrap();
+    assert_eq!(config.index, PathBuf::from("data/index.json"));
+    assert_eq!(config.bind, "0.0.0.0:8080");
+    assert_eq!((config.threads, config.m, config.ef_construction, config.ef_search), (3, 8, 100, Some(40)));
+    assert_eq!(config.metric, DistanceMetric::Minkowski(3.0));
+    assert_eq!(config.max_body, 2 << 20);
+    assert_eq!(config.grpc.as_deref(), Some("127.0.0.1:7071"));
+
+    assert!(parse_config(&["--help"]).unwrap().is_none());
+    assert!(parse_config(&["--index", "x", "-h"]).unwrap().is_none());
+
+    let error = |args: &[&str]| parse_config(args).unwrap_err();
+    assert_eq!(error(&[]), "Missing --index");
+    assert_eq!(error(&["--index", "x", "--verbose"]), "Unknown option '--verbose'");
+    assert_eq!(error(&["--index"]), "Missing value for --index");
+    assert_eq!(error(&["--index", "x", "--threads", "0"]), "--threads must be greater than zero");
+    assert_eq!(error(&["--index", "x", "--m", "many"]), "Invalid value 'many' for --m");
+    assert!(error(&["--index", "x", "--metric", "minkowski:-1"]).contains("Minkowski order"));
+}
+
+#[test]
+fn test_routes_serve_every_endpoint() {
+    let state = new_state("endpoints");
+
+    let (status, body) = request(&state, "POST", "/insert", json!({ "id": "a", "vector": [0.0, 0.0], "document": { "title": "A" } }));
+    assert_eq!(status, 200);
+    assert_eq!(body, json!({ "inserted": 1, "errors": [] }));
+
+    let records = json!([
+        { "id": "b", "vector": [1.0, 0.0] },
+        { "id": "c", "vector": [0.0, 5.0] },
+        { "id": "a", "vector": [9.0, 9.0] },
+        { "id": "d", "vector": [1.0, 2.0, 3.0] },
+    ]);
+    let (_, body) = request(&state, "POST", "/insert", records);
+    assert_eq!(body["inserted"], 2);
+    let failed: Vec<&Value> = body["errors"].as_array().unwrap().iter().map(|error| &error["id"]).collect();
+    assert_eq!(failed, [&json!("a"), &json!("d")]);
+    assert!(body.get("replaced").is_none());
+
+    let (status, body) = request(&state, "POST", "/search", json!({ "vector": [0.1, 0.0], "k": 2 }));
+    assert_eq!(status, 200);
+    let results = body["results"].as_array().unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0]["id"], "a");
+    assert_eq!(results[0]["document"], json!({ "title": "A" }));
+    assert_eq!(results[1]["id"], "b");
+
+    // k defaults to DEFAULT_K, more than the index holds
+    let (_, body) = request(&state, "POST", "/search/batch", json!({ "vectors": [[0.0, 5.0], [1.0, 0.1]] }));
+    let results = body["results"].as_array().unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].as_array().unwrap().len(), 3);
+    assert_eq!(results[0][0]["id"], "c");
+    assert_eq!(results[1][0]["id"], "b");
+
+    let (status, body) = request(&state, "POST", "/search/batch", json!({ "vectors": [[0.0, 5.0], [1.0]] }));
+    assert_eq!(status, 400);
+    assert!(body["error"].as_str().unwrap().starts_with("Query 1:"));
+
+    let (_, body) = request(&state, "GET", "/stats", Value::Null);
+    assert_eq!(body["nodes"], 3);
+    assert_eq!(body["dimension"], 2);
+    assert_eq!(body["m"], 8);
+    assert_eq!(body["path"], state.path.display().to_string());
+
+    let (_, body) = request(&state, "POST", "/remove", json!({ "ids": ["b", "missing"] }));
+    assert_eq!(body, json!({ "removed": 1, "missing": ["missing"] }));
+
+    let response = routes::handle(&state, "GET", "/metrics?format=text", b"");
+    assert_eq!(response.status, 200);
+    match response.body {
+        Body::Metrics(text) => {
+            assert!(text.contains("swarc_nodes 2\n"));
+            assert!(text.contains("swarc_removes_total 1"));
+        }
+        Body::Json(_) => panic!("/metrics returned JSON"),
+    }
+
+    assert_eq!(request(&state, "GET", "/search", Value::Null).0, 405);
+    assert_eq!(request(&state, "POST", "/metrics", Value::Null).0, 405);
+    assert_eq!(request(&state, "GET", "/nowhere", Value::Null).0, 404);
+    let response = routes::handle(&state, "POST", "/insert", b"{not json");
+    assert_eq!(response.status, 400);
+    assert_eq!(request(&state, "POST", "/search", json!({ "k": 2 })).0, 400);
+}
+
+#[test]
+fn test_routes_upsert_replaces_nodes() {
+    let state = new_state("upsert");
+    request(&state, "POST", "/insert", json!([{ "id": "a", "vector": [0.0, 0.0] }, { "id": "b", "vector": [5.0, 5.0] }]));
+
+    let (_, body) = request(&state, "POST", "/upsert", json!([{ "id": "a", "vector": [10.0, 10.0], "document": 1 }, { "id": "new", "vector": [1.0, 1.0] }]));
+    assert_eq!(body, json!({ "inserted": 2, "replaced": 1, "errors": [] }));
+
+    let (_, body) = request(&state, "POST", "/search", json!({ "vector": [10.0, 10.0], "k": 1 }));
+    assert_eq!(body["results"][0]["id"], "a");
+    assert_eq!(body["results"][0]["document"], 1);
+    assert_eq!(body["results"][0]["distance"], 0.0);
+
+    // A record that cannot be inserted leaves the node it would replace
+    let (_, body) = request(&state, "POST", "/upsert", json!({ "id": "a", "vector": [1.0, 2.0, 3.0] }));
+    assert_eq!(body["inserted"], 0);
+    assert_eq!(body["replaced"], 0);
+    assert!(state.index.read().unwrap().contains("a"));
+    assert_eq!(state.index.read().unwrap().len(), 3);
+}
+
+#[test]
+fn test_routes_reject_bad_vectors() {
+    let state = new_state("vectors");
+
+    // 1e39 overflows f32, and so arrives as infinity
+    let (_, body) = request(&state, "POST", "/insert", json!([{ "id": "inf", "vector": [1e39, 0.0] }, { "id": "empty", "vector": [] }]));
+    assert_eq!(body["inserted"], 0);
+    assert_eq!(body["errors"][0]["error"], "Vector has a non-finite value");
+    assert_eq!(body["errors"][1]["error"], "Vector is empty");
+    assert!(state.index.read().unwrap().is_empty());
+
+    request(&state, "POST", "/insert", json!({ "id": "a", "vector": [0.0, 0.0] }));
+    let (status, body) = request(&state, "POST", "/search", json!({ "vector": [-1e39, 0.0] }));
+    assert_eq!(status, 400);
+    assert_eq!(body["error"], "Vector has a non-finite value");
+    let (status, body) = request(&state, "POST", "/search", json!({ "vector": [0.0] }));
+    assert_eq!(status, 400);
+    assert_eq!(body["error"], "Vector has dimension 1, index expects 2");
+}
+
+#[test]
+fn test_read_body_enforces_the_limit() {
+    let body = routes::read_body(Cursor::new(vec![1u8; 100]), Some(100), 100).unwrap();
+    assert_eq!(body.len(), 100);
+    let body = routes::read_body(Cursor::new(vec![1u8; 10]), None, 100).unwrap();
+    assert_eq!(body.len(), 10);
+
+    // A declared length over the limit is refused before reading
+    assert_eq!(error_status(routes::read_body(Cursor::new(Vec::new()), Some(101), 100)), 413);
+    // A body without a declared length, or longer than declared, is cut off past the limit
+    assert_eq!(error_status(routes::read_body(Cursor::new(vec![1u8; 101]), None, 100)), 413);
+    assert_eq!(error_status(routes::read_body(Cursor::new(vec![1u8; 500]), Some(10), 100)), 413);
+}
+
+#[test]
+fn test_snapshot_replaces_the_index_file() {
+    let state = new_state("snapshot");
+    request(&state, "POST", "/insert", json!([{ "id": "a", "vector": [0.0, 1.0] }, { "id": "b", "vector": [1.0, 0.0] }]));
+    std::fs::write(&state.path, "old snapshot").unwrap();
+
+    let (status, body) = request(&state, "POST", "/snapshot", Value::Null);
+    assert_eq!(status, 200);
+    assert_eq!(body["nodes"], 2);
+
+    let mut temporary = state.path.clone().into_os_string();
+    temporary.push(".tmp");
+    assert!(!PathBuf::from(temporary).exists());
+    let loaded = Index::load(&state.path).unwrap();
+    assert_eq!(loaded.len(), 2);
+    assert!(loaded.contains("a") && loaded.contains("b"));
+    std::fs::remove_file(&state.path).unwrap();
+
+    // A snapshot that cannot be written is reported
+    let state = State { index: RwLock::new(Index::new(0, 8, 50)), path: temp_path("missing_dir").join("index.json") };
+    let (status, body) = request(&state, "POST", "/snapshot", Value::Null);
+    assert_eq!(status, 500);
+    assert!(body["error"].as_str().is_some());
+    assert!(!state.path.exists());
+}
+
+#[test]
+fn test_routes_recover_from_a_poisoned_lock() {
+    let state = new_state("poisoned");
+    request(&state, "POST", "/insert", json!([{ "id": "a", "vector": [0.0, 1.0] }, { "id": "b", "vector": [1.0, 0.0] }]));
+
+    // A handler that panics while holding the lock poisons it
+    std::thread::scope(|scope| {
+        let panicked = scope.spawn(|| {
+            let _index = state.index.write().unwrap();
+            panic!("handler failed");
+        });
+        assert!(panicked.join().is_err());
+    });
+    assert!(state.index.is_poisoned());
+
+    let (status, body) = request(&state, "POST", "/search", json!({ "vector": [1.0, 0.0], "k": 1 }));
+    assert_eq!(status, 200);
+    assert_eq!(body["results"][0]["id"], "b");
+    let (_, body) = request(&state, "POST", "/insert", json!({ "id": "c", "vector": [1.0, 1.0] }));
+    assert_eq!(body["inserted"], 1);
+    assert_eq!(request(&state, "POST", "/snapshot", Value::Null).1["nodes"], 3);
+    std::fs::remove_file(&state.path).unwrap();
+}
@@ -0,0 +1,117 @@
+use swarc::simd::{self, SimdBackend};
+use rand::Rng;
+
+const BACKENDS: [SimdBackend; 4] = [SimdBackend::Scalar, SimdBackend::Avx2, SimdBackend::Avx512, SimdBackend::Neon];
+
+fn random_vector(len: usize) -> Vec<f32> {
+    let mut rng = rand::thread_rng();
+    (0..len).map(|_| rng.gen_range(-1.0..1.0)).collect()
+}
+
+fn reference_dot(a: &[f32], b: &[f32]) -> f64 {
+    a.iter().zip(b.iter()).map(|(x, y)| *x as f64 * *y as f64).sum()
+}
+
+fn assert_close(actual: f32, expected: f64, context: &str) {
+    let tolerance = 1e-4 * expected.abs().max(1.0);
+    assert!((actual as f64 - expected).abs() < tolerance, "{}: got {}, expected {}", context, actual, expected);
+}
+
+#[test]
+fn test_detected_backend_is_available() {
+    let backend = simd::backend();
+    assert!(backend.is_available());
+    assert_eq!(backend, SimdBackend::detect());
+    assert!(SimdBackend::Scalar.is_available());
+}
+
+#[test]
+fn test_kernels_match_reference_for_all_lengths() {
+    // Cover empty vectors, lengths below one register and every tail length
+    for len in (0..70).chain([1536, 3072]) {
+        let a = random_vector(len);
+        let b = random_vector(len);
+
+        let diff: Vec<f32> = a.iter().zip(b.iter()).map(|(x, y)| x - y).collect();
+        let expected_l2 = reference_dot(&diff, &diff);
+        let expected_dot = reference_dot(&a, &b);
+        let (norm_a, norm_b) = (reference_dot(&a, &a).sqrt(), reference_dot(&b, &b).sqrt());
+        let expected_cosine = if len == 0 { 1.0 } else { 1.0 - expected_dot / (norm_a * norm_b) };
+
+        for backend in BACKENDS.iter().filter(|backend| backend.is_available()) {
+            let context = format!("{:?} with length {}", backend, len);
+            assert_close(backend.l2_squared(&a, &b), expected_l2, &context);
+            assert_close(backend.dot(&a, &b), expected_dot, &context);
+            assert_close(backend.cosine_distance(&a, &b), expected_cosine, &context);
+        }
+    }
+}
+
+#[test]
+fn test_kernels_use_shorter_length() {
+    // Like zip, mismatched lengths only compare the common prefix
+    let a = vec![1.0; 20];
+    let b = vec![2.0; 17];
+
+    for backend in BACKENDS.iter().filter(|backend| backend.is_available()) {
+        assert_eq!(backend.l2_squared(&a, &b), 17.0);
+        assert_eq!(backend.dot(&a, &b), 34.0);
+    }
+}
+
+#[test]
+fn test_cosine_with_zero_vector() {
+    let zero = vec![0.0; 40];
+    let other = random_vector(40);
+
+    for backend in BACKENDS.iter().filter(|backend| backend.is_available()) {
+        assert_eq!(backend.cosine_distance(&zero, &other), 1.0);
+        assert_eq!(backend.cosine_distance(&zero, &zero), 1.0);
+    }
+}
+
+#[test]
+fn test_unavailable_backend_falls_back_to_scalar() {
+    let a = random_vector(33);
+    let b = random_vector(33);
+    let expected = SimdBackend::Scalar.dot(&a, &b);
+
+    for backend in BACKENDS.iter().filter(|backend| !backend.is_available()) {
+        assert_eq!(backend.dot(&a, &b), expected);
+    }
+}
+
+#[test]
+fn test_hamming_matches_reference() {
+    let mut rng = rand::thread_rng();
+    for len in 0..40 {
+        let a: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+        let b: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+        let expected: u32 = a.iter().zip(&b).map(|(x, y)| (x ^ y).count_ones()).sum();
+
+        for backend in BACKENDS.iter() {
+            assert_eq!(backend.hamming(&a, &b), expected);
+        }
+        assert_eq!(simd::hamming(&a, &b), expected);
+    }
+}
+
+#[test]
+fn test_half_conversion_matches_reference() {
+    for len in (0..40).chain([1536]) {
+        let values = random_vector(len);
+        let halves: Vec<u16> = values.iter().map(|&x| half::f16::from_f32(x).to_bits()).collect();
+        let expected: Vec<f32> = halves.iter().map(|&bits| half::f16::from_bits(bits).to_f32()).collect();
+
+        let mut out = vec![7.0; 3];
+        for backend in BACKENDS.iter().filter(|backend| backend.is_available()) {
+            backend.f16_to_f32(&halves, &mut out);
+            assert_eq!(out, expected, "{:?} at length {}", backend, len);
+        }
+
+        let brains: Vec<u16> = values.iter().map(|&x| half::bf16::from_f32(x).to_bits()).collect();
+        simd::bf16_to_f32(&brains, &mut out);
+        let expected: Vec<f32> = brains.iter().map(|&bits| half::bf16::from_bits(bits).to_f32()).collect();
+        assert_eq!(out, expected);
+    }
+}
@@ -1,4 +1,4 @@
-use swarc::types::{Document, HNSWNode};
+use swarc::types::{Document, DistanceMetric, HNSWNode};
 
 #[test]
 fn test_document_creation() {
@@ -35,45 +35,37 @@ fn test_document_with_different_types() {
 
 #[test]
 fn test_hnsw_node_creation() {
-    let embedding = vec![1.0, 2.0, 3.0];
     let doc = Document {
         id: "test_doc".to_string(),
         data: "test content".to_string(),
     };
     
-    let node = HNSWNode::new("node1".to_string(), embedding.clone(), Some(doc.clone()));
+    let node = HNSWNode::new("node1".to_string(), Some(doc.clone()));
     
     assert_eq!(node.id, "node1");
-    assert_eq!(node.embedding, embedding);
     assert_eq!(node.document.as_ref().unwrap().id, "test_doc");
     assert_eq!(node.document.as_ref().unwrap().data, "test content");
-    assert!(node.connections.is_empty());
 }
 
 #[test]
 fn test_hnsw_node_without_document() {
-    let embedding = vec![1.0, 2.0, 3.0];
-    let node: HNSWNode<String> = HNSWNode::new("node1".to_string(), embedding.clone(), None);
+    let node: HNSWNode<String> = HNSWNode::new("node1".to_string(), None);
     
     assert_eq!(node.id, "node1");
-    assert_eq!(node.embedding, embedding);
     assert!(node.document.is_none());
-    assert!(node.connections.is_empty());
 }
 
 #[test]
 fn test_hnsw_node_clone() {
-    let embedding = vec![1.0, 2.0, 3.0];
     let doc = Document {
         id: "test_doc".to_string(),
         data: "test content".to_string(),
     };
     
-    let node1 = HNSWNode::new("node1".to_string(), embedding.clone(), Some(doc.clone()));
+    let node1 = HNSWNode::new("node1".to_string(), Some(doc.clone()));
     let node2 = node1.clone();
     
     assert_eq!(node1.id, node2.id);
-    assert_eq!(node1.embedding, node2.embedding);
     assert_eq!(node1.document.as_ref().unwrap().id, node2.document.as_ref().unwrap().id);
     assert_eq!(node1.document.as_ref().unwrap().data, node2.document.as_ref().unwrap().data);
 }
@@ -121,3 +113,14 @@ fn test_document_with_complex_data() {
     assert_eq!(doc.id, "complex_doc");
     assert_eq!(doc.data, complex_data);
 }
+
+#[test]
+fn test_distance_metric_from_str() {
+    assert_eq!("euclidean".parse::<DistanceMetric>(), Ok(DistanceMetric::Euclidean));
+    assert_eq!("Cosine".parse::<DistanceMetric>(), Ok(DistanceMetric::Cosine));
+    assert_eq!("ip".parse::<DistanceMetric>(), Ok(DistanceMetric::InnerProduct));
+    assert_eq!("l1".parse::<DistanceMetric>(), Ok(DistanceMetric::Manhattan));
+    assert_eq!("minkowski:3".parse::<DistanceMetric>(), Ok(DistanceMetric::Minkowski(3.0)));
+    assert!("minkowski:-1".parse::<DistanceMetric>().is_err());
+    assert!("hamming".parse::<DistanceMetric>().is_err());
+}
@@ -0,0 +1,75 @@
+use swarc::index::HNSWIndex;
+use rand::Rng;
+
+fn random_index(count: usize, dim: usize) -> HNSWIndex<()> {
+    let mut rng = rand::thread_rng();
+    let mut index = HNSWIndex::new(dim, 8, 50);
+    for i in 0..count {
+        let embedding: Vec<f32> = (0..dim).map(|_| rng.gen_range(-1.0..1.0)).collect();
+        index.insert(format!("node_{}", i), embedding, None).unwrap();
+    }
+    index
+}
+
+#[test]
+fn test_built_index_is_valid() {
+    let mut index = random_index(300, 8);
+    assert_eq!(index.validate(), Vec::<String>::new());
+
+    for i in (0..300).step_by(7) {
+        index.remove(&format!("node_{}", i)).unwrap();
+    }
+    assert_eq!(index.validate(), Vec::<String>::new());
+
+    let counts = index.layer_counts();
+    assert_eq!(counts[0], index.len());
+    assert!(counts.windows(2).all(|pair| pair[0] >= pair[1]));
+}
+
+#[test]
+fn test_validate_reports_a_bad_entry_point() {
+    let mut index = random_index(200, 4);
+    let counts = index.layer_counts();
+    assert!(counts.len() > 1);
+
+    // Any node that is only on layer 0 is below the top of the graph
+    let bottom = index
+        .get_all_ids()
+        .iter()
+        .position(|id| index.get_connections(id).unwrap().len() == 1)
+        .unwrap();
+    index.entry_point = Some(bottom);
+    let problems = index.validate();
+    assert_eq!(problems.len(), 1);
+    assert!(problems[0].contains("Entry point"));
+
+    index.entry_point = Some(500);
+    assert!(index.validate()[0].contains("out of range"));
+
+    index.entry_point = None;
+    assert!(!index.validate().is_empty());
+    assert!(HNSWIndex::<()>::new(4, 8, 50).validate().is_empty());
+}
+
+#[test]
+fn test_validate_reports_unlinked_nodes() {
+    // An index saved before the graph arena, whose nodes carry their own (empty) links
+    let path = std::env::temp_dir().join(format!("swarc_validate_{}_unlinked.json", std::process::id()));
+    let node = |id: &str, x: f32| format!(r#"{{"id": "{}", "document": null, "embedding": [{}, 0.0], "connections": [[]]}}"#, id, x);
+    let json = format!(
+        r#"{{"nodes": [{}, {}, {}], "max_layers": 10, "m": 8, "m_max": 8, "ef_construction": 50, "ml": 1.44, "entry_point": 0, "distance_metric": "Euclidean"}}"#,
+        node("a", 0.0), node("b", 1.0), node("c", 2.0)
+    );
+    std::fs::write(&path, json).unwrap();
+    let index: HNSWIndex<()> = HNSWIndex::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let problems = index.validate();
+    assert_eq!(problems.len(), 1);
+    assert!(problems[0].contains("3 of 3 nodes have no links on layer 0"), "{}", problems[0]);
+
+    // A single node has nothing to link to
+    let mut single = HNSWIndex::<()>::new(2, 8, 50);
+    single.insert("a".to_string(), vec![0.0, 0.0], None).unwrap();
+    assert!(single.validate().is_empty());
+}